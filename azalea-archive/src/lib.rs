@@ -0,0 +1,56 @@
+use azalea_bridge::{ClientSide, PlatformBuilder};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+mod query;
+pub use query::{Archive, Query};
+
+mod record;
+pub use record::{ArchivedEvent, Record};
+
+mod writer;
+
+/// Where transcripts are written.
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    pub directory: PathBuf,
+    // Also write plain text transcripts next to the JSON Lines ones
+    pub plain_text: bool,
+}
+
+impl ArchiveConfig {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            plain_text: true,
+        }
+    }
+
+    // Only write JSON Lines transcripts
+    pub fn without_plain_text(mut self) -> Self {
+        self.plain_text = false;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchivePlugin {
+    writer: Arc<Mutex<writer::Writer>>,
+}
+
+impl ArchivePlugin {
+    /// Writes every Minecraft message to a transcript for each day.
+    ///
+    /// Register the plugin with a `BridgeHub` to also archive messages from other platforms.
+    pub async fn new(config: ArchiveConfig, ignore_list: Vec<&str>) -> ClientSide<ArchivePlugin> {
+        let plugin = ArchivePlugin {
+            writer: Arc::new(Mutex::new(writer::Writer::new(config))),
+        };
+
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .build()
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use log::warn;
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+use crate::Record;
+
+/// Reads the transcripts written by an `ArchivePlugin`.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    directory: PathBuf,
+}
+
+/// Which records to find, everything by default.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    player: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl Archive {
+    pub fn open(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    // Every record matching the query, oldest first
+    pub fn search(&self, query: &Query) -> anyhow::Result<Vec<Record>> {
+        let mut days = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "jsonl")
+            {
+                continue;
+            }
+            let Some(day) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").ok())
+            else {
+                continue;
+            };
+            if query.includes_day(day) {
+                days.push((day, path));
+            }
+        }
+        days.sort();
+
+        let mut records = Vec::new();
+        for (_, path) in days {
+            for (number, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+                let line = line?;
+                match serde_json::from_str::<Record>(&line) {
+                    Ok(record) if query.matches(&record) => records.push(record),
+                    Ok(_) => {}
+                    // Lines can be cut short if the bot stopped while writing
+                    Err(e) => warn!("Skipping {}:{}: {e}", path.display(), number + 1),
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Only messages sent by a player, by name or id, like a Minecraft uuid
+    pub fn player(mut self, player: &str) -> Self {
+        self.player = Some(player.to_string());
+        self
+    }
+
+    // Only records archived at or after a time
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    // Only records archived before a time
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    fn includes_day(&self, day: NaiveDate) -> bool {
+        let after_start = self.since.map_or(true, |since| day >= since.date_naive());
+        let before_end = self.until.map_or(true, |until| day <= until.date_naive());
+        after_start && before_end
+    }
+
+    fn matches(&self, record: &Record) -> bool {
+        let time = record.datetime();
+        let too_early = self.since.map_or(false, |since| time < since);
+        let too_late = self.until.map_or(false, |until| time >= until);
+        if too_early || too_late {
+            return false;
+        }
+
+        match &self.player {
+            Some(player) => record.message().map_or(false, |message| {
+                message.author.id.eq_ignore_ascii_case(player)
+                    || message.author.name.eq_ignore_ascii_case(player)
+            }),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{writer::Writer, ArchiveConfig, ArchivedEvent};
+    use azalea_bridge::{Author, BridgeMessage, MessageId};
+    use std::io::Write;
+
+    const NOTCH: &str = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn chat(time: &str, author: Author, content: &str) -> Record {
+        let id = MessageId::new("minecraft", content);
+        let message = BridgeMessage::new(id, String::new(), author, content.to_string());
+        let mut record = Record::new(ArchivedEvent::Chat {
+            message,
+            channels: Vec::new(),
+        });
+        record.time = at(time).timestamp_millis() as u64;
+        record
+    }
+
+    // Write an archive to its own directory
+    fn archive(name: &str, records: &[Record]) -> (Archive, PathBuf) {
+        let directory =
+            std::env::temp_dir().join(format!("azalea-archive-{name}-{}", std::process::id()));
+        drop(fs::remove_dir_all(&directory));
+        let mut writer = Writer::new(ArchiveConfig::new(&directory).without_plain_text());
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        writer.flush().unwrap();
+        (Archive::open(&directory), directory)
+    }
+
+    fn contents(records: &[Record]) -> Vec<&str> {
+        let messages = records.iter().filter_map(Record::message);
+        messages.map(|message| message.content.as_str()).collect()
+    }
+
+    #[test]
+    fn finds_players_by_name_or_uuid() {
+        let (archive, directory) = archive(
+            "player",
+            &[
+                chat("2023-03-16T10:00:00Z", Author::new(NOTCH, "Notch"), "hello"),
+                chat("2023-03-16T10:01:00Z", Author::new("1234", "Alice"), "hi"),
+                chat(
+                    "2023-03-17T10:00:00Z",
+                    Author::new(NOTCH, "Notch2"),
+                    "renamed",
+                ),
+            ],
+        );
+
+        let by_name = archive.search(&Query::new().player("notch")).unwrap();
+        assert_eq!(contents(&by_name), ["hello"]);
+        let by_uuid = archive.search(&Query::new().player(NOTCH)).unwrap();
+        assert_eq!(contents(&by_uuid), ["hello", "renamed"]);
+        let everyone = archive.search(&Query::new()).unwrap();
+        assert_eq!(contents(&everyone), ["hello", "hi", "renamed"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn until_is_exclusive() {
+        let notch = || Author::new(NOTCH, "Notch");
+        let (archive, directory) = archive(
+            "time",
+            &[
+                chat("2023-03-16T23:59:59Z", notch(), "one"),
+                chat("2023-03-17T00:00:00Z", notch(), "two"),
+                chat("2023-03-17T12:00:00Z", notch(), "three"),
+                chat("2023-03-18T00:00:00Z", notch(), "four"),
+            ],
+        );
+
+        let query = Query::new()
+            .since(at("2023-03-17T00:00:00Z"))
+            .until(at("2023-03-18T00:00:00Z"));
+        assert_eq!(contents(&archive.search(&query).unwrap()), ["two", "three"]);
+
+        let query = Query::new().until(at("2023-03-17T12:00:00Z"));
+        let until = archive.search(&query).unwrap();
+        assert_eq!(contents(&until), ["one", "two"]);
+
+        let query = Query::new().since(at("2023-03-17T12:00:00.001Z"));
+        assert_eq!(contents(&archive.search(&query).unwrap()), ["four"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn skips_truncated_lines() {
+        let notch = || Author::new(NOTCH, "Notch");
+        let first = chat("2023-03-16T10:00:00Z", notch(), "first");
+        let (archive, directory) = archive("truncated", &[first]);
+
+        // The bot stopped while writing a line, then carried on after a restart
+        let path = directory.join("2023-03-16.jsonl");
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        let last = chat("2023-03-16T10:02:00Z", notch(), "last");
+        let line = serde_json::to_string(&last).unwrap();
+        write!(file, "{}\n{line}\n", &line[..line.len() / 2]).unwrap();
+        drop(file);
+
+        let records = archive.search(&Query::new()).unwrap();
+        assert_eq!(contents(&records), ["first", "last"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn ignores_other_files() {
+        let notch = || Author::new(NOTCH, "Notch");
+        let (archive, directory) = archive("other", &[chat("2023-03-16T10:00:00Z", notch(), "hi")]);
+        fs::write(directory.join("notes.jsonl"), "not a day").unwrap();
+        fs::write(directory.join("2023-03-16.txt"), "[10:00:00] not json").unwrap();
+
+        assert_eq!(contents(&archive.search(&Query::new()).unwrap()), ["hi"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
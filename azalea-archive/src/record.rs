@@ -0,0 +1,154 @@
+use azalea_bridge::{BridgeMessage, MessageId, MINECRAFT_PLATFORM};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A line of a JSON Lines transcript.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    // When the event was archived, in milliseconds since the Unix epoch
+    pub time: u64,
+    pub event: ArchivedEvent,
+}
+
+/// Something that happened in chat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ArchivedEvent {
+    // The channels are empty if the message was sent to every channel
+    Chat {
+        message: BridgeMessage,
+        channels: Vec<String>,
+    },
+    Edit {
+        message: BridgeMessage,
+    },
+    Delete {
+        id: MessageId,
+    },
+    Notice {
+        channel: String,
+        content: String,
+    },
+}
+
+impl Record {
+    pub fn new(event: ArchivedEvent) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Self {
+            time: time as u64,
+            event,
+        }
+    }
+
+    pub fn datetime(&self) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.time as i64)
+            .single()
+            .unwrap_or_default()
+    }
+
+    // The message the event is about, if any
+    pub fn message(&self) -> Option<&BridgeMessage> {
+        match &self.event {
+            ArchivedEvent::Chat { message, .. } | ArchivedEvent::Edit { message } => Some(message),
+            ArchivedEvent::Delete { .. } | ArchivedEvent::Notice { .. } => None,
+        }
+    }
+
+    // A line of a plain text transcript, with line breaks escaped
+    pub fn plain_text(&self) -> String {
+        let time = self.datetime().format("%H:%M:%S");
+        match &self.event {
+            ArchivedEvent::Chat { message, .. } => {
+                format!(
+                    "[{time}] {} {}",
+                    sender(message),
+                    one_line(&message.content)
+                )
+            }
+            ArchivedEvent::Edit { message } => format!(
+                "[{time}] {} edited: {}",
+                sender(message),
+                one_line(&message.content)
+            ),
+            ArchivedEvent::Delete { id } => format!(
+                "[{time}] [{}] Message {} deleted",
+                one_line(&id.platform),
+                one_line(&id.id)
+            ),
+            ArchivedEvent::Notice { channel, content } => format!(
+                "[{time}] Notice to {}: {}",
+                one_line(channel),
+                one_line(content)
+            ),
+        }
+    }
+}
+
+// Who sent a message, with their id so players who change names can be found
+fn sender(message: &BridgeMessage) -> String {
+    let platform = one_line(&message.id.platform);
+    let (name, id) = (one_line(&message.author.name), one_line(&message.author.id));
+    if platform == MINECRAFT_PLATFORM || message.channel.is_empty() {
+        format!("[{platform}] {name} ({id})")
+    } else {
+        let channel = one_line(&message.channel);
+        format!("[{platform}/{channel}] {name} ({id})")
+    }
+}
+
+// Escape line breaks and other control characters, and the backslashes that escape them
+fn one_line(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push(c),
+            // Some editors also break lines at these
+            '\u{2028}' | '\u{2029}' => result.extend(c.escape_unicode()),
+            c if c.is_control() => result.extend(c.escape_unicode()),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::Author;
+
+    fn chat(content: &str) -> Record {
+        let id = MessageId::new("discord", "1");
+        let author = Author::new("1234", "Alice\nBob");
+        let message = BridgeMessage::new(id, "general".to_string(), author, content.to_string());
+        let mut record = Record::new(ArchivedEvent::Chat {
+            message,
+            channels: Vec::new(),
+        });
+        record.time = 1678960496123;
+        record
+    }
+
+    #[test]
+    fn plain_text_is_one_line() {
+        let record = chat("first\nsecond\r\nthird");
+        assert_eq!(
+            record.plain_text(),
+            "[09:54:56] [discord/general] Alice\\nBob (1234) first\\nsecond\\r\\nthird"
+        );
+    }
+
+    #[test]
+    fn one_line_escapes() {
+        assert_eq!(one_line("a\\nb"), "a\\\\nb");
+        assert_eq!(one_line("tab\tbell\u{7}"), "tab\tbell\\u{7}");
+        assert_eq!(one_line("a\u{2028}b"), "a\\u{2028}b");
+        assert_eq!(one_line("plain"), "plain");
+    }
+}
@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+use azalea_bridge::{AzaleaEvent, BridgePlatform, Capabilities, PluginEvent};
+use chrono::NaiveDate;
+use flume::Sender;
+use log::info;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use crate::{ArchiveConfig, ArchivePlugin, ArchivedEvent, Record};
+
+pub(crate) const PLATFORM: &str = "archive";
+
+// Appends records to the transcripts of the current day
+#[derive(Debug)]
+pub(crate) struct Writer {
+    config: ArchiveConfig,
+    // The day the open files are for
+    day: Option<NaiveDate>,
+    jsonl: Option<File>,
+    text: Option<File>,
+}
+
+#[async_trait]
+impl BridgePlatform for ArchivePlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    // Everything is written down as it was sent
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
+    // Nothing is sent to Minecraft
+    async fn start(&self, _tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let writer = self.writer.lock().unwrap();
+        fs::create_dir_all(&writer.config.directory)?;
+        info!("Archiving chat to {}", writer.config.directory.display());
+        Ok(())
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        let event = match event {
+            AzaleaEvent::Chat(message, channels) => ArchivedEvent::Chat { message, channels },
+            AzaleaEvent::Edit(message, _) => ArchivedEvent::Edit { message },
+            AzaleaEvent::Delete(id) => ArchivedEvent::Delete { id },
+            AzaleaEvent::Notice(content, channel) => ArchivedEvent::Notice { channel, content },
+        };
+        self.writer.lock().unwrap().write(&Record::new(event))
+    }
+
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        self.writer.lock().unwrap().flush()
+    }
+}
+
+impl Writer {
+    pub fn new(config: ArchiveConfig) -> Self {
+        Self {
+            config,
+            day: None,
+            jsonl: None,
+            text: None,
+        }
+    }
+
+    pub fn write(&mut self, record: &Record) -> anyhow::Result<()> {
+        // Start new files at midnight
+        let day = record.datetime().date_naive();
+        if self.day != Some(day) {
+            self.rotate(day)?;
+        }
+
+        if let Some(jsonl) = &mut self.jsonl {
+            writeln!(jsonl, "{}", serde_json::to_string(record)?)?;
+        }
+        if let Some(text) = &mut self.text {
+            writeln!(text, "{}", record.plain_text())?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        for file in [&mut self.jsonl, &mut self.text].into_iter().flatten() {
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self, day: NaiveDate) -> anyhow::Result<()> {
+        self.flush()?;
+        fs::create_dir_all(&self.config.directory)?;
+
+        let name = day.format("%Y-%m-%d").to_string();
+        let directory = &self.config.directory;
+        self.jsonl = Some(append(&directory.join(format!("{name}.jsonl")))?);
+        self.text = match self.config.plain_text {
+            true => Some(append(&directory.join(format!("{name}.txt")))?),
+            false => None,
+        };
+        self.day = Some(day);
+        Ok(())
+    }
+}
+
+fn append(path: &Path) -> anyhow::Result<File> {
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ArchivedEvent;
+    use chrono::{DateTime, Utc};
+
+    fn notice(time: &str, content: &str) -> Record {
+        let mut record = Record::new(ArchivedEvent::Notice {
+            channel: "general".to_string(),
+            content: content.to_string(),
+        });
+        record.time = time.parse::<DateTime<Utc>>().unwrap().timestamp_millis() as u64;
+        record
+    }
+
+    #[test]
+    fn starts_new_files_at_midnight() {
+        let directory = std::env::temp_dir().join(format!("azalea-archive-{}", std::process::id()));
+        drop(fs::remove_dir_all(&directory));
+        let mut writer = Writer::new(ArchiveConfig::new(&directory));
+
+        writer
+            .write(&notice("2023-03-16T23:59:59.999Z", "before"))
+            .unwrap();
+        writer
+            .write(&notice("2023-03-17T00:00:00Z", "after"))
+            .unwrap();
+        writer
+            .write(&notice("2023-03-17T00:00:01Z", "later"))
+            .unwrap();
+        writer.flush().unwrap();
+
+        let read = |name: &str| fs::read_to_string(directory.join(name)).unwrap();
+        assert_eq!(read("2023-03-16.jsonl").lines().count(), 1);
+        assert_eq!(read("2023-03-17.jsonl").lines().count(), 2);
+        assert_eq!(
+            read("2023-03-16.txt"),
+            "[23:59:59] Notice to general: before\n"
+        );
+        assert_eq!(
+            read("2023-03-17.txt"),
+            "[00:00:00] Notice to general: after\n[00:00:01] Notice to general: later\n"
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use crate::{Author, MINECRAFT_PLATFORM};
+
+/// The uuid server messages are sent with, since they aren't from a player.
+pub const NIL_UUID: &str = "00000000-0000-0000-0000-000000000000";
+
+// Player heads from Crafatar, by uuid
+pub const DEFAULT_AVATAR_URL: &str = "https://crafatar.com/avatars/{uuid}?size=128&overlay";
+
+// How many players to remember the avatars of
+const AVATAR_CAPACITY: usize = 2048;
+
+// Characters that can be left alone in part of a url
+const URL_SAFE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Head renders for Minecraft players, filled in from a url template.
+#[derive(Debug, Clone)]
+pub struct Avatars {
+    // No avatars are sent for players if not set
+    template: Option<String>,
+    // So players keep their head when their skin isn't known
+    resolved: Arc<Mutex<Resolved>>,
+}
+
+// Urls by player uuid, forgetting the players seen least recently
+#[derive(Debug, Default)]
+struct Resolved {
+    urls: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl Avatars {
+    pub fn new(template: Option<&str>) -> Self {
+        Self {
+            template: template.map(str::to_string),
+            resolved: Arc::default(),
+        }
+    }
+
+    // The avatar to send a message with
+    pub fn url(&self, author: &Author, platform: &str) -> Option<String> {
+        if platform != MINECRAFT_PLATFORM {
+            return author.avatar.clone();
+        }
+        let template = self.template.as_ref()?;
+        if author.id == NIL_UUID {
+            return None;
+        }
+
+        let mut resolved = self.resolved.lock().unwrap();
+        let cached = resolved.get(&author.id);
+
+        // Only render the url again if the skin could have changed
+        let encode = |value: &str| utf8_percent_encode(value, URL_SAFE).to_string();
+        let url = match (&author.skin, cached) {
+            (None, Some(cached)) => return Some(cached),
+            // Renderers that need the skin can't be used without it
+            (None, None) if template.contains("{texture}") => return None,
+            (skin, _) => template
+                .replace("{uuid}", &encode(&author.id))
+                .replace("{name}", &encode(&author.name))
+                .replace("{texture}", &encode(skin.as_deref().unwrap_or_default())),
+        };
+        resolved.insert(author.id.clone(), url.clone());
+        Some(url)
+    }
+}
+
+impl Resolved {
+    fn get(&mut self, uuid: &str) -> Option<String> {
+        let url = self.urls.get(uuid)?.clone();
+        self.touch(uuid);
+        Some(url)
+    }
+
+    fn insert(&mut self, uuid: String, url: String) {
+        match self.urls.insert(uuid.clone(), url) {
+            Some(_) => self.touch(&uuid),
+            None => self.order.push_back(uuid),
+        }
+
+        // Forget the players seen least recently
+        while self.order.len() > AVATAR_CAPACITY {
+            if let Some(old) = self.order.pop_front() {
+                self.urls.remove(&old);
+            }
+        }
+    }
+
+    // Move a player to the back of the queue
+    fn touch(&mut self, uuid: &str) {
+        if let Some(index) = self.order.iter().position(|id| id == uuid) {
+            if let Some(id) = self.order.remove(index) {
+                self.order.push_back(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+
+    fn player(uuid: &str, name: &str, skin: Option<&str>) -> Author {
+        let mut author = Author::new(uuid, name);
+        author.skin = skin.map(str::to_string);
+        author
+    }
+
+    #[test]
+    fn avatar_urls_are_encoded() {
+        let avatars = Avatars::new(Some("https://heads.example.com/{name}/{texture}?id={uuid}"));
+        let author = player(UUID, "Alex & {uuid}", Some("a/b"));
+        assert_eq!(
+            avatars.url(&author, MINECRAFT_PLATFORM).as_deref(),
+            Some("https://heads.example.com/Alex%20%26%20%7Buuid%7D/a%2Fb?id=069a79f4-44e9-4726-a5be-fca90e38aaf5")
+        );
+    }
+
+    #[test]
+    fn avatars_are_kept_without_skins() {
+        let avatars = Avatars::new(Some("https://heads.example.com/{texture}"));
+        assert_eq!(
+            avatars.url(&player(UUID, "Notch", None), MINECRAFT_PLATFORM),
+            None
+        );
+
+        let url = avatars.url(&player(UUID, "Notch", Some("abc")), MINECRAFT_PLATFORM);
+        assert_eq!(url.as_deref(), Some("https://heads.example.com/abc"));
+        assert_eq!(
+            avatars.url(&player(UUID, "Notch", None), MINECRAFT_PLATFORM),
+            url
+        );
+
+        // Only players get head renders
+        let mut author = player("1", "Alex", None);
+        author.avatar = Some("https://cdn.example.com/alex.png".to_string());
+        assert_eq!(avatars.url(&author, "matrix"), author.avatar);
+        assert_eq!(
+            avatars.url(&player(NIL_UUID, "Server", Some("abc")), MINECRAFT_PLATFORM),
+            None
+        );
+    }
+
+    #[test]
+    fn avatars_forget_players_seen_least_recently() {
+        let avatars = Avatars::new(Some("https://heads.example.com/{texture}"));
+        let url = |uuid: usize, skin| {
+            avatars.url(&player(&uuid.to_string(), "Alex", skin), MINECRAFT_PLATFORM)
+        };
+
+        for uuid in 0..AVATAR_CAPACITY {
+            url(uuid, Some("abc"));
+        }
+        // Seeing the first player again keeps them
+        assert!(url(0, None).is_some());
+        url(AVATAR_CAPACITY, Some("abc"));
+
+        assert!(url(0, None).is_some());
+        assert_eq!(url(1, None), None);
+        assert_eq!(avatars.resolved.lock().unwrap().urls.len(), AVATAR_CAPACITY);
+    }
+}
@@ -0,0 +1,292 @@
+use async_trait::async_trait;
+use flume::{Receiver, Sender};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{
+    Attachment, AttachmentKind, Author, AzaleaEvent, BridgeMessage, BridgePlatform, Capabilities,
+    MessageId, PlatformBuilder, PluginEvent, Reply, MINECRAFT_PLATFORM,
+};
+
+// How long a platform can take with an event before it counts as stuck
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// The channel events are sent to when only checking how they are adapted
+const SAMPLE_CHANNEL: &str = "conformance";
+
+/// Checks that a platform behaves the way the bridge expects, without connecting it.
+///
+/// Every event is handled for real and sent to `channel`, so the platform should be
+/// pointed at a stub server that the test can check the requests of afterwards.
+/// The sample attachment is a link, so nothing is downloaded.
+pub async fn check_platform<P: BridgePlatform>(platform: P, channel: &str) {
+    let name = platform.name();
+    assert!(!name.is_empty(), "Platform name is empty");
+    assert_ne!(
+        name, MINECRAFT_PLATFORM,
+        "Platform can't use the Minecraft name"
+    );
+
+    let capabilities = platform.capabilities();
+    check_adapt(capabilities);
+    check_builder(capabilities).await;
+
+    for event in sample_events(channel) {
+        let Some(event) = capabilities.adapt(event) else {
+            continue;
+        };
+        let description = format!("{event:?}");
+        match tokio::time::timeout(TIMEOUT, platform.handle(event)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => panic!("{name} failed to handle {description}: {e}"),
+            Err(_) => panic!("{name} took too long to handle {description}"),
+        }
+    }
+}
+
+/// Checks that events are only changed as much as the capabilities need.
+pub fn check_adapt(capabilities: Capabilities) {
+    for event in sample_events(SAMPLE_CHANNEL) {
+        let adapted = capabilities.adapt(event.clone());
+        match (event, adapted) {
+            (AzaleaEvent::Chat(original, targets), Some(AzaleaEvent::Chat(message, adapted))) => {
+                assert_eq!(targets, adapted, "Chat targets changed");
+                check_message(capabilities, &original, &message);
+            }
+            (AzaleaEvent::Edit(original, targets), Some(AzaleaEvent::Edit(message, adapted))) => {
+                assert!(capabilities.edits, "Edit kept without edit support");
+                assert_eq!(original.content, message.content, "Edit content changed");
+                assert_eq!(targets, adapted, "Edit targets changed");
+            }
+            // Edits are sent as a follow-up message to the same channels
+            (AzaleaEvent::Edit(original, targets), Some(AzaleaEvent::Chat(message, adapted))) => {
+                assert!(!capabilities.edits, "Edit changed with edit support");
+                assert_eq!(targets, adapted, "Follow-up edit sent to other channels");
+                assert_eq!(
+                    message.author.name,
+                    format!("{} (edited)", original.author.name)
+                );
+                assert!(message.reply.is_none(), "Follow-up edit kept its reply");
+            }
+            (AzaleaEvent::Delete(_), None) => {
+                assert!(!capabilities.deletes, "Delete dropped with delete support");
+            }
+            (AzaleaEvent::Delete(id), Some(AzaleaEvent::Delete(adapted))) => {
+                assert!(capabilities.deletes, "Delete kept without delete support");
+                assert_eq!(id, adapted, "Deleted id changed");
+            }
+            (AzaleaEvent::Notice(..), Some(AzaleaEvent::Notice(..))) => {}
+            (event, adapted) => panic!("{event:?} was adapted into {adapted:?}"),
+        }
+    }
+}
+
+fn check_message(capabilities: Capabilities, original: &BridgeMessage, message: &BridgeMessage) {
+    match (&original.reply, capabilities.replies) {
+        (Some(_), true) => assert!(message.reply.is_some(), "Reply dropped"),
+        (Some(reply), false) => {
+            assert!(message.reply.is_none(), "Reply kept without reply support");
+            assert!(
+                message.content.starts_with(&reply.to_string()),
+                "Reply not quoted in the content"
+            );
+        }
+        (None, _) => assert!(message.reply.is_none(), "Reply added"),
+    }
+
+    if capabilities.attachments {
+        assert_eq!(original.attachments.len(), message.attachments.len());
+    } else {
+        assert!(message.attachments.is_empty(), "Attachments kept");
+        for attachment in &original.attachments {
+            assert!(
+                message.content.contains(&attachment.to_string()),
+                "Attachment not linked in the content"
+            );
+        }
+    }
+
+    match capabilities.avatars {
+        true => assert_eq!(original.author.avatar, message.author.avatar),
+        false => assert!(message.author.avatar.is_none(), "Avatar kept"),
+    }
+}
+
+/// Checks that a built plugin passes events both ways and shuts the platform down.
+pub async fn check_builder(capabilities: Capabilities) {
+    let (platform, side) = FakePlatform::new(capabilities);
+    let client = PlatformBuilder::new(platform).build();
+
+    // Events from Azalea reach the platform, adapted
+    for event in sample_events(SAMPLE_CHANNEL) {
+        let expected = capabilities.adapt(event.clone());
+        client.tx.send(event).unwrap();
+        if let Some(expected) = expected {
+            let handled = recv(&side.handled).await;
+            assert_eq!(format!("{expected:?}"), format!("{handled:?}"));
+        }
+    }
+
+    // Messages from the platform reach Azalea
+    side.incoming
+        .send(PluginEvent::Chat(sample_message(SAMPLE_CHANNEL)))
+        .unwrap();
+    let received = recv(&client.rx).await;
+    assert!(matches!(received, PluginEvent::Chat(message) if message.content == "hello"));
+
+    // The platform shuts down once Azalea is gone
+    drop(client);
+    for _ in 0..50 {
+        if side.shut_down.load(Ordering::SeqCst) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Platform wasn't shut down");
+}
+
+/// A platform that only passes events over channels, for testing the bridge itself.
+#[derive(Debug, Clone)]
+pub struct FakePlatform {
+    capabilities: Capabilities,
+    // Sent on to Azalea once started
+    incoming: Receiver<PluginEvent>,
+    handled: Sender<AzaleaEvent>,
+    shut_down: Arc<AtomicBool>,
+}
+
+/// The other end of a [`FakePlatform`].
+#[derive(Debug, Clone)]
+pub struct FakeSide {
+    // Send messages as if they came from the platform
+    pub incoming: Sender<PluginEvent>,
+    // Every event the platform handled
+    pub handled: Receiver<AzaleaEvent>,
+    pub shut_down: Arc<AtomicBool>,
+}
+
+impl FakePlatform {
+    pub fn new(capabilities: Capabilities) -> (Self, FakeSide) {
+        let (incoming_tx, incoming) = flume::unbounded();
+        let (handled, handled_rx) = flume::unbounded();
+        let shut_down = Arc::new(AtomicBool::new(false));
+
+        let platform = Self {
+            capabilities,
+            incoming,
+            handled,
+            shut_down: shut_down.clone(),
+        };
+        let side = FakeSide {
+            incoming: incoming_tx,
+            handled: handled_rx,
+            shut_down,
+        };
+        (platform, side)
+    }
+}
+
+#[async_trait]
+impl BridgePlatform for FakePlatform {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        while let Ok(event) = self.incoming.recv_async().await {
+            tx.send_async(event).await?;
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        self.handled.send_async(event).await?;
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        self.shut_down.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+// Wait for the next event, failing instead of hanging
+async fn recv<E>(rx: &Receiver<E>) -> E {
+    tokio::time::timeout(TIMEOUT, rx.recv_async())
+        .await
+        .expect("Timed out waiting for an event")
+        .expect("Channel closed")
+}
+
+// A message using everything a platform could support
+fn sample_message(channel: &str) -> BridgeMessage {
+    let id = MessageId::new(MINECRAFT_PLATFORM, "conformance");
+    let mut author = Author::new("069a79f4-44e9-4726-a5be-fca90e38aaf5", "Notch");
+    author.avatar = Some("https://example.com/avatar.png".to_string());
+
+    let mut message = BridgeMessage::new(id, channel.to_string(), author, "hello".into());
+    let replied = MessageId::new(MINECRAFT_PLATFORM, "earlier");
+    message.reply = Some(Reply::new(
+        replied,
+        "jeb_".to_string(),
+        "an earlier message",
+    ));
+    message.attachments.push(Attachment {
+        kind: AttachmentKind::Embed,
+        name: "Example".to_string(),
+        url: "https://example.com/".to_string(),
+        mime: None,
+    });
+    message
+}
+
+// One of each event, sent to a channel
+fn sample_events(channel: &str) -> Vec<AzaleaEvent> {
+    let message = sample_message(channel);
+    let mut plain = message.clone();
+    plain.reply = None;
+    plain.attachments.clear();
+
+    vec![
+        AzaleaEvent::Chat(message.clone(), vec![channel.to_string()]),
+        AzaleaEvent::Chat(plain, Vec::new()),
+        AzaleaEvent::Edit(message.clone(), vec![channel.to_string()]),
+        AzaleaEvent::Delete(message.id),
+        AzaleaEvent::Notice("notice".to_string(), channel.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adapt_all() {
+        check_adapt(Capabilities::all());
+    }
+
+    #[test]
+    fn adapt_none() {
+        check_adapt(Capabilities::default());
+    }
+
+    #[tokio::test]
+    async fn fake_platform() {
+        let (platform, _side) = FakePlatform::new(Capabilities::all());
+        check_platform(platform, SAMPLE_CHANNEL).await;
+    }
+
+    #[tokio::test]
+    async fn builder_without_capabilities() {
+        check_builder(Capabilities::default()).await;
+    }
+}
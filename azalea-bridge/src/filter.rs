@@ -0,0 +1,445 @@
+use regex::{NoExpand, Regex};
+use std::{collections::HashSet, fmt::Debug, sync::Arc};
+
+/// Which way a message is going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ToMinecraft,
+    FromMinecraft,
+}
+
+/// Where a message being filtered came from.
+#[derive(Debug, Clone, Copy)]
+pub struct FilterContext<'a> {
+    pub direction: Direction,
+    pub platform: &'a str,
+    pub user_id: &'a str,
+    pub username: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    Allow,
+    Block,
+}
+
+/// A stage in a [`FilterChain`], which can change or block messages.
+pub trait ChatFilter: Debug + Send + Sync {
+    fn filter(&self, context: &FilterContext<'_>, content: &mut String) -> FilterAction;
+}
+
+/// Filters applied in order to every message a plugin relays.
+#[derive(Debug, Clone, Default)]
+pub struct FilterChain {
+    filters: Vec<Arc<dyn ChatFilter>>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add a filter to the end of the chain
+    pub fn with(mut self, filter: impl ChatFilter + 'static) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    // Run every filter, returning None if the message was blocked
+    pub fn apply(&self, context: &FilterContext<'_>, content: &str) -> Option<String> {
+        let mut content = content.to_string();
+        for filter in &self.filters {
+            if filter.filter(context, &mut content) == FilterAction::Block {
+                return None;
+            }
+        }
+        Some(content)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum WordAction {
+    Replace(String),
+    Block,
+}
+
+/// Replaces or blocks words matching regular expressions.
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    words: Vec<(Regex, WordAction)>,
+}
+
+impl WordFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replace anything matching a pattern
+    pub fn replace(mut self, pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
+        let replacement = WordAction::Replace(replacement.to_string());
+        self.words.push((Regex::new(pattern)?, replacement));
+        Ok(self)
+    }
+
+    // Block messages matching a pattern
+    pub fn block(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.words.push((Regex::new(pattern)?, WordAction::Block));
+        Ok(self)
+    }
+}
+
+impl ChatFilter for WordFilter {
+    fn filter(&self, _: &FilterContext<'_>, content: &mut String) -> FilterAction {
+        for (pattern, action) in &self.words {
+            if !pattern.is_match(content) {
+                continue;
+            }
+            match action {
+                WordAction::Replace(replacement) => {
+                    *content = pattern
+                        .replace_all(content, NoExpand(replacement))
+                        .into_owned();
+                }
+                WordAction::Block => return FilterAction::Block,
+            }
+        }
+        FilterAction::Allow
+    }
+}
+
+/// Blocks messages containing links, or removes the links.
+#[derive(Debug, Clone)]
+pub struct LinkFilter {
+    pattern: Regex,
+    block: bool,
+}
+
+impl LinkFilter {
+    // Block messages with links
+    pub fn block() -> Self {
+        Self::new(true)
+    }
+
+    // Replace links with `[link removed]`
+    pub fn remove() -> Self {
+        Self::new(false)
+    }
+
+    fn new(block: bool) -> Self {
+        Self {
+            pattern: Regex::new(r"(?i)\b(?:[a-z][a-z0-9+.-]*://|www\.)\S+").unwrap(),
+            block,
+        }
+    }
+}
+
+impl ChatFilter for LinkFilter {
+    fn filter(&self, _: &FilterContext<'_>, content: &mut String) -> FilterAction {
+        if !self.pattern.is_match(content) {
+            return FilterAction::Allow;
+        }
+        if self.block {
+            return FilterAction::Block;
+        }
+
+        *content = self
+            .pattern
+            .replace_all(content, "[link removed]")
+            .into_owned();
+        FilterAction::Allow
+    }
+}
+
+/// Lowercases messages that are mostly capital letters.
+#[derive(Debug, Clone)]
+pub struct CapsFilter {
+    // Most capital letters allowed, from 0.0 to 1.0
+    pub max_ratio: f32,
+    // Messages with fewer letters are left alone
+    pub min_letters: usize,
+}
+
+impl Default for CapsFilter {
+    fn default() -> Self {
+        Self {
+            max_ratio: 0.7,
+            min_letters: 8,
+        }
+    }
+}
+
+impl ChatFilter for CapsFilter {
+    fn filter(&self, _: &FilterContext<'_>, content: &mut String) -> FilterAction {
+        let letters = content.chars().filter(|c| c.is_alphabetic()).count();
+        let capitals = content.chars().filter(|c| c.is_uppercase()).count();
+
+        if letters >= self.min_letters && capitals as f32 / letters as f32 > self.max_ratio {
+            *content = content.to_lowercase();
+        }
+        FilterAction::Allow
+    }
+}
+
+/// Shortens repeated characters and words, like `nooooooo`.
+#[derive(Debug, Clone)]
+pub struct RepeatFilter {
+    // Most times something can repeat in a row
+    pub max_repeats: usize,
+}
+
+impl Default for RepeatFilter {
+    fn default() -> Self {
+        Self { max_repeats: 3 }
+    }
+}
+
+impl ChatFilter for RepeatFilter {
+    fn filter(&self, _: &FilterContext<'_>, content: &mut String) -> FilterAction {
+        // Repeated characters
+        let mut result = String::with_capacity(content.len());
+        let mut previous = None;
+        let mut count = 0;
+        for c in content.chars() {
+            count = if previous == Some(c) { count + 1 } else { 1 };
+            previous = Some(c);
+            if count <= self.max_repeats {
+                result.push(c);
+            }
+        }
+
+        // Repeated words
+        let mut words: Vec<&str> = Vec::new();
+        let mut count = 0;
+        for word in result.split(' ') {
+            let repeated = words
+                .last()
+                .map_or(false, |last| last.eq_ignore_ascii_case(word));
+            count = if repeated { count + 1 } else { 1 };
+            if count <= self.max_repeats {
+                words.push(word);
+            }
+        }
+
+        *content = words.join(" ");
+        FilterAction::Allow
+    }
+}
+
+/// Allows or denies users on a platform by id or name.
+#[derive(Debug, Clone)]
+pub struct UserFilter {
+    platform: String,
+    allow: Option<HashSet<String>>,
+    deny: HashSet<String>,
+}
+
+impl UserFilter {
+    pub fn new(platform: &str) -> Self {
+        Self {
+            platform: platform.to_string(),
+            allow: None,
+            deny: HashSet::new(),
+        }
+    }
+
+    // Only allow these users, everyone else is blocked
+    pub fn allow<S: AsRef<str>>(mut self, users: impl IntoIterator<Item = S>) -> Self {
+        let allow = self.allow.get_or_insert_with(HashSet::new);
+        allow.extend(users.into_iter().map(|user| user.as_ref().to_lowercase()));
+        self
+    }
+
+    // Block these users
+    pub fn deny<S: AsRef<str>>(mut self, users: impl IntoIterator<Item = S>) -> Self {
+        let deny = &mut self.deny;
+        deny.extend(users.into_iter().map(|user| user.as_ref().to_lowercase()));
+        self
+    }
+}
+
+impl ChatFilter for UserFilter {
+    fn filter(&self, context: &FilterContext<'_>, _: &mut String) -> FilterAction {
+        if context.platform != self.platform {
+            return FilterAction::Allow;
+        }
+
+        let user_id = context.user_id.to_lowercase();
+        let username = context.username.to_lowercase();
+        let matches =
+            |users: &HashSet<String>| users.contains(&user_id) || users.contains(&username);
+
+        if matches(&self.deny) {
+            return FilterAction::Block;
+        }
+        match &self.allow {
+            Some(allow) if !matches(allow) => FilterAction::Block,
+            _ => FilterAction::Allow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(platform: &str) -> FilterContext<'_> {
+        FilterContext {
+            direction: Direction::ToMinecraft,
+            platform,
+            user_id: "1234",
+            username: "Alex",
+        }
+    }
+
+    fn apply(filter: impl ChatFilter + 'static, content: &str) -> Option<String> {
+        FilterChain::new()
+            .with(filter)
+            .apply(&context("discord"), content)
+    }
+
+    #[test]
+    fn word_filter_replace() {
+        let filter = WordFilter::new().replace(r"(?i)\bheck\b", "h*ck").unwrap();
+        assert_eq!(
+            apply(filter.clone(), "what the HECK").unwrap(),
+            "what the h*ck"
+        );
+        assert_eq!(apply(filter, "checking").unwrap(), "checking");
+    }
+
+    #[test]
+    fn word_filter_replacement_is_literal() {
+        let filter = WordFilter::new().replace("cost", "$1").unwrap();
+        assert_eq!(apply(filter, "cost").unwrap(), "$1");
+    }
+
+    #[test]
+    fn word_filter_block() {
+        let filter = WordFilter::new().block(r"(?i)free robux").unwrap();
+        assert_eq!(apply(filter.clone(), "get FREE ROBUX here"), None);
+        assert_eq!(apply(filter, "robux").unwrap(), "robux");
+    }
+
+    #[test]
+    fn link_filter_block() {
+        assert_eq!(apply(LinkFilter::block(), "see https://example.com"), None);
+        assert_eq!(apply(LinkFilter::block(), "see www.example.com"), None);
+        assert_eq!(apply(LinkFilter::block(), "no links").unwrap(), "no links");
+    }
+
+    #[test]
+    fn link_filter_remove() {
+        assert_eq!(
+            apply(LinkFilter::remove(), "see http://a.com and HTTPS://b.org/x").unwrap(),
+            "see [link removed] and [link removed]"
+        );
+    }
+
+    #[test]
+    fn caps_filter_threshold() {
+        let filter = CapsFilter::default();
+        assert_eq!(
+            apply(filter.clone(), "HELLO EVERYONE").unwrap(),
+            "hello everyone"
+        );
+        // Mostly lowercase, or too short to count
+        assert_eq!(
+            apply(filter.clone(), "Hello Everyone").unwrap(),
+            "Hello Everyone"
+        );
+        assert_eq!(apply(filter, "OK LOL").unwrap(), "OK LOL");
+    }
+
+    #[test]
+    fn caps_filter_custom_ratio() {
+        let filter = CapsFilter {
+            max_ratio: 0.5,
+            min_letters: 4,
+        };
+        assert_eq!(apply(filter.clone(), "ABCdef").unwrap(), "ABCdef");
+        assert_eq!(apply(filter, "ABCDef").unwrap(), "abcdef");
+    }
+
+    #[test]
+    fn repeat_filter_characters() {
+        let filter = RepeatFilter::default();
+        assert_eq!(apply(filter.clone(), "nooooooo").unwrap(), "nooo");
+        assert_eq!(apply(filter, "!!!!!!").unwrap(), "!!!");
+    }
+
+    #[test]
+    fn repeat_filter_words() {
+        let filter = RepeatFilter { max_repeats: 2 };
+        assert_eq!(
+            apply(filter.clone(), "spam SPAM spam Spam").unwrap(),
+            "spam SPAM"
+        );
+        assert_eq!(apply(filter, "a b a b").unwrap(), "a b a b");
+    }
+
+    #[test]
+    fn user_filter_deny() {
+        let filter = UserFilter::new("discord").deny(["alex"]);
+        assert_eq!(apply(filter.clone(), "hi"), None);
+
+        // Other platforms aren't affected
+        let chain = FilterChain::new().with(filter);
+        assert_eq!(chain.apply(&context("matrix"), "hi").unwrap(), "hi");
+    }
+
+    #[test]
+    fn user_filter_allow() {
+        let allowed = UserFilter::new("discord").allow(["1234"]);
+        assert_eq!(apply(allowed, "hi").unwrap(), "hi");
+
+        let other = UserFilter::new("discord").allow(["Steve"]);
+        assert_eq!(apply(other.clone(), "hi"), None);
+        let chain = FilterChain::new().with(other);
+        assert_eq!(chain.apply(&context("matrix"), "hi").unwrap(), "hi");
+    }
+
+    #[test]
+    fn user_filter_deny_wins() {
+        let filter = UserFilter::new("discord").allow(["alex"]).deny(["1234"]);
+        assert_eq!(apply(filter, "hi"), None);
+    }
+
+    // Counts how many times it was run
+    #[derive(Debug, Default)]
+    struct Counter(std::sync::atomic::AtomicUsize);
+
+    impl ChatFilter for Arc<Counter> {
+        fn filter(&self, _: &FilterContext<'_>, _: &mut String) -> FilterAction {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            FilterAction::Allow
+        }
+    }
+
+    #[test]
+    fn chain_runs_in_order() {
+        let chain = FilterChain::new()
+            .with(WordFilter::new().replace("cat", "DOG").unwrap())
+            .with(CapsFilter {
+                max_ratio: 0.5,
+                min_letters: 1,
+            });
+        assert_eq!(chain.apply(&context("discord"), "cat").unwrap(), "dog");
+    }
+
+    #[test]
+    fn chain_stops_when_blocked() {
+        let counter = Arc::new(Counter::default());
+        let chain = FilterChain::new()
+            .with(LinkFilter::block())
+            .with(counter.clone());
+
+        assert_eq!(
+            chain.apply(&context("discord"), "https://example.com"),
+            None
+        );
+        assert_eq!(counter.0.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        assert!(chain.apply(&context("discord"), "fine").is_some());
+        assert_eq!(counter.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::template::{Template, TemplateValues};
+
+// Longest chat message the server accepts, in UTF-16 code units
+const MAX_LENGTH: usize = 256;
+// Most lines to send for a single message
+pub(crate) const MAX_LINES: usize = 4;
+// Longest name to show before a message
+const MAX_NAME_LENGTH: usize = 32;
+
+// Split a message into lines the server will accept,
+// each formatted with the template
+pub(crate) fn split_message(
+    template: &Template,
+    name: &str,
+    platform: &str,
+    msg: &str,
+) -> Vec<String> {
+    let name = truncate(&sanitize(name), MAX_NAME_LENGTH);
+    let render = |line: &str| {
+        let values = TemplateValues {
+            name: name.trim(),
+            message: line,
+            platform,
+        };
+        template.render(&values, sanitize)
+    };
+
+    // Leave room for everything around the message
+    let width = MAX_LENGTH.saturating_sub(text_length(&render(""))).max(1);
+
+    split_words(&sanitize(msg), width)
+        .into_iter()
+        .map(|line| render(&line))
+        .collect()
+}
+
+// Cut off anything past the last line, returning how many lines were removed
+pub(crate) fn limit_lines(lines: &mut Vec<String>, max: usize) -> usize {
+    if lines.len() <= max {
+        return 0;
+    }
+
+    let removed = lines.len() - max;
+    lines.truncate(max);
+    if let Some(last) = lines.last_mut() {
+        *last = format!("{}…", truncate(last, MAX_LENGTH - 1).trim_end());
+    }
+    removed
+}
+
+// Remove characters the server rejects and put everything on one line
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '§' => None,
+            c if c.is_whitespace() => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+// Split text into lines, preferring to split between words
+fn split_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_length = 0;
+
+    for word in text.split(' ').filter(|word| !word.is_empty()) {
+        let word_length = text_length(word);
+
+        // Add the word to the current line if it fits
+        let space = usize::from(!line.is_empty());
+        if line_length + space + word_length <= width {
+            if space == 1 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            line_length += space + word_length;
+            continue;
+        }
+
+        // Otherwise start a new line
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        // Words that don't fit on a line have to be split
+        let mut rest = word;
+        while text_length(rest) > width {
+            let (first, second) = split_at_length(rest, width);
+            lines.push(first.to_string());
+            rest = second;
+        }
+        line.push_str(rest);
+        line_length = text_length(rest);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// Split text after the last character that fits in a length
+fn split_at_length(text: &str, length: usize) -> (&str, &str) {
+    let mut total = 0;
+    for (index, c) in text.char_indices() {
+        total += c.len_utf16();
+        if total > length {
+            return text.split_at(index);
+        }
+    }
+    (text, "")
+}
+
+fn truncate(text: &str, length: usize) -> String {
+    split_at_length(text, length).0.to_string()
+}
+
+// The server counts UTF-16 code units, not bytes or characters
+fn text_length(text: &str) -> usize {
+    text.chars().map(char::len_utf16).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Templates;
+
+    #[test]
+    fn split_at_length_multi_byte() {
+        // 'é' is two bytes but one UTF-16 code unit
+        assert_eq!(split_at_length("héllo", 2), ("hé", "llo"));
+        assert_eq!(split_at_length("héllo", 10), ("héllo", ""));
+    }
+
+    #[test]
+    fn split_at_length_emoji() {
+        // Emoji are two UTF-16 code units, and are never split in half
+        assert_eq!(split_at_length("😀😀", 3), ("😀", "😀"));
+        assert_eq!(split_at_length("😀😀", 1), ("", "😀😀"));
+        assert_eq!(text_length("a😀"), 3);
+    }
+
+    #[test]
+    fn split_words_between_words() {
+        assert_eq!(
+            split_words("hello world again", 11),
+            ["hello world", "again"]
+        );
+        assert_eq!(split_words("  spaced   out  ", 20), ["spaced out"]);
+    }
+
+    #[test]
+    fn split_words_long_word() {
+        assert_eq!(split_words("abcdefgh ij", 3), ["abc", "def", "gh", "ij"]);
+        assert_eq!(split_words("😀😀😀", 4), ["😀😀", "😀"]);
+    }
+
+    #[test]
+    fn split_message_fits_server_limit() {
+        let template = Templates::new().minecraft().clone();
+        let message = "😀".repeat(300);
+        let lines = split_message(&template, "Alex", "discord", &message);
+
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert!(line.starts_with("Alex: "));
+            assert!(text_length(line) <= MAX_LENGTH);
+        }
+    }
+
+    #[test]
+    fn split_message_truncates_name() {
+        let template = Templates::new().minecraft().clone();
+        let name = "n".repeat(40);
+        let lines = split_message(&template, &name, "discord", "hi");
+        assert_eq!(lines, [format!("{}: hi", "n".repeat(MAX_NAME_LENGTH))]);
+    }
+
+    #[test]
+    fn limit_lines_adds_ellipsis() {
+        let mut lines = vec!["one".to_string(), "two ".to_string(), "three".to_string()];
+        assert_eq!(limit_lines(&mut lines, 2), 1);
+        assert_eq!(lines, ["one", "two…"]);
+    }
+
+    #[test]
+    fn limit_lines_keeps_short_messages() {
+        let mut lines = vec!["one".to_string()];
+        assert_eq!(limit_lines(&mut lines, MAX_LINES), 0);
+        assert_eq!(lines, ["one"]);
+    }
+
+    #[test]
+    fn limit_lines_stays_under_limit() {
+        let mut lines = vec!["a".repeat(MAX_LENGTH), "b".to_string()];
+        limit_lines(&mut lines, 1);
+        assert_eq!(text_length(&lines[0]), MAX_LENGTH);
+        assert!(lines[0].ends_with('…'));
+    }
+
+    #[test]
+    fn sanitize_section_sign() {
+        assert_eq!(sanitize("§cred §lbold"), "cred lbold");
+    }
+
+    #[test]
+    fn sanitize_control_characters() {
+        assert_eq!(sanitize("a\nb\tc\r\nd"), "a b c  d");
+        assert_eq!(sanitize("bell\u{7}\u{1b}[0m"), "bell[0m");
+    }
+}
@@ -0,0 +1,317 @@
+use azalea_auth::game_profile::GameProfile;
+use azalea_client::{
+    chat::{ChatReceivedEvent, SendChatEvent},
+    GameProfileComponent,
+};
+use azalea_world::entity::Local;
+use bevy::prelude::{App, Events};
+use flume::{Receiver, Sender};
+use log::error;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+use crate::{AzaleaEvent, ChatLimit, ClientSide, PluginEvent, PluginSide};
+
+/// An event seen going through a [`ClientSide`], and when.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recorded {
+    // Milliseconds since recording started
+    pub at: u64,
+    pub event: RecordedEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedEvent {
+    // From Minecraft, or relayed from another plugin
+    ToPlugin(AzaleaEvent),
+    FromPlugin(PluginEvent),
+}
+
+/// Records every event going to and from a plugin, one JSON object per line.
+///
+/// This should be done before registering the plugin with a hub.
+pub fn record<T>(
+    mut client: ClientSide<T>,
+    path: impl AsRef<Path>,
+) -> anyhow::Result<ClientSide<T>> {
+    let file = Arc::new(Mutex::new(BufWriter::new(File::create(path)?)));
+    let start = Instant::now();
+
+    // Put a channel between Azalea and the plugin in each direction
+    let (to_plugin, to_plugin_rx) = flume::unbounded();
+    let (from_plugin_tx, from_plugin) = flume::unbounded();
+    let plugin_tx = std::mem::replace(&mut client.tx, to_plugin);
+    let plugin_rx = std::mem::replace(&mut client.rx, from_plugin);
+
+    let writer = file.clone();
+    thread::spawn(move || {
+        forward(to_plugin_rx, plugin_tx, |event| {
+            write_event(&writer, start, RecordedEvent::ToPlugin(event.clone()))
+        })
+    });
+    thread::spawn(move || {
+        forward(plugin_rx, from_plugin_tx, |event| {
+            write_event(&file, start, RecordedEvent::FromPlugin(event.clone()))
+        })
+    });
+
+    Ok(client)
+}
+
+// Pass events along until either side is gone
+fn forward<E>(rx: Receiver<E>, tx: Sender<E>, record: impl Fn(&E)) {
+    while let Ok(event) = rx.recv() {
+        record(&event);
+        if tx.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+fn write_event(file: &Mutex<BufWriter<File>>, start: Instant, event: RecordedEvent) {
+    let recorded = Recorded {
+        at: start.elapsed().as_millis() as u64,
+        event,
+    };
+    if let Err(e) = write_line(&mut *file.lock().unwrap(), &recorded) {
+        error!("Unable to record event: {e}");
+    }
+}
+
+// Flush every line so nothing is lost if the program is stopped
+fn write_line(file: &mut impl Write, recorded: &Recorded) -> anyhow::Result<()> {
+    writeln!(file, "{}", serde_json::to_string(recorded)?)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Plays back events saved by [`record`].
+///
+/// By default events are sent straight away, so tests don't depend on timing.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    events: Vec<Recorded>,
+    realtime: bool,
+}
+
+impl Replay {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self {
+            events: read_lines(path)?,
+            realtime: false,
+        })
+    }
+
+    pub fn new(events: Vec<Recorded>) -> Self {
+        Self {
+            events,
+            realtime: false,
+        }
+    }
+
+    // Wait between events as long as was waited while recording
+    pub fn realtime(mut self) -> Self {
+        self.realtime = true;
+        self
+    }
+
+    pub fn events(&self) -> &[Recorded] {
+        &self.events
+    }
+
+    // Send the events that went to the plugin, as if they came from Minecraft
+    pub fn play_to_plugin<T>(&self, client: &ClientSide<T>) -> anyhow::Result<()> {
+        self.send_each(|event| match event {
+            RecordedEvent::ToPlugin(event) => Ok(client.tx.send(event.clone())?),
+            RecordedEvent::FromPlugin(_) => Ok(()),
+        })
+    }
+
+    // Send the events that came from the plugin, as if the plugin sent them
+    pub fn play_from_plugin<T>(&self, plugin: &PluginSide<T>) -> anyhow::Result<()> {
+        self.send_each(|event| match event {
+            RecordedEvent::FromPlugin(event) => Ok(plugin.tx.send(event.clone())?),
+            RecordedEvent::ToPlugin(_) => Ok(()),
+        })
+    }
+
+    fn send_each(&self, send: impl Fn(&RecordedEvent) -> anyhow::Result<()>) -> anyhow::Result<()> {
+        let start = Instant::now();
+        for recorded in &self.events {
+            if self.realtime {
+                let at = Duration::from_millis(recorded.at);
+                thread::sleep(at.saturating_sub(start.elapsed()));
+            }
+            send(&recorded.event)?;
+        }
+        Ok(())
+    }
+}
+
+fn read_lines<E: DeserializeOwned>(path: impl AsRef<Path>) -> anyhow::Result<Vec<E>> {
+    let mut events = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            events.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(events)
+}
+
+/// Runs a [`ClientSide`] without a server, collecting the chat it would send.
+pub struct FakeClient {
+    app: App,
+}
+
+impl FakeClient {
+    // A bot called `FakeBot`, which can send chat as fast as it likes
+    pub fn new<T: Clone + Sync + Send + 'static>(client: ClientSide<T>) -> Self {
+        let mut app = App::new();
+        app.add_event::<ChatReceivedEvent>()
+            .add_event::<SendChatEvent>()
+            .insert_resource(ChatLimit {
+                per_second: 1000.0,
+                burst: 1000.0,
+                max_queued: ChatLimit::default().max_queued,
+            })
+            .add_plugin(client);
+
+        let profile = GameProfile::new(Uuid::nil(), "FakeBot".to_string());
+        app.world.spawn((Local, GameProfileComponent(profile)));
+        Self { app }
+    }
+
+    // Let the plugin handle events, running twice so queued chat is sent
+    pub fn update(&mut self) {
+        self.app.update();
+        self.app.update();
+    }
+
+    // Chat sent since the last call, in order
+    pub fn sent_chat(&mut self) -> Vec<String> {
+        self.update();
+        self.app
+            .world
+            .resource_mut::<Events<SendChatEvent>>()
+            .drain()
+            .map(|event| event.content)
+            .collect()
+    }
+
+    // Panic unless exactly these lines were sent since the last call
+    pub fn assert_sent(&mut self, expected: &[&str]) {
+        let sent = self.sent_chat();
+        assert_eq!(sent, expected, "Sent chat didn't match");
+    }
+
+    pub fn app(&mut self) -> &mut App {
+        &mut self.app
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Author, BridgeMessage, MessageId, PluginBridge};
+
+    #[derive(Debug, Clone)]
+    struct Plugin;
+
+    fn message(id: &str, name: &str, content: &str) -> BridgeMessage {
+        let author = Author::new(name, name);
+        let id = MessageId::new("test", id);
+        BridgeMessage::new(id, "general".to_string(), author, content.to_string())
+    }
+
+    fn path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("azalea-harness-{}.jsonl", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let path = path();
+        let bridge = PluginBridge::<Plugin>::new(Vec::new());
+        let client = record(bridge.client, &path).unwrap();
+
+        // Once received, the event has already been written
+        let timeout = Duration::from_secs(5);
+        client
+            .tx
+            .send(AzaleaEvent::Notice("hello".into(), "general".into()))
+            .unwrap();
+        bridge.plugin.rx.recv_timeout(timeout).unwrap();
+        let chat = PluginEvent::Chat(message("1", "Alex", "hi"));
+        bridge.plugin.tx.send(chat).unwrap();
+        client.rx.recv_timeout(timeout).unwrap();
+
+        let replay = Replay::open(&path).unwrap();
+        let events = replay.events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0].event,
+            RecordedEvent::ToPlugin(AzaleaEvent::Notice(notice, _)) if notice == "hello"
+        ));
+        assert!(matches!(
+            &events[1].event,
+            RecordedEvent::FromPlugin(PluginEvent::Chat(message)) if message.content == "hi"
+        ));
+        assert!(events[0].at <= events[1].at);
+
+        // Playing it back sends each event the way it went
+        let other = PluginBridge::<Plugin>::new(Vec::new());
+        replay.play_to_plugin(&other.client).unwrap();
+        replay.play_from_plugin(&other.plugin).unwrap();
+        assert!(matches!(
+            other.plugin.rx.try_recv(),
+            Ok(AzaleaEvent::Notice(..))
+        ));
+        assert!(other.plugin.rx.is_empty());
+        assert!(matches!(
+            other.client.rx.try_recv(),
+            Ok(PluginEvent::Chat(_))
+        ));
+        assert!(other.client.rx.is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn fake_client_sends_chat() {
+        let bridge = PluginBridge::<Plugin>::new(Vec::new());
+        let mut client = FakeClient::new(bridge.client);
+
+        let events = vec![
+            Recorded {
+                at: 0,
+                event: RecordedEvent::FromPlugin(PluginEvent::Chat(message("1", "Alex", "hi"))),
+            },
+            Recorded {
+                at: 10,
+                event: RecordedEvent::FromPlugin(PluginEvent::Edit(message("1", "Alex", "hey"))),
+            },
+        ];
+        Replay::new(events)
+            .play_from_plugin(&bridge.plugin)
+            .unwrap();
+
+        client.assert_sent(&["Alex: hi", "Alex (edited): hey"]);
+        client.assert_sent(&[]);
+    }
+
+    #[test]
+    fn fake_client_without_chat() {
+        let bridge = PluginBridge::<Plugin>::new(Vec::new());
+        let mut client = FakeClient::new(bridge.client);
+        assert!(client.sent_chat().is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+use flume::Sender;
+use log::{debug, error};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    AzaleaEvent, BridgeMessage, ClientSide, Direction, FilterChain, FilterContext, MessageId,
+    PluginEvent, Routes,
+};
+
+// How many relayed messages to remember, so edits follow them and they can't be relayed again
+const RELAYED_CAPACITY: usize = 1024;
+
+/// Relays messages between any number of plugins.
+///
+/// Every plugin should be registered before it is added to Azalea.
+/// Messages are relayed as soon as a plugin sends them,
+/// even if they aren't sent to Minecraft or no bot is logged in.
+#[derive(Debug, Clone, Default)]
+pub struct BridgeHub {
+    inner: Arc<Mutex<HubInner>>,
+}
+
+#[derive(Debug, Default)]
+struct HubInner {
+    members: Vec<Member>,
+    relayed: VecDeque<Relayed>,
+    next_id: usize,
+}
+
+// A message relayed to other plugins
+#[derive(Debug)]
+struct Relayed {
+    id: MessageId,
+    // The plugins it was sent to, and the channels it was sent to on each
+    targets: Vec<(usize, Vec<String>)>,
+}
+
+#[derive(Debug)]
+struct Member {
+    // Given out when registering, so two plugins of the same type can be told apart
+    id: usize,
+    // The type name of the plugin, for logging
+    name: &'static str,
+    tx: Sender<AzaleaEvent>,
+    routes: Routes,
+}
+
+impl BridgeHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Relay messages to and from a plugin
+    pub fn register<T: Clone + Sync + Send + 'static>(&self, client: &mut ClientSide<T>) {
+        let id = {
+            let mut inner = self.inner.lock().unwrap();
+            let id = inner.next_id;
+            inner.next_id += 1;
+            inner.members.push(Member {
+                id,
+                name: std::any::type_name::<T>(),
+                tx: client.tx.clone(),
+                routes: client.routes.clone(),
+            });
+            id
+        };
+
+        // Put a channel between the plugin and Azalea, relaying everything on the way
+        let (tx, rx) = flume::unbounded();
+        let plugin_rx = std::mem::replace(&mut client.rx, rx);
+        let (filters, routes) = (client.filters.clone(), client.routes.clone());
+        let hub = self.clone();
+        thread::spawn(move || {
+            while let Ok(event) = plugin_rx.recv() {
+                if let Some(filtered) = filter(&filters, &routes, event.clone()) {
+                    hub.relay(id, &filtered);
+                }
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    // Send an event from a plugin to every other plugin
+    fn relay(&self, source: usize, event: &PluginEvent) {
+        let mut inner = self.inner.lock().unwrap();
+
+        match event {
+            PluginEvent::Chat(message) => {
+                // Messages coming back around have already been relayed
+                if inner.find(&message.id).is_some() {
+                    debug!("Not relaying {:?} again", message.id);
+                    return;
+                }
+
+                let mut targets = Vec::new();
+                for member in inner.others(source) {
+                    let mut sent = None;
+                    for route in member.routes.for_relay(&message.content) {
+                        let route_targets = route.targets().to_vec();
+                        member.send(AzaleaEvent::Chat(message.clone(), route_targets));
+                        sent = Some(join_targets(sent, route.targets()));
+                    }
+                    if let Some(sent) = sent {
+                        targets.push((member.id, sent));
+                    }
+                }
+
+                inner.relayed.push_back(Relayed {
+                    id: message.id.clone(),
+                    targets,
+                });
+                if inner.relayed.len() > RELAYED_CAPACITY {
+                    inner.relayed.pop_front();
+                }
+            }
+            // Edits and deletions only go where the message was sent
+            PluginEvent::Edit(message) => {
+                let Some(relayed) = inner.find(&message.id) else {
+                    debug!("Not relaying edit of {:?}, it wasn't relayed", message.id);
+                    return;
+                };
+                for (id, targets) in &relayed.targets {
+                    if let Some(member) = inner.member(*id) {
+                        member.send(AzaleaEvent::Edit(message.clone(), targets.clone()));
+                    }
+                }
+            }
+            PluginEvent::Delete(id) => {
+                let Some(relayed) = inner.find(id) else {
+                    debug!("Not relaying deletion of {id:?}, it wasn't relayed");
+                    return;
+                };
+                for (member, _) in &relayed.targets {
+                    if let Some(member) = inner.member(*member) {
+                        member.send(AzaleaEvent::Delete(id.clone()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl HubInner {
+    // Every plugin except the one an event came from
+    fn others(&self, source: usize) -> impl Iterator<Item = &Member> {
+        self.members
+            .iter()
+            .filter(move |member| member.id != source)
+    }
+
+    fn member(&self, id: usize) -> Option<&Member> {
+        self.members.iter().find(|member| member.id == id)
+    }
+
+    fn find(&self, id: &MessageId) -> Option<&Relayed> {
+        self.relayed.iter().find(|relayed| relayed.id == *id)
+    }
+}
+
+impl Member {
+    // A plugin that stopped shouldn't stop the others from getting the event
+    fn send(&self, event: AzaleaEvent) {
+        if let Err(e) = self.tx.send(event) {
+            error!("Unable to relay message to {}: {e}", self.name);
+        }
+    }
+}
+
+// Add the channels of another route, where no channels means every channel
+fn join_targets(sent: Option<Vec<String>>, targets: &[String]) -> Vec<String> {
+    match sent {
+        None => targets.to_vec(),
+        Some(sent) if sent.is_empty() || targets.is_empty() => Vec::new(),
+        Some(mut sent) => {
+            for target in targets {
+                if !sent.contains(target) {
+                    sent.push(target.clone());
+                }
+            }
+            sent
+        }
+    }
+}
+
+// Filter messages with the filters of the plugin they came from,
+// the same as on their way to Minecraft, and skip channels that aren't relayed
+fn filter(filters: &FilterChain, routes: &Routes, event: PluginEvent) -> Option<PluginEvent> {
+    let filter = |mut message: BridgeMessage| {
+        if !routes.is_relayed(&message.channel) {
+            return None;
+        }
+        let context = FilterContext {
+            direction: Direction::ToMinecraft,
+            platform: &message.id.platform,
+            user_id: &message.author.id,
+            username: &message.author.name,
+        };
+        message.content = filters.apply(&context, &message.content)?;
+        Some(message)
+    };
+
+    match event {
+        PluginEvent::Chat(message) => filter(message).map(PluginEvent::Chat),
+        PluginEvent::Edit(message) => filter(message).map(PluginEvent::Edit),
+        PluginEvent::Delete(id) => Some(PluginEvent::Delete(id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Author, MessageClass, PluginBridge, Route};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    struct Plugin;
+
+    fn message(id: &str) -> BridgeMessage {
+        let id = MessageId::new("test", id);
+        let author = Author::new("1", "Alex");
+        BridgeMessage::new(id, String::new(), author, "hi".into())
+    }
+
+    fn chat(id: &str) -> PluginEvent {
+        PluginEvent::Chat(message(id))
+    }
+
+    fn registered(hub: &BridgeHub) -> PluginBridge<Plugin> {
+        routed(hub, Routes::default())
+    }
+
+    fn routed(hub: &BridgeHub, routes: Routes) -> PluginBridge<Plugin> {
+        let mut bridge = PluginBridge::new(Vec::new());
+        bridge.client.routes = routes;
+        hub.register(&mut bridge.client);
+        bridge
+    }
+
+    #[test]
+    fn relays_between_plugins_of_the_same_type() {
+        let hub = BridgeHub::new();
+        let (first, second) = (registered(&hub), registered(&hub));
+
+        first.plugin.tx.send(chat("1")).unwrap();
+
+        // Sent on to Minecraft and to the other plugin, but not back
+        let timeout = Duration::from_secs(5);
+        assert!(first.client.rx.recv_timeout(timeout).is_ok());
+        let relayed = second.plugin.rx.recv_timeout(timeout).unwrap();
+        assert!(matches!(relayed, AzaleaEvent::Chat(message, _) if message.id.id == "1"));
+        assert!(first.plugin.rx.is_empty());
+    }
+
+    #[test]
+    fn keeps_relaying_past_closed_plugins() {
+        let hub = BridgeHub::new();
+        let (first, second, third) = (registered(&hub), registered(&hub), registered(&hub));
+        drop(second);
+
+        first.plugin.tx.send(chat("1")).unwrap();
+        assert!(third.plugin.rx.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn does_not_relay_twice() {
+        let hub = BridgeHub::new();
+        let (first, second) = (registered(&hub), registered(&hub));
+
+        first.plugin.tx.send(chat("1")).unwrap();
+        first.plugin.tx.send(chat("1")).unwrap();
+        assert!(first.client.rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        assert!(first.client.rx.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        assert!(second.plugin.rx.try_recv().is_ok());
+        assert!(second.plugin.rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn edits_follow_the_original() {
+        let hub = BridgeHub::new();
+        let first = registered(&hub);
+        let public = Route::new("a").target("b");
+        let second = routed(&hub, Routes::new().with(public).with(Route::new("c")));
+        let system = Route::new("d").classes([MessageClass::System]);
+        let third = routed(&hub, Routes::new().with(system));
+
+        first.plugin.tx.send(chat("1")).unwrap();
+        let timeout = Duration::from_secs(5);
+        assert!(second.plugin.rx.recv_timeout(timeout).is_ok());
+        assert!(second.plugin.rx.recv_timeout(timeout).is_ok());
+
+        let tx = &first.plugin.tx;
+        tx.send(PluginEvent::Edit(message("1"))).unwrap();
+        tx.send(PluginEvent::Delete(message("1").id)).unwrap();
+
+        // Once for every channel the message was sent to
+        let edit = second.plugin.rx.recv_timeout(timeout).unwrap();
+        assert!(matches!(edit, AzaleaEvent::Edit(_, targets) if targets == ["a", "b", "c"]));
+        let delete = second.plugin.rx.recv_timeout(timeout).unwrap();
+        assert!(matches!(delete, AzaleaEvent::Delete(id) if id.id == "1"));
+        assert!(second.plugin.rx.is_empty());
+        assert!(third.plugin.rx.is_empty());
+    }
+
+    #[test]
+    fn edits_of_unknown_messages_are_not_relayed() {
+        let hub = BridgeHub::new();
+        let (first, second) = (registered(&hub), registered(&hub));
+
+        let tx = &first.plugin.tx;
+        tx.send(PluginEvent::Edit(message("1"))).unwrap();
+        tx.send(PluginEvent::Delete(message("1").id)).unwrap();
+
+        // Both still reach Minecraft
+        let timeout = Duration::from_secs(5);
+        assert!(first.client.rx.recv_timeout(timeout).is_ok());
+        assert!(first.client.rx.recv_timeout(timeout).is_ok());
+        assert!(second.plugin.rx.is_empty());
+    }
+}
@@ -1,26 +1,75 @@
 use azalea_auth::game_profile::GameProfile;
-use azalea_client::{
-    chat::{ChatPacket, ChatReceivedEvent, SendChatEvent},
-    GameProfileComponent,
-};
+use azalea_client::{chat::ChatReceivedEvent, GameProfileComponent};
 use azalea_world::entity::Local;
 use bevy::prelude::{
-    App, CoreSet, Entity, EventReader, EventWriter, IntoSystemConfig, IntoSystemConfigs, Plugin,
-    Query, Res, Resource, With,
+    App, CoreSet, Entity, EventReader, IntoSystemConfig, IntoSystemConfigs, Plugin, Query, Res,
+    ResMut, Resource, With,
 };
 use flume::{Receiver, Sender};
+use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 use uuid::Uuid;
 
-#[cfg(feature = "bridge")]
-use azalea_protocol::packets::game::clientbound_player_chat_packet::{
-    ChatType, ChatTypeBound, ClientboundPlayerChatPacket, FilterMask, PackedLastSeenMessages,
-    PackedSignedMessageBody,
+mod avatar;
+pub use avatar::{Avatars, DEFAULT_AVATAR_URL, NIL_UUID};
+
+#[cfg(feature = "harness")]
+mod conformance;
+#[cfg(feature = "harness")]
+pub use conformance::{check_adapt, check_builder, check_platform, FakePlatform, FakeSide};
+
+mod filter;
+pub use filter::{
+    CapsFilter, ChatFilter, Direction, FilterAction, FilterChain, FilterContext, LinkFilter,
+    RepeatFilter, UserFilter, WordAction, WordFilter,
 };
+
+mod format;
+
 #[cfg(feature = "bridge")]
-use log::error;
+mod hub;
 #[cfg(feature = "bridge")]
-use std::sync::Arc;
+pub use hub::BridgeHub;
+
+#[cfg(feature = "harness")]
+mod harness;
+#[cfg(feature = "harness")]
+pub use harness::{record, FakeClient, Recorded, RecordedEvent, Replay};
+
+mod message;
+pub use message::{Attachment, AttachmentKind, Author, BridgeMessage, Reply};
+
+mod message_map;
+pub use message_map::{MessageId, MessageMap};
+
+mod mention;
+pub use mention::{split_mentions, Segment};
+
+mod link;
+pub use link::{parse_link_command, Identities, Identity, LinkCodes, LinkPlugin};
+
+mod platform;
+pub use platform::{BridgePlatform, Capabilities, PlatformBuilder};
+
+mod route;
+pub use route::{MessageClass, Route, Routes};
+
+mod scheduler;
+use scheduler::{send_queued_chat, ChatSource};
+pub use scheduler::{ChatLimit, ChatQueue};
+
+mod template;
+pub use template::{EventKind, Template, TemplateValues, Templates};
+
+mod storage;
+#[cfg(feature = "sqlite")]
+pub use storage::SqliteStorage;
+pub use storage::{set_storage, storage, FileStorage, MemoryStorage, Storage};
+
+use log::debug;
+
+// The platform name used for messages from Minecraft
+pub const MINECRAFT_PLATFORM: &str = "minecraft";
 
 #[derive(Debug, Clone)]
 pub struct PluginBridge<T: Clone + Sync + Send + 'static> {
@@ -39,9 +88,10 @@ impl<T: Clone + Sync + Send + 'static> PluginBridge<T> {
                 rx: plugin_rx,
                 tx: client_tx,
                 ignore_list,
+                filters: FilterChain::new(),
+                templates: Templates::new(),
+                routes: Routes::default(),
                 _d: PhantomData,
-                #[cfg(feature = "bridge")]
-                links: vec![],
             },
             plugin: PluginSide {
                 rx: client_rx,
@@ -57,17 +107,29 @@ pub struct ClientSide<T> {
     pub rx: Receiver<PluginEvent>,
     pub tx: Sender<AzaleaEvent>,
     pub ignore_list: Vec<String>,
+    pub filters: FilterChain,
+    pub templates: Templates,
+    pub routes: Routes,
     _d: PhantomData<T>,
-    #[cfg(feature = "bridge")]
-    links: Vec<Sender<AzaleaEvent>>,
 }
 
 impl<T: Clone + Sync + Send + 'static> ClientSide<T> {
-    // Bridge two plugins together and share events
-    #[cfg(feature = "bridge")]
-    pub fn bridge<U: Clone + Sync + Send + 'static>(&mut self, other: &mut ClientSide<U>) {
-        self.links.push(other.tx.clone());
-        other.links.push(self.tx.clone());
+    // Filter messages going to and from the plugin, this should be done before registering with a hub
+    pub fn with_filters(mut self, filters: FilterChain) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    // Change how messages are formatted
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    // Change where messages are sent, this should be done before registering with a hub
+    pub fn with_routes(mut self, routes: Routes) -> Self {
+        self.routes = routes;
+        self
     }
 
     // Send chat events to plugin
@@ -98,11 +160,54 @@ impl<T: Clone + Sync + Send + 'static> ClientSide<T> {
                 continue;
             }
 
-            // Send event to plugin
-            client
-                .tx
-                .send(AzaleaEvent::Chat(profile, event.packet.clone()))
-                .unwrap_or_else(|e| panic!("Unable to send event to plugin: {e}"));
+            // Do not send link codes to other platforms
+            if link::is_link_whisper(&event.packet) {
+                continue;
+            }
+
+            // Filter the message, skipping it if blocked
+            let message = BridgeMessage::from_packet(&event.packet, &profile);
+            let context = FilterContext {
+                direction: Direction::FromMinecraft,
+                platform: MINECRAFT_PLATFORM,
+                user_id: &message.author.id,
+                username: &message.author.name,
+            };
+            let Some(filtered) = client.filters.apply(&context, &message.content) else {
+                continue;
+            };
+
+            let name = template::subject(&event.packet).unwrap_or_else(|| profile.name.clone());
+
+            // Send the message along every route it matches
+            for route in client.routes.for_packet(&event.packet) {
+                let Some(filtered) = route.filters().apply(&context, &filtered) else {
+                    continue;
+                };
+
+                // Format the message for the plugin
+                let values = TemplateValues {
+                    name: &name,
+                    message: &filtered,
+                    platform: MINECRAFT_PLATFORM,
+                };
+                let templates = route.templates().unwrap_or(&client.templates);
+                let mut message = message.clone();
+                // Filtered words shouldn't show up in the formatted message either
+                if filtered != message.content {
+                    message.ansi = None;
+                }
+                message.content = templates
+                    .platform(message.kind)
+                    .render(&values, str::to_string);
+
+                // Send event to plugin
+                let targets = route.targets().to_vec();
+                client
+                    .tx
+                    .send(AzaleaEvent::Chat(message, targets))
+                    .unwrap_or_else(|e| panic!("Unable to send event to plugin: {e}"));
+            }
         }
     }
 
@@ -114,75 +219,93 @@ impl<T: Clone + Sync + Send + 'static> ClientSide<T> {
     // Process events from channel
     pub fn listen_event(
         client: Res<ClientSide<T>>,
-        mut events: EventWriter<SendChatEvent>,
-        query: Query<(Entity, &GameProfileComponent), With<Local>>,
+        limit: Res<ChatLimit>,
+        mut queue: ResMut<ChatQueue>,
+        query: Query<Entity, (With<Local>, With<GameProfileComponent>)>,
     ) {
-        let (entity, _profile) = query.single();
+        // Messages can't be sent to Minecraft until a bot has logged in,
+        // with several bots they are sent by the first one
+        let Some(entity) = query.iter().next() else {
+            let dropped = client.rx.drain().count();
+            debug!("No bot to send {dropped} message(s) to Minecraft");
+            return;
+        };
+
         while let Ok(event) = client.rx.try_recv() {
-            #[cfg(feature = "bridge")]
-            if let Err(e) = Self::link_plugins(&client, &event, _profile) {
-                error!("Unable to send message to linked plugin: {e}");
-            }
+            let Some(event) = client.route_event(event) else {
+                continue;
+            };
 
             match event {
-                PluginEvent::Chat(username, message) => {
-                    for content in format_message(username, message) {
-                        events.send(SendChatEvent { entity, content });
+                PluginEvent::Chat(message) => {
+                    let source = client.chat_source(entity, &message.channel);
+                    for line in message.lines() {
+                        let username = message.author.name.clone();
+                        queue.push(&limit, &source, username, &message, line);
                     }
                 }
-                _ => {}
+                // Minecraft messages can't be edited, so send a follow-up line
+                PluginEvent::Edit(message) => {
+                    let source = client.chat_source(entity, &message.channel);
+                    let username = format!("{} (edited)", message.author.name);
+                    let content = message.content.clone();
+                    queue.push(&limit, &source, username, &message, content);
+                }
+                PluginEvent::Delete(_) => {}
             }
         }
     }
 
-    // Bridge events to other plugins
-    #[cfg(feature = "bridge")]
-    fn link_plugins(
-        client: &ClientSide<T>,
-        event: &PluginEvent,
-        profile: &GameProfileComponent,
-    ) -> anyhow::Result<()> {
-        if client.links.len() == 0 {
-            return Ok(());
-        }
+    // Filter messages from the plugin, returning None if blocked
+    // or if they were sent somewhere not routed to Minecraft
+    fn route_event(&self, event: PluginEvent) -> Option<PluginEvent> {
+        let filter = |mut message: BridgeMessage| {
+            let route = self.routes.for_target(&message.channel)?;
+            let context = FilterContext {
+                direction: Direction::ToMinecraft,
+                platform: &message.id.platform,
+                user_id: &message.author.id,
+                username: &message.author.name,
+            };
+            let content = self.filters.apply(&context, &message.content)?;
+            message.content = route.filters().apply(&context, &content)?;
+            Some(message)
+        };
+
         match event {
-            PluginEvent::Chat(username, message) => {
-                let packet = ClientboundPlayerChatPacket {
-                    sender: profile.uuid.clone(),
-                    index: 0,
-                    signature: None,
-                    body: PackedSignedMessageBody {
-                        content: format!("{username}: {message}"),
-                        timestamp: 0,
-                        salt: 0,
-                        last_seen: PackedLastSeenMessages {
-                            entries: Vec::new(),
-                        },
-                    },
-                    unsigned_content: Some(format!("{username}: {message}").into()),
-                    filter_mask: FilterMask::PassThrough,
-                    chat_type: ChatTypeBound {
-                        chat_type: ChatType::Chat,
-                        name: profile.name.clone().into(),
-                        target_name: None,
-                    },
-                };
+            PluginEvent::Chat(message) => filter(message).map(PluginEvent::Chat),
+            PluginEvent::Edit(message) => filter(message).map(PluginEvent::Edit),
+            PluginEvent::Delete(id) => Some(PluginEvent::Delete(id)),
+        }
+    }
 
-                let packet = ChatPacket::Player(Arc::new(packet));
+    // Where messages from a channel are queued from and how they are shown
+    fn chat_source(&self, entity: Entity, channel: &str) -> ChatSource {
+        let templates = self
+            .routes
+            .for_target(channel)
+            .and_then(Route::templates)
+            .unwrap_or(&self.templates);
 
-                for link in &client.links {
-                    link.send(AzaleaEvent::Chat(profile.0.clone(), packet.clone()))?
-                }
-            }
-            _ => {}
+        ChatSource {
+            entity,
+            name: std::any::type_name::<T>(),
+            notices: self.tx.clone(),
+            template: templates.minecraft().clone(),
         }
-        Ok(())
     }
 }
 
 // Add channel and systems to Bevy
 impl<T: Clone + Sync + Send + 'static> Plugin for ClientSide<T> {
     fn build(&self, app: &mut App) {
+        // Every plugin shares the same queue
+        if !app.world.contains_resource::<ChatQueue>() {
+            app.init_resource::<ChatLimit>()
+                .init_resource::<ChatQueue>()
+                .add_system(send_queued_chat);
+        }
+
         app.insert_resource(self.clone()).add_systems(
             (
                 ClientSide::<T>::listen_chat,
@@ -201,16 +324,26 @@ pub struct PluginSide<T> {
     _d: PhantomData<T>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AzaleaEvent {
-    Chat(GameProfile, ChatPacket),
-    UnusedEnum,
+    // Chat messages and the channels to send them to, or every channel if empty,
+    // which may have been relayed from another plugin
+    Chat(BridgeMessage, Vec<String>),
+    // Edits relayed from another plugin and the channels the original was sent to,
+    // or every channel if empty
+    Edit(BridgeMessage, Vec<String>),
+    // Deletions relayed from another plugin
+    Delete(MessageId),
+    // Something the plugin should tell the users of a channel
+    Notice(String, String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PluginEvent {
-    Chat(String, String),
-    UnusedEnum,
+    Chat(BridgeMessage),
+    // The id is the id of the original message
+    Edit(BridgeMessage),
+    Delete(MessageId),
 }
 
 fn find_profile(
@@ -224,20 +357,3 @@ fn find_profile(
     }
     None
 }
-
-// Limit message length to 254 characters
-fn format_message(name: String, msg: String) -> Vec<String> {
-    let mut message = format!("{name}: {msg}");
-    if message.len() < 255 {
-        return vec![message];
-    }
-
-    let mut result = vec![];
-    while message.len() >= 255 {
-        let (first, second) = message.split_at(254);
-        result.push(first.to_string());
-        message = second.to_string();
-    }
-    result.push(message);
-    result
-}
@@ -0,0 +1,241 @@
+use azalea_client::{
+    chat::{ChatPacket, ChatReceivedEvent, SendChatEvent},
+    GameProfileComponent,
+};
+use azalea_protocol::packets::game::clientbound_player_chat_packet::ChatType;
+use azalea_world::entity::Local;
+use bevy::prelude::{App, Entity, EventReader, EventWriter, Plugin, Query, With};
+use log::{error, info};
+use rand::{distributions::Uniform, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::storage::storage;
+
+// Players whisper this to the bot to get a code
+const LINK_REQUEST: &str = "link";
+// Users post this with the code on other platforms
+const LINK_COMMAND: &str = "!link";
+// How long a code can be used for
+const CODE_LIFETIME: Duration = Duration::from_secs(10 * 60);
+// Where links are saved
+const NAMESPACE: &str = "links";
+
+/// The platform accounts a Minecraft player is known as.
+#[derive(Debug, Clone, Default)]
+pub struct Identities {
+    // Lowercase player name to identity
+    inner: Arc<RwLock<HashMap<String, Identity>>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Identity {
+    pub player: String,
+    // Platform name to user id
+    pub accounts: HashMap<String, String>,
+}
+
+impl Identities {
+    // Shared between every plugin, loaded from storage when first used
+    pub fn global() -> &'static Identities {
+        static GLOBAL: OnceLock<Identities> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            Identities::load().unwrap_or_else(|e| {
+                error!("Unable to load account links: {e}");
+                Identities::default()
+            })
+        })
+    }
+
+    // Load links from storage
+    pub fn load() -> anyhow::Result<Self> {
+        let mut identities = HashMap::new();
+        for (key, value) in storage().entries(NAMESPACE)? {
+            identities.insert(key, serde_json::from_str(&value)?);
+        }
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(identities)),
+        })
+    }
+
+    // Link a player to a platform account
+    pub fn link(&self, player: &str, platform: &str, user_id: impl ToString) {
+        let mut inner = self.inner.write().unwrap();
+
+        let key = player.to_lowercase();
+        let identity = inner.entry(key.clone()).or_insert_with(|| Identity {
+            player: player.to_string(),
+            accounts: HashMap::new(),
+        });
+        identity.player = player.to_string();
+        identity
+            .accounts
+            .insert(platform.to_string(), user_id.to_string());
+
+        if let Err(e) = storage().set_json(NAMESPACE, &key, identity) {
+            error!("Unable to save account link for {player}: {e}");
+        }
+    }
+
+    // Remove a player's link to a platform account
+    pub fn unlink(&self, player: &str, platform: &str) -> Option<String> {
+        let mut inner = self.inner.write().unwrap();
+
+        let key = player.to_lowercase();
+        let identity = inner.get_mut(&key)?;
+        let user_id = identity.accounts.remove(platform);
+
+        let result = if identity.accounts.is_empty() {
+            inner.remove(&key);
+            storage().remove(NAMESPACE, &key)
+        } else {
+            storage().set_json(NAMESPACE, &key, identity)
+        };
+        if let Err(e) = result {
+            error!("Unable to save account link for {player}: {e}");
+        }
+
+        user_id
+    }
+
+    // Get the platform account of a player
+    pub fn user_id(&self, platform: &str, player: &str) -> Option<String> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .get(&player.to_lowercase())?
+            .accounts
+            .get(platform)
+            .cloned()
+    }
+
+    // Get the player a platform account belongs to
+    pub fn player(&self, platform: &str, user_id: &str) -> Option<String> {
+        let inner = self.inner.read().unwrap();
+        inner
+            .values()
+            .find(|identity| identity.accounts.get(platform).map(String::as_str) == Some(user_id))
+            .map(|identity| identity.player.clone())
+    }
+
+    // Get every linked player
+    pub fn all(&self) -> Vec<Identity> {
+        let inner = self.inner.read().unwrap();
+        inner.values().cloned().collect()
+    }
+}
+
+/// One-time codes players use to link their accounts.
+#[derive(Debug, Clone, Default)]
+pub struct LinkCodes {
+    inner: Arc<Mutex<HashMap<String, (String, Instant)>>>,
+}
+
+impl LinkCodes {
+    // Shared between every plugin
+    pub fn global() -> &'static LinkCodes {
+        static GLOBAL: OnceLock<LinkCodes> = OnceLock::new();
+        GLOBAL.get_or_init(LinkCodes::default)
+    }
+
+    // Create a new code for a player, replacing any old one
+    pub fn create(&self, player: &str) -> String {
+        let mut codes = self.inner.lock().unwrap();
+        codes.retain(|_, (name, created)| name != player && created.elapsed() < CODE_LIFETIME);
+
+        let code: String = rand::thread_rng()
+            .sample_iter(Uniform::new_inclusive('A', 'Z'))
+            .take(6)
+            .collect();
+
+        codes.insert(code.clone(), (player.to_string(), Instant::now()));
+        code
+    }
+
+    // Use a code, returning the player it was created for
+    pub fn redeem(&self, code: &str) -> Option<String> {
+        let mut codes = self.inner.lock().unwrap();
+
+        let (player, created) = codes.remove(&code.trim().to_uppercase())?;
+        if created.elapsed() < CODE_LIFETIME {
+            Some(player)
+        } else {
+            None
+        }
+    }
+}
+
+// Get the code from a message like `!link ABCDEF`
+pub fn parse_link_command(content: &str) -> Option<&str> {
+    let code = content.trim().strip_prefix(LINK_COMMAND)?;
+    if code.starts_with(char::is_whitespace) {
+        Some(code.trim())
+    } else {
+        None
+    }
+}
+
+// Whether a message is part of linking an account
+pub(crate) fn is_link_whisper(packet: &ChatPacket) -> bool {
+    let ChatPacket::Player(player) = packet else {
+        return false;
+    };
+
+    match player.chat_type.chat_type {
+        ChatType::MsgCommandIncoming => packet.content().trim().eq_ignore_ascii_case(LINK_REQUEST),
+        // Replies to players are always private
+        ChatType::MsgCommandOutgoing => true,
+        _ => false,
+    }
+}
+
+/// Answers players who whisper `link` to the bot with a one-time code.
+///
+/// Add this once, no matter how many plugins are bridged.
+#[derive(Debug, Clone, Default)]
+pub struct LinkPlugin;
+
+impl Plugin for LinkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(listen_link_requests);
+    }
+}
+
+// Whisper codes back to players
+fn listen_link_requests(
+    mut chat_events: EventReader<ChatReceivedEvent>,
+    mut events: EventWriter<SendChatEvent>,
+    query: Query<Entity, (With<Local>, With<GameProfileComponent>)>,
+) {
+    for event in chat_events.iter() {
+        let ChatPacket::Player(packet) = &event.packet else {
+            continue;
+        };
+        if packet.chat_type.chat_type != ChatType::MsgCommandIncoming
+            || !is_link_whisper(&event.packet)
+        {
+            continue;
+        }
+
+        let Some(player) = event.packet.username() else {
+            continue;
+        };
+        let Ok(entity) = query.get(event.entity) else {
+            continue;
+        };
+
+        let code = LinkCodes::global().create(&player);
+        info!("Created link code for {player}");
+
+        events.send(SendChatEvent {
+            entity,
+            content: format!(
+                "/msg {player} Send \"{LINK_COMMAND} {code}\" in a bridged chat within 10 minutes to link your account"
+            ),
+        });
+    }
+}
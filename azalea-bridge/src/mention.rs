@@ -0,0 +1,50 @@
+/// Part of a message, split around `@name` mentions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    // The mentioned name, without the '@'
+    Mention(&'a str),
+}
+
+// Split a message into text and mentions of Minecraft players
+pub fn split_mentions(content: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut previous = None;
+
+    for (index, c) in content.char_indices() {
+        // Mentions can't be in the middle of a word, like an email address
+        let after_word = previous.map_or(false, is_name_char);
+        previous = Some(c);
+
+        // Skip characters already part of a mention
+        if index < start || c != '@' || after_word {
+            continue;
+        }
+
+        let name_start = index + 1;
+        let name_end = content[name_start..]
+            .find(|c: char| !is_name_char(c))
+            .map_or(content.len(), |end| name_start + end);
+
+        // Minecraft names are 3 to 16 characters long
+        if !(3..=16).contains(&(name_end - name_start)) {
+            continue;
+        }
+
+        if start < index {
+            segments.push(Segment::Text(&content[start..index]));
+        }
+        segments.push(Segment::Mention(&content[name_start..name_end]));
+        start = name_end;
+    }
+
+    if start < content.len() {
+        segments.push(Segment::Text(&content[start..]));
+    }
+    segments
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
@@ -0,0 +1,249 @@
+use azalea_auth::game_profile::GameProfile;
+use azalea_client::chat::ChatPacket;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+use uuid::Uuid;
+
+use crate::{EventKind, MessageId, MINECRAFT_PLATFORM};
+
+/// A chat message from any platform, including Minecraft.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeMessage {
+    // The id on the platform the message was first sent on
+    pub id: MessageId,
+    // The channel or room the message was sent in
+    pub channel: String,
+    // Only Minecraft has joins, leaves and deaths
+    pub kind: EventKind,
+    pub author: Author,
+    pub content: String,
+    // Minecraft messages with their colours as ANSI escape codes, for terminals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ansi: Option<String>,
+    // Saved as milliseconds since the Unix epoch
+    #[serde(with = "millis")]
+    pub timestamp: SystemTime,
+    pub reply: Option<Reply>,
+    pub attachments: Vec<Attachment>,
+}
+
+/// Who sent a message.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Author {
+    // The id of the sender on their platform
+    pub id: String,
+    pub name: String,
+    // A link to their profile picture
+    pub avatar: Option<String>,
+    // The texture id of a Minecraft player's skin, for rendering their head
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skin: Option<String>,
+}
+
+impl Author {
+    pub fn new(id: impl ToString, name: impl ToString) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            avatar: None,
+            skin: None,
+        }
+    }
+}
+
+impl BridgeMessage {
+    pub fn new(id: MessageId, channel: String, author: Author, content: String) -> Self {
+        Self {
+            id,
+            channel,
+            kind: EventKind::Chat,
+            author,
+            content,
+            ansi: None,
+            timestamp: SystemTime::now(),
+            reply: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    // Convert a message received in Minecraft,
+    // with the profile of the sender if it is known
+    pub fn from_packet(packet: &ChatPacket, profile: &GameProfile) -> Self {
+        let name = packet.username().unwrap_or_else(|| profile.name.clone());
+        let uuid = packet.uuid().unwrap_or(profile.uuid);
+        let mut author = Author::new(uuid, name);
+        if uuid == profile.uuid {
+            author.skin = skin_texture(profile);
+        }
+
+        let id = MessageId::new(MINECRAFT_PLATFORM, Uuid::new_v4());
+
+        let mut message = Self::new(id, String::new(), author, packet.content());
+        message.kind = EventKind::of(packet);
+        message.ansi = Some(packet.message().to_ansi());
+        if let ChatPacket::Player(player) = packet {
+            if player.body.timestamp != 0 {
+                message.timestamp =
+                    SystemTime::UNIX_EPOCH + Duration::from_millis(player.body.timestamp);
+            }
+        }
+        message
+    }
+
+    // The lines to show in Minecraft, without the username
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.content.is_empty() {
+            lines.push(self.content.clone());
+        }
+        for attachment in &self.attachments {
+            lines.push(attachment.to_string());
+        }
+
+        // Show what the message is replying to
+        if let Some(reply) = &self.reply {
+            match lines.first_mut() {
+                Some(first) => *first = format!("{reply} {first}"),
+                None => lines.push(reply.to_string()),
+            }
+        }
+        lines
+    }
+}
+
+// The texture id from the skin url in a profile's textures property
+fn skin_texture(profile: &GameProfile) -> Option<String> {
+    let property = profile.properties.get("textures")?;
+    let textures: serde_json::Value =
+        serde_json::from_slice(&STANDARD.decode(&property.value).ok()?).ok()?;
+    let url = textures["textures"]["SKIN"]["url"].as_str()?;
+    url.rsplit('/').next().map(str::to_string)
+}
+
+/// The message a chat message is replying to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reply {
+    pub id: MessageId,
+    pub username: String,
+    pub snippet: String,
+}
+
+impl Reply {
+    pub fn new(id: MessageId, username: String, content: &str) -> Self {
+        Self {
+            id,
+            username,
+            snippet: snippet(content),
+        }
+    }
+}
+
+// Looks like `[reply to Alex: "first words…"]`
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[reply to {}: \"{}\"]", self.username, self.snippet)
+    }
+}
+
+// Shorten a message to its first few words
+fn snippet(content: &str) -> String {
+    const MAX_LENGTH: usize = 32;
+
+    let mut result = String::new();
+    for word in content.split_whitespace() {
+        let length = result.chars().count() + word.chars().count();
+        if length >= MAX_LENGTH {
+            // Cut long words instead of leaving the snippet empty
+            if result.is_empty() {
+                result = word.chars().take(MAX_LENGTH).collect();
+            }
+            result.push('…');
+            break;
+        }
+
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+/// A file, sticker or embed sent with a chat message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    pub name: String,
+    pub url: String,
+    pub mime: Option<String>,
+}
+
+// Looks like `[image: cat.png] https://…`
+impl fmt::Display for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "[{}] {}", self.kind, self.url)
+        } else {
+            write!(f, "[{}: {}] {}", self.kind, self.name, self.url)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Image,
+    Video,
+    Audio,
+    File,
+    Sticker,
+    Embed,
+}
+
+impl AttachmentKind {
+    // Guess the kind of a file from its mime type
+    pub fn from_mime(mime: Option<&str>) -> Self {
+        match mime.and_then(|mime| mime.split('/').next()) {
+            Some("image") => Self::Image,
+            Some("video") => Self::Video,
+            Some("audio") => Self::Audio,
+            _ => Self::File,
+        }
+    }
+}
+
+impl fmt::Display for AttachmentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::File => "file",
+            Self::Sticker => "sticker",
+            Self::Embed => "embed",
+        };
+        f.write_str(kind)
+    }
+}
+
+// Timestamps as milliseconds since the Unix epoch, which is easier to read in JSON
+mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        serializer.serialize_u64(millis as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        Ok(UNIX_EPOCH + Duration::from_millis(u64::deserialize(deserializer)?))
+    }
+}
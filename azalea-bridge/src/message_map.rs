@@ -0,0 +1,214 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use crate::storage::storage;
+
+// How many relayed messages to remember per plugin
+const MAP_CAPACITY: usize = 2048;
+
+/// A message as identified by the platform it was sent on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MessageId {
+    pub platform: String,
+    pub id: String,
+}
+
+impl MessageId {
+    pub fn new(platform: &str, id: impl ToString) -> Self {
+        Self {
+            platform: platform.to_string(),
+            id: id.to_string(),
+        }
+    }
+
+    // Stored as `platform:id`
+    fn key(&self) -> String {
+        format!("{}:{}", self.platform, self.id)
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        let (platform, id) = key.split_once(':')?;
+        Some(Self::new(platform, id))
+    }
+}
+
+/// Remembers which local message was created when relaying a message from another platform.
+///
+/// Every plugin keeps its own map, keyed by the id of the original message,
+/// so edits and deletions can be applied to the relayed copy later.
+/// Maps created with [`MessageMap::open`] are saved to the plugin [`Storage`](crate::Storage).
+#[derive(Debug, Clone, Default)]
+pub struct MessageMap {
+    inner: Arc<Mutex<MessageMapInner>>,
+}
+
+#[derive(Debug, Default)]
+struct MessageMapInner {
+    map: HashMap<MessageId, String>,
+    // The original message for each local id
+    origins: HashMap<String, MessageId>,
+    order: VecDeque<MessageId>,
+    // Where the map is saved
+    namespace: Option<String>,
+}
+
+impl MessageMap {
+    // Load a map from storage and save any changes to it
+    pub fn open(namespace: &str) -> anyhow::Result<Self> {
+        let mut inner = MessageMapInner {
+            namespace: Some(namespace.to_string()),
+            ..Default::default()
+        };
+
+        for (key, local) in storage().entries(namespace)? {
+            if let Some(origin) = MessageId::from_key(&key) {
+                inner.origins.insert(local.clone(), origin.clone());
+                inner.map.insert(origin.clone(), local);
+                inner.order.push_back(origin);
+            }
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(inner)),
+        })
+    }
+
+    // Load the map for one channel or room of a plugin
+    pub fn open_for(namespace: &str, target: &str) -> anyhow::Result<Self> {
+        // Escape anything not allowed in a namespace
+        let mut name = format!("{namespace}-");
+        for c in target.chars() {
+            if c.is_ascii_lowercase() || c.is_ascii_digit() {
+                name.push(c);
+            } else {
+                name.push_str(&format!("_{:x}", c as u32));
+            }
+        }
+        Self::open(&name)
+    }
+
+    // Record that `origin` was relayed as `local`
+    pub fn insert(&self, origin: MessageId, local: impl ToString) {
+        let mut inner = self.inner.lock().unwrap();
+
+        let local = local.to_string();
+        inner.save(&origin, Some(&local));
+        match inner.map.insert(origin.clone(), local.clone()) {
+            Some(previous) => inner.forget_origin(&previous, &origin),
+            None => inner.order.push_back(origin.clone()),
+        }
+        inner.origins.insert(local, origin);
+
+        // Forget the oldest messages
+        while inner.order.len() > MAP_CAPACITY {
+            if let Some(old) = inner.order.pop_front() {
+                if let Some(local) = inner.map.remove(&old) {
+                    inner.forget_origin(&local, &old);
+                }
+                inner.save(&old, None);
+            }
+        }
+    }
+
+    // Get the local id of a relayed message
+    pub fn get(&self, origin: &MessageId) -> Option<String> {
+        self.inner.lock().unwrap().map.get(origin).cloned()
+    }
+
+    // Get the original id of a relayed message
+    pub fn origin(&self, local: &str) -> Option<MessageId> {
+        self.inner.lock().unwrap().origins.get(local).cloned()
+    }
+
+    // Get the local id for a message, which may already be from this platform
+    pub fn local_id(&self, platform: &str, id: &MessageId) -> Option<String> {
+        if id.platform == platform {
+            Some(id.id.clone())
+        } else {
+            self.get(id)
+        }
+    }
+
+    // Forget a relayed message, returning its local id
+    pub fn remove(&self, origin: &MessageId) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let local = inner.map.remove(origin)?;
+        inner.forget_origin(&local, origin);
+        inner.order.retain(|id| id != origin);
+        inner.save(origin, None);
+        Some(local)
+    }
+}
+
+impl MessageMapInner {
+    // Remove a local id from the reverse index, unless it now belongs to another message
+    fn forget_origin(&mut self, local: &str, origin: &MessageId) {
+        if self.origins.get(local) == Some(origin) {
+            self.origins.remove(local);
+        }
+    }
+
+    fn save(&self, origin: &MessageId, local: Option<&str>) {
+        let Some(namespace) = &self.namespace else {
+            return;
+        };
+
+        let result = match local {
+            Some(local) => storage().set(namespace, &origin.key(), local),
+            None => storage().remove(namespace, &origin.key()),
+        };
+        if let Err(e) = result {
+            error!("Unable to save message map {namespace}: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(id: usize) -> MessageId {
+        MessageId::new("test", id)
+    }
+
+    #[test]
+    fn origin_of_local_id() {
+        let map = MessageMap::default();
+        map.insert(id(1), "a");
+        map.insert(id(2), "b");
+        assert_eq!(map.origin("a"), Some(id(1)));
+        assert_eq!(map.origin("b"), Some(id(2)));
+        assert_eq!(map.origin("c"), None);
+    }
+
+    #[test]
+    fn origin_after_changes() {
+        let map = MessageMap::default();
+        map.insert(id(1), "a");
+        map.insert(id(1), "a");
+        assert_eq!(map.origin("a"), Some(id(1)));
+
+        map.insert(id(1), "b");
+        assert_eq!(map.origin("a"), None);
+        assert_eq!(map.origin("b"), Some(id(1)));
+
+        assert_eq!(map.remove(&id(1)).as_deref(), Some("b"));
+        assert_eq!(map.origin("b"), None);
+    }
+
+    #[test]
+    fn forgets_oldest() {
+        let map = MessageMap::default();
+        for i in 0..=MAP_CAPACITY {
+            map.insert(id(i), i);
+        }
+        assert_eq!(map.get(&id(0)), None);
+        assert_eq!(map.origin("0"), None);
+        assert_eq!(map.origin("1"), Some(id(1)));
+    }
+}
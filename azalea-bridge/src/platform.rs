@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+use flume::Sender;
+use log::error;
+use std::fmt::Debug;
+
+use crate::{AzaleaEvent, ClientSide, PluginBridge, PluginEvent, PluginSide, Routes};
+
+/// What a platform can show itself.
+///
+/// Anything it can't is approximated before the event is handled,
+/// the same way Minecraft shows edits and replies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub edits: bool,
+    pub deletes: bool,
+    pub replies: bool,
+    pub attachments: bool,
+    pub avatars: bool,
+}
+
+impl Capabilities {
+    // Everything a platform could support
+    pub fn all() -> Self {
+        Self {
+            edits: true,
+            deletes: true,
+            replies: true,
+            attachments: true,
+            avatars: true,
+        }
+    }
+
+    // Change an event into something the platform can show, or None to drop it
+    pub(crate) fn adapt(&self, event: AzaleaEvent) -> Option<AzaleaEvent> {
+        match event {
+            AzaleaEvent::Chat(mut message, targets) => {
+                if !self.replies {
+                    if let Some(reply) = message.reply.take() {
+                        message.content = format!("{reply} {}", message.content);
+                    }
+                }
+                if !self.attachments {
+                    for attachment in message.attachments.drain(..) {
+                        message.content.push_str(&format!("\n{attachment}"));
+                    }
+                }
+                if !self.avatars {
+                    message.author.avatar = None;
+                }
+                Some(AzaleaEvent::Chat(message, targets))
+            }
+            // Send a follow-up message instead, wherever the original went
+            AzaleaEvent::Edit(mut message, targets) if !self.edits => {
+                message.author.name = format!("{} (edited)", message.author.name);
+                message.reply = None;
+                self.adapt(AzaleaEvent::Chat(message, targets))
+            }
+            AzaleaEvent::Delete(_) if !self.deletes => None,
+            event => Some(event),
+        }
+    }
+}
+
+/// A chat platform that can be bridged to Minecraft.
+///
+/// Use a [`PlatformBuilder`] to start it and get a plugin for Azalea.
+#[async_trait]
+pub trait BridgePlatform: Debug + Clone + Send + Sync + 'static {
+    // The platform name used in message ids
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    // Connect to the platform and send its messages to Azalea
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()>;
+
+    // Show an event from Minecraft or another plugin
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()>;
+
+    // Disconnect once Azalea stops sending events
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Starts a [`BridgePlatform`] and wires it into Azalea.
+#[derive(Debug, Clone)]
+pub struct PlatformBuilder<P> {
+    platform: P,
+    ignore_list: Vec<String>,
+    routes: Option<Routes>,
+}
+
+impl<P: BridgePlatform> PlatformBuilder<P> {
+    pub fn new(platform: P) -> Self {
+        Self {
+            platform,
+            ignore_list: Vec::new(),
+            routes: None,
+        }
+    }
+
+    // Players whose messages are not sent to the platform
+    pub fn ignore_list<S: ToString>(mut self, ignore_list: impl IntoIterator<Item = S>) -> Self {
+        self.ignore_list = ignore_list.into_iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    // Where messages are sent, every channel by default
+    pub fn routes(mut self, routes: Routes) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+
+    // Spawn the platform, this needs to be called inside a Tokio runtime
+    pub fn build(self) -> ClientSide<P> {
+        // Create commucation channel
+        let bridge = PluginBridge::<P>::new(self.ignore_list);
+
+        tokio::spawn(run(self.platform, bridge.plugin));
+
+        // Return a 'ClientSide' Plugin to insert into Azalea
+        match self.routes {
+            Some(routes) => bridge.client.with_routes(routes),
+            None => bridge.client,
+        }
+    }
+}
+
+// Pass events to the platform until Azalea stops sending them
+async fn run<P: BridgePlatform>(platform: P, plugin: PluginSide<P>) {
+    let name = platform.name();
+
+    let connection = {
+        let platform = platform.clone();
+        let tx = plugin.tx;
+        tokio::spawn(async move {
+            if let Err(e) = platform.start(tx).await {
+                error!("{name} bridge stopped: {e}");
+            }
+        })
+    };
+
+    let capabilities = platform.capabilities();
+    while let Ok(event) = plugin.rx.recv_async().await {
+        let Some(event) = capabilities.adapt(event) else {
+            continue;
+        };
+        if let Err(e) = platform.handle(event).await {
+            error!("{name} bridge unable to handle event: {e}");
+        }
+    }
+
+    if let Err(e) = platform.shutdown().await {
+        error!("{name} bridge unable to shut down: {e}");
+    }
+    connection.abort();
+}
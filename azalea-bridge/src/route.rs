@@ -0,0 +1,210 @@
+use azalea_client::chat::ChatPacket;
+use azalea_protocol::packets::game::clientbound_player_chat_packet::ChatType;
+use regex::Regex;
+
+use crate::{FilterChain, Templates};
+
+/// A kind of Minecraft message a [`Route`] carries.
+#[derive(Debug, Clone)]
+pub enum MessageClass {
+    PublicChat,
+    TeamChat,
+    // Whispers sent to the bot
+    Whisper,
+    System,
+    // Any message with content matching the pattern
+    Matching(Regex),
+}
+
+impl MessageClass {
+    pub fn matching(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(MessageClass::Matching(Regex::new(pattern)?))
+    }
+
+    fn matches(&self, packet: &ChatPacket) -> bool {
+        let chat_type = match packet {
+            ChatPacket::Player(player) => Some(&player.chat_type.chat_type),
+            ChatPacket::System(_) => None,
+        };
+
+        match self {
+            MessageClass::PublicChat => matches!(
+                chat_type,
+                Some(ChatType::Chat | ChatType::SayCommand | ChatType::EmoteCommand)
+            ),
+            MessageClass::TeamChat => matches!(
+                chat_type,
+                Some(ChatType::TeamMsgCommandIncoming | ChatType::TeamMsgCommandOutgoing)
+            ),
+            MessageClass::Whisper => matches!(chat_type, Some(ChatType::MsgCommandIncoming)),
+            MessageClass::System => chat_type.is_none(),
+            MessageClass::Matching(pattern) => pattern.is_match(&packet.content()),
+        }
+    }
+}
+
+/// Connects kinds of Minecraft messages to channels or rooms on a platform.
+///
+/// A route with no targets is used for every channel the plugin is in.
+#[derive(Debug, Clone)]
+pub struct Route {
+    classes: Vec<MessageClass>,
+    targets: Vec<String>,
+    // Whether messages from the targets are sent to Minecraft
+    to_minecraft: bool,
+    // Whether messages from the targets are relayed to other plugins by a hub
+    relayed: bool,
+    filters: FilterChain,
+    // Uses the templates of the plugin if not set
+    templates: Option<Templates>,
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Self {
+            classes: vec![
+                MessageClass::PublicChat,
+                MessageClass::TeamChat,
+                MessageClass::Whisper,
+                MessageClass::System,
+            ],
+            targets: Vec::new(),
+            to_minecraft: true,
+            relayed: true,
+            filters: FilterChain::new(),
+            templates: None,
+        }
+    }
+}
+
+impl Route {
+    // Send every kind of message to and from a channel or room
+    pub fn new(target: impl ToString) -> Self {
+        Self::default().target(target)
+    }
+
+    // Also send messages to and from another channel or room
+    pub fn target(mut self, target: impl ToString) -> Self {
+        self.targets.push(target.to_string());
+        self
+    }
+
+    // Only send these kinds of messages
+    pub fn classes(mut self, classes: impl IntoIterator<Item = MessageClass>) -> Self {
+        self.classes = classes.into_iter().collect();
+        self
+    }
+
+    // Don't send messages from the targets to Minecraft
+    pub fn one_way(mut self) -> Self {
+        self.to_minecraft = false;
+        self
+    }
+
+    // Don't relay messages from the targets to other plugins, like commands for the bot
+    pub fn local(mut self) -> Self {
+        self.relayed = false;
+        self
+    }
+
+    // Filter messages after the filters of the plugin
+    pub fn with_filters(mut self, filters: FilterChain) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    // Use these templates instead of the ones of the plugin
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
+    pub fn targets(&self) -> &[String] {
+        &self.targets
+    }
+
+    pub(crate) fn filters(&self) -> &FilterChain {
+        &self.filters
+    }
+
+    pub(crate) fn templates(&self) -> Option<&Templates> {
+        self.templates.as_ref()
+    }
+
+    fn has_target(&self, target: &str) -> bool {
+        self.targets.is_empty() || self.targets.iter().any(|t| t == target)
+    }
+}
+
+/// The routes a plugin sends messages along.
+///
+/// By default every message goes to every channel the plugin is in.
+#[derive(Debug, Clone)]
+pub struct Routes {
+    routes: Vec<Route>,
+}
+
+impl Default for Routes {
+    fn default() -> Self {
+        Self {
+            routes: vec![Route::default()],
+        }
+    }
+}
+
+impl Routes {
+    // An empty table, add routes with `with`
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    pub fn with(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    // Every channel or room used by a route
+    pub fn targets(&self) -> Vec<String> {
+        let mut targets: Vec<String> = Vec::new();
+        for target in self.routes.iter().flat_map(|route| &route.targets) {
+            if !targets.contains(target) {
+                targets.push(target.clone());
+            }
+        }
+        targets
+    }
+
+    // The routes a Minecraft message should be sent along
+    pub fn for_packet<'a>(&'a self, packet: &'a ChatPacket) -> impl Iterator<Item = &'a Route> {
+        self.routes
+            .iter()
+            .filter(move |route| route.classes.iter().any(|class| class.matches(packet)))
+    }
+
+    // The routes a message from another plugin should be sent along,
+    // which are the ones carrying public chat
+    pub(crate) fn for_relay<'a>(&'a self, content: &'a str) -> impl Iterator<Item = &'a Route> {
+        self.routes.iter().filter(move |route| {
+            route.classes.iter().any(|class| match class {
+                MessageClass::PublicChat => true,
+                MessageClass::Matching(pattern) => pattern.is_match(content),
+                _ => false,
+            })
+        })
+    }
+
+    // Whether messages from a channel or room can be relayed to other plugins
+    pub fn is_relayed(&self, target: &str) -> bool {
+        !self
+            .routes
+            .iter()
+            .any(|route| !route.relayed && route.has_target(target))
+    }
+
+    // The route messages from a channel or room are sent to Minecraft along
+    pub fn for_target(&self, target: &str) -> Option<&Route> {
+        self.routes
+            .iter()
+            .find(|route| route.to_minecraft && route.has_target(target))
+    }
+}
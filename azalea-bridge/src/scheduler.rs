@@ -0,0 +1,283 @@
+use azalea_client::chat::SendChatEvent;
+use bevy::prelude::{App, Entity, EventWriter, Plugin, Res, ResMut, Resource};
+use flume::Sender;
+use log::error;
+use std::{collections::VecDeque, time::Instant};
+
+use crate::{
+    format::{limit_lines, split_message, MAX_LINES},
+    template::Template,
+    AzaleaEvent, BridgeMessage,
+};
+
+/// Limits how fast messages from plugins are sent to the server.
+///
+/// The defaults stay under the vanilla anti-spam limit,
+/// add this as a plugin to change them.
+#[derive(Debug, Clone, Resource)]
+pub struct ChatLimit {
+    // Messages that can be sent each second
+    pub per_second: f32,
+    // Messages that can be sent at once after waiting
+    pub burst: f32,
+    // Most lines that can be waiting to be sent
+    pub max_queued: usize,
+}
+
+impl Default for ChatLimit {
+    fn default() -> Self {
+        Self {
+            per_second: 1.0,
+            burst: 5.0,
+            max_queued: 12,
+        }
+    }
+}
+
+impl Plugin for ChatLimit {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.clone());
+    }
+}
+
+// Messages waiting to be sent, shared by every plugin
+#[derive(Debug, Default, Resource)]
+pub struct ChatQueue {
+    messages: VecDeque<QueuedMessage>,
+    tokens: f32,
+    last_update: Option<Instant>,
+}
+
+// Where queued messages come from and how they are shown
+#[derive(Debug, Clone)]
+pub(crate) struct ChatSource {
+    pub entity: Entity,
+    // The plugin that sent the message
+    pub name: &'static str,
+    pub notices: Sender<AzaleaEvent>,
+    pub template: Template,
+}
+
+#[derive(Debug)]
+struct QueuedMessage {
+    source: ChatSource,
+    username: String,
+    platform: String,
+    channel: String,
+    content: String,
+    lines: VecDeque<String>,
+    // Whether any lines have been sent yet
+    started: bool,
+    // Commands and replies are never merged
+    alone: bool,
+}
+
+impl ChatQueue {
+    // Queue a message, merging it with the last message if it's from the same user and channel,
+    // commands are always sent on their own so they aren't run with the wrong arguments,
+    // and so are replies so they aren't joined to what they didn't reply to
+    pub(crate) fn push(
+        &mut self,
+        limit: &ChatLimit,
+        source: &ChatSource,
+        username: String,
+        origin: &BridgeMessage,
+        content: String,
+    ) {
+        // Check the message as it was sent, a reply in front of it would hide a command
+        let alone = origin.reply.is_some() || is_command(&origin.content) || is_command(&content);
+        let merge = self.messages.back().map_or(false, |last| {
+            !last.started
+                && last.source.entity == source.entity
+                && last.source.name == source.name
+                && last.username == username
+                && last.platform == origin.id.platform
+                && last.channel == origin.channel
+                && !last.alone
+                && !alone
+        });
+
+        let mut message = if merge {
+            let mut last = self.messages.pop_back().unwrap();
+            last.content = format!("{} {content}", last.content);
+            last
+        } else {
+            QueuedMessage {
+                source: source.clone(),
+                username,
+                platform: origin.id.platform.clone(),
+                channel: origin.channel.clone(),
+                content,
+                lines: VecDeque::new(),
+                started: false,
+                alone,
+            }
+        };
+
+        // Only send what fits in the queue
+        let available = limit.max_queued.saturating_sub(self.queued_lines());
+        let mut lines = split_message(
+            &message.source.template,
+            &message.username,
+            &message.platform,
+            &message.content,
+        );
+        let removed = limit_lines(&mut lines, available.min(MAX_LINES));
+
+        // Let the sender know their message was cut off
+        if removed > 0 {
+            let notice = format!(
+                "Message from {} was too long, {removed} line(s) were not sent to Minecraft",
+                message.username
+            );
+            let notice = AzaleaEvent::Notice(notice, message.channel.clone());
+            if let Err(e) = message.source.notices.send(notice) {
+                error!("Unable to send notice to plugin: {e}");
+            }
+        }
+
+        message.lines = lines.into();
+        if !message.lines.is_empty() {
+            self.messages.push_back(message);
+        }
+    }
+
+    fn queued_lines(&self) -> usize {
+        self.messages
+            .iter()
+            .map(|message| message.lines.len())
+            .sum()
+    }
+
+    // Regain sending budget over time
+    fn refill(&mut self, limit: &ChatLimit) {
+        let now = Instant::now();
+        self.tokens = match self.last_update {
+            Some(last) => {
+                let elapsed = now.duration_since(last).as_secs_f32();
+                (self.tokens + elapsed * limit.per_second).min(limit.burst)
+            }
+            None => limit.burst,
+        };
+        self.last_update = Some(now);
+    }
+}
+
+fn is_command(content: &str) -> bool {
+    content.starts_with('/')
+}
+
+// Send queued messages as the budget allows
+pub(crate) fn send_queued_chat(
+    limit: Res<ChatLimit>,
+    mut queue: ResMut<ChatQueue>,
+    mut events: EventWriter<SendChatEvent>,
+) {
+    queue.refill(&limit);
+
+    let ChatQueue {
+        messages, tokens, ..
+    } = &mut *queue;
+
+    while *tokens >= 1.0 {
+        let Some(message) = messages.front_mut() else {
+            break;
+        };
+
+        message.started = true;
+        if let Some(content) = message.lines.pop_front() {
+            events.send(SendChatEvent {
+                entity: message.source.entity,
+                content,
+            });
+            *tokens -= 1.0;
+        }
+
+        if message.lines.is_empty() {
+            messages.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Author, MessageId, Reply, Templates};
+
+    fn message(content: &str) -> BridgeMessage {
+        BridgeMessage::new(
+            MessageId::new("console", content),
+            String::new(),
+            Author::new("console", "Bot"),
+            content.to_string(),
+        )
+    }
+
+    fn push(queue: &mut ChatQueue, source: &ChatSource, content: &str) {
+        push_message(queue, source, &message(content));
+    }
+
+    // Push each line of a message like plugins do
+    fn push_message(queue: &mut ChatQueue, source: &ChatSource, origin: &BridgeMessage) {
+        let limit = ChatLimit::default();
+        for line in origin.lines() {
+            queue.push(&limit, source, "Bot".into(), origin, line);
+        }
+    }
+
+    fn contents(queue: &ChatQueue) -> Vec<&str> {
+        queue.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    fn source() -> ChatSource {
+        ChatSource {
+            entity: Entity::from_raw(0),
+            name: "test",
+            notices: flume::unbounded().0,
+            template: Templates::new().minecraft().clone(),
+        }
+    }
+
+    #[test]
+    fn merges_chat() {
+        let (mut queue, source) = (ChatQueue::default(), source());
+        push(&mut queue, &source, "hello");
+        push(&mut queue, &source, "there");
+        assert_eq!(queue.messages.len(), 1);
+        assert_eq!(queue.messages[0].content, "hello there");
+    }
+
+    #[test]
+    fn keeps_commands_apart() {
+        let (mut queue, source) = (ChatQueue::default(), source());
+        push(&mut queue, &source, "/time set day");
+        push(&mut queue, &source, "/weather clear");
+        push(&mut queue, &source, "done");
+        assert_eq!(
+            contents(&queue),
+            ["/time set day", "/weather clear", "done"]
+        );
+    }
+
+    #[test]
+    fn keeps_replies_apart() {
+        let (mut queue, source) = (ChatQueue::default(), source());
+        push(&mut queue, &source, "anyone online?");
+        let mut reply = message("on my way");
+        reply.reply = Some(Reply::new(
+            MessageId::new("minecraft", "1"),
+            "Notch".into(),
+            "yes",
+        ));
+        push_message(&mut queue, &source, &reply);
+        push(&mut queue, &source, "see you");
+        assert_eq!(
+            contents(&queue),
+            [
+                "anyone online?",
+                "[reply to Notch: \"yes\"] on my way",
+                "see you"
+            ]
+        );
+    }
+}
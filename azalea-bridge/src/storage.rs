@@ -0,0 +1,394 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+
+/// Key-value storage for plugin state that should survive restarts.
+///
+/// Values are grouped by namespace, which should only contain
+/// lowercase letters, numbers, `-` and `_`.
+pub trait Storage: Debug + Send + Sync {
+    fn get(&self, namespace: &str, key: &str) -> anyhow::Result<Option<String>>;
+    fn set(&self, namespace: &str, key: &str, value: &str) -> anyhow::Result<()>;
+    fn remove(&self, namespace: &str, key: &str) -> anyhow::Result<()>;
+    fn entries(&self, namespace: &str) -> anyhow::Result<Vec<(String, String)>>;
+}
+
+impl dyn Storage {
+    pub fn get_json<T: DeserializeOwned>(
+        &self,
+        namespace: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<T>> {
+        match self.get(namespace, key)? {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_json<T: Serialize>(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &T,
+    ) -> anyhow::Result<()> {
+        self.set(namespace, key, &serde_json::to_string(value)?)
+    }
+}
+
+// Storage used by every plugin
+fn global() -> &'static RwLock<Arc<dyn Storage>> {
+    static GLOBAL: OnceLock<RwLock<Arc<dyn Storage>>> = OnceLock::new();
+    GLOBAL.get_or_init(|| RwLock::new(Arc::new(MemoryStorage::default())))
+}
+
+// Get the storage used by every plugin, which is in memory unless set
+pub fn storage() -> Arc<dyn Storage> {
+    global().read().unwrap().clone()
+}
+
+// Set the storage used by every plugin,
+// this should be done before creating any plugins
+pub fn set_storage(storage: impl Storage + 'static) {
+    *global().write().unwrap() = Arc::new(storage);
+}
+
+/// Keeps everything in memory, nothing is saved.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    inner: Mutex<HashMap<String, BTreeMap<String, String>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn get(&self, namespace: &str, key: &str) -> anyhow::Result<Option<String>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.get(namespace).and_then(|map| map.get(key)).cloned())
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: &str) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(map) = inner.get_mut(namespace) {
+            map.remove(key);
+        }
+        Ok(())
+    }
+
+    fn entries(&self, namespace: &str) -> anyhow::Result<Vec<(String, String)>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .get(namespace)
+            .map(|map| map.clone().into_iter().collect())
+            .unwrap_or_default())
+    }
+}
+
+/// Saves each namespace as a JSON file in a directory.
+///
+/// Changes are appended to a log next to the file,
+/// which is only rewritten once the log gets long.
+#[derive(Debug)]
+pub struct FileStorage {
+    directory: PathBuf,
+    // Namespaces that have been read from disk
+    cache: Mutex<HashMap<String, Namespace>>,
+}
+
+#[derive(Debug, Default)]
+struct Namespace {
+    map: BTreeMap<String, String>,
+    // Changes in the log since the file was written
+    logged: usize,
+}
+
+// Changes to log before rewriting a namespace, more if the namespace is bigger
+const MIN_LOGGED: usize = 1024;
+
+impl FileStorage {
+    pub fn new(directory: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // The file for a namespace and the log of changes to it
+    fn paths(&self, namespace: &str) -> anyhow::Result<(PathBuf, PathBuf)> {
+        let valid = namespace
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if namespace.is_empty() || !valid {
+            return Err(anyhow::Error::msg(format!(
+                "Invalid namespace: {namespace}"
+            )));
+        }
+        Ok((
+            self.directory.join(format!("{namespace}.json")),
+            self.directory.join(format!("{namespace}.log")),
+        ))
+    }
+
+    // Run a function on a namespace, reading it from disk if needed
+    fn with_namespace<R>(
+        &self,
+        namespace: &str,
+        f: impl FnOnce(&mut Namespace) -> R,
+    ) -> anyhow::Result<R> {
+        let mut cache = self.cache.lock().unwrap();
+
+        if !cache.contains_key(namespace) {
+            let loaded = self.load(namespace)?;
+            cache.insert(namespace.to_string(), loaded);
+        }
+
+        Ok(f(cache.get_mut(namespace).unwrap()))
+    }
+
+    fn load(&self, namespace: &str) -> anyhow::Result<Namespace> {
+        let (path, log) = self.paths(namespace)?;
+        let mut loaded = Namespace::default();
+        if path.exists() {
+            loaded.map = serde_json::from_str(&fs::read_to_string(path)?)?;
+        }
+        if !log.exists() {
+            return Ok(loaded);
+        }
+
+        for line in fs::read_to_string(&log)?.lines() {
+            // The last change is cut off if the program stopped while writing it
+            let Ok((key, value)) = serde_json::from_str::<(String, Option<String>)>(line) else {
+                break;
+            };
+            match value {
+                Some(value) => loaded.map.insert(key, value),
+                None => loaded.map.remove(&key),
+            };
+        }
+
+        // Start a new log, so nothing is appended after a cut off change
+        self.compact(namespace, &mut loaded)?;
+        Ok(loaded)
+    }
+
+    // Log a change, rewriting the namespace instead if the log is long enough
+    fn append(
+        &self,
+        namespace: &str,
+        loaded: &mut Namespace,
+        key: &str,
+        value: Option<&str>,
+    ) -> anyhow::Result<()> {
+        if loaded.logged >= MIN_LOGGED.max(loaded.map.len()) {
+            return self.compact(namespace, loaded);
+        }
+
+        let (_, log) = self.paths(namespace)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+        writeln!(file, "{}", serde_json::to_string(&(key, value))?)?;
+        loaded.logged += 1;
+        Ok(())
+    }
+
+    // Write the whole namespace and remove the log
+    fn compact(&self, namespace: &str, loaded: &mut Namespace) -> anyhow::Result<()> {
+        let (path, log) = self.paths(namespace)?;
+
+        // Write to a temporary file first so a crash can't corrupt it,
+        // the log can be applied again if it isn't removed
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(&loaded.map)?)?;
+        fs::rename(temp, path)?;
+        if log.exists() {
+            fs::remove_file(log)?;
+        }
+        loaded.logged = 0;
+        Ok(())
+    }
+}
+
+impl Storage for FileStorage {
+    fn get(&self, namespace: &str, key: &str) -> anyhow::Result<Option<String>> {
+        self.with_namespace(namespace, |loaded| loaded.map.get(key).cloned())
+    }
+
+    // Saved while the cache is locked, so writes can't happen out of order
+    fn set(&self, namespace: &str, key: &str, value: &str) -> anyhow::Result<()> {
+        self.with_namespace(namespace, |loaded| {
+            loaded.map.insert(key.to_string(), value.to_string());
+            self.append(namespace, loaded, key, Some(value))
+        })?
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> anyhow::Result<()> {
+        self.with_namespace(namespace, |loaded| match loaded.map.remove(key) {
+            Some(_) => self.append(namespace, loaded, key, None),
+            None => Ok(()),
+        })?
+    }
+
+    fn entries(&self, namespace: &str) -> anyhow::Result<Vec<(String, String)>> {
+        self.with_namespace(namespace, |loaded| loaded.map.clone().into_iter().collect())
+    }
+}
+
+/// Saves everything in a single SQLite database.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    pub fn new(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS storage (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (namespace, key)
+            )",
+            (),
+        )?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn get(&self, namespace: &str, key: &str) -> anyhow::Result<Option<String>> {
+        use rusqlite::OptionalExtension;
+
+        let connection = self.connection.lock().unwrap();
+        let value = connection
+            .query_row(
+                "SELECT value FROM storage WHERE namespace = ?1 AND key = ?2",
+                (namespace, key),
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set(&self, namespace: &str, key: &str, value: &str) -> anyhow::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT OR REPLACE INTO storage (namespace, key, value) VALUES (?1, ?2, ?3)",
+            (namespace, key, value),
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, namespace: &str, key: &str) -> anyhow::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "DELETE FROM storage WHERE namespace = ?1 AND key = ?2",
+            (namespace, key),
+        )?;
+        Ok(())
+    }
+
+    fn entries(&self, namespace: &str) -> anyhow::Result<Vec<(String, String)>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement =
+            connection.prepare("SELECT key, value FROM storage WHERE namespace = ?1")?;
+        let rows = statement.query_map((namespace,), |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            entries.push(row?);
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> PathBuf {
+        std::env::temp_dir().join(format!("azalea-storage-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn file_storage_reopens() {
+        let directory = directory();
+        let storage = FileStorage::new(&directory).unwrap();
+        storage.set("test", "a", "1").unwrap();
+        storage.set("test", "b", "2").unwrap();
+        storage.remove("test", "a").unwrap();
+        drop(storage);
+
+        let storage = FileStorage::new(&directory).unwrap();
+        assert_eq!(storage.get("test", "a").unwrap(), None);
+        assert_eq!(storage.get("test", "b").unwrap().as_deref(), Some("2"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn file_storage_appends_instead_of_rewriting() {
+        let directory = directory();
+        let storage = FileStorage::new(&directory).unwrap();
+        for i in 0..10 {
+            storage.set("test", &i.to_string(), "value").unwrap();
+        }
+
+        // Nothing has been compacted yet
+        assert!(!directory.join("test.json").exists());
+        let log = fs::read_to_string(directory.join("test.log")).unwrap();
+        assert_eq!(log.lines().count(), 10);
+
+        // Until the log gets long enough
+        for i in 0..MIN_LOGGED {
+            storage.set("test", &i.to_string(), "other").unwrap();
+        }
+        assert!(directory.join("test.json").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn file_storage_ignores_cut_off_changes() {
+        let directory = directory();
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("test.log"), "[\"a\",\"1\"]\n[\"b\",\"2").unwrap();
+
+        let storage = FileStorage::new(&directory).unwrap();
+        assert_eq!(storage.get("test", "a").unwrap().as_deref(), Some("1"));
+        assert_eq!(storage.get("test", "b").unwrap(), None);
+
+        // The log was started again, so new changes aren't lost after the cut off one
+        storage.set("test", "c", "3").unwrap();
+        drop(storage);
+        let storage = FileStorage::new(&directory).unwrap();
+        assert_eq!(storage.get("test", "c").unwrap().as_deref(), Some("3"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_namespace() {
+        let directory = directory();
+        let storage = FileStorage::new(&directory).unwrap();
+        assert!(storage.set("../escape", "a", "1").is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
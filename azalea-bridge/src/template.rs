@@ -0,0 +1,292 @@
+use azalea_chat::{FormattedText, StringOrComponent};
+use azalea_client::chat::ChatPacket;
+use serde::{Deserialize, Serialize};
+
+/// A message format like `[D] {name}: {message}`.
+///
+/// Templates can use `{name}`, `{message}` and `{platform}`,
+/// write `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Message,
+    Platform,
+}
+
+impl Template {
+    pub fn new(template: &str) -> anyhow::Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    if chars.as_str().starts_with('{') {
+                        chars.next();
+                        text.push('{');
+                        continue;
+                    }
+
+                    let Some(end) = chars.as_str().find('}') else {
+                        return Err(anyhow::Error::msg(format!(
+                            "Unclosed placeholder in template: {template}"
+                        )));
+                    };
+                    let field = match &chars.as_str()[..end] {
+                        "name" => Field::Name,
+                        "message" => Field::Message,
+                        "platform" => Field::Platform,
+                        other => {
+                            return Err(anyhow::Error::msg(format!(
+                                "Unknown placeholder {{{other}}} in template: {template}"
+                            )))
+                        }
+                    };
+                    chars = chars.as_str()[end + 1..].chars();
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                '}' => {
+                    if !chars.as_str().starts_with('}') {
+                        return Err(anyhow::Error::msg(format!(
+                            "Unmatched '}}' in template: {template}"
+                        )));
+                    }
+                    chars.next();
+                    text.push('}');
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    // Fill in the template, escaping values but not the template itself
+    pub fn render(&self, values: &TemplateValues<'_>, escape: impl Fn(&str) -> String) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => result.push_str(text),
+                Part::Field(Field::Name) => result.push_str(&escape(values.name)),
+                Part::Field(Field::Message) => result.push_str(&escape(values.message)),
+                Part::Field(Field::Platform) => result.push_str(&escape(values.platform)),
+            }
+        }
+        result
+    }
+
+    fn has_message(&self) -> bool {
+        self.parts.contains(&Part::Field(Field::Message))
+    }
+
+    // The first character of the template itself that the server rejects,
+    // filled in values are sanitized when rendering
+    fn rejected_char(&self) -> Option<char> {
+        self.parts.iter().find_map(|part| match part {
+            Part::Text(text) => text.chars().find(|&c| c == '§' || c.is_control()),
+            Part::Field(_) => None,
+        })
+    }
+}
+
+/// The values a [`Template`] is filled in with.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateValues<'a> {
+    pub name: &'a str,
+    pub message: &'a str,
+    pub platform: &'a str,
+}
+
+/// The kinds of Minecraft messages that can have their own template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    #[default]
+    Chat,
+    Join,
+    Leave,
+    Death,
+}
+
+impl EventKind {
+    // Work out what a message is from its translation key
+    pub fn of(packet: &ChatPacket) -> Self {
+        let ChatPacket::System(system) = packet else {
+            return EventKind::Chat;
+        };
+        let FormattedText::Translatable(translatable) = &system.content else {
+            return EventKind::Chat;
+        };
+
+        match translatable.key.as_str() {
+            key if key.starts_with("multiplayer.player.joined") => EventKind::Join,
+            "multiplayer.player.left" => EventKind::Leave,
+            key if key.starts_with("death.") => EventKind::Death,
+            _ => EventKind::Chat,
+        }
+    }
+}
+
+/// The templates a plugin uses for each direction and kind of message.
+///
+/// By default messages are sent unchanged, with the sender's name
+/// before messages sent to Minecraft.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    to_minecraft: Template,
+    chat: Template,
+    join: Template,
+    leave: Template,
+    death: Template,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        let message = Template {
+            parts: vec![Part::Field(Field::Message)],
+        };
+
+        Self {
+            to_minecraft: Template {
+                parts: vec![
+                    Part::Field(Field::Name),
+                    Part::Text(": ".to_string()),
+                    Part::Field(Field::Message),
+                ],
+            },
+            chat: message.clone(),
+            join: message.clone(),
+            leave: message.clone(),
+            death: message,
+        }
+    }
+}
+
+impl Templates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Set the format of messages sent to Minecraft, vanilla servers kick players who send '§'
+    // or control characters so templates with them are errors, like "§9[M]§r {name}: {message}",
+    // colours have to be added by the server instead
+    pub fn with_minecraft(mut self, template: &str) -> anyhow::Result<Self> {
+        let template = Template::new(template)?;
+        if !template.has_message() {
+            return Err(anyhow::Error::msg(
+                "Templates for Minecraft must include {message}",
+            ));
+        }
+        if let Some(c) = template.rejected_char() {
+            return Err(anyhow::Error::msg(format!(
+                "Templates for Minecraft can't include {c:?}, the server would kick the bot"
+            )));
+        }
+        self.to_minecraft = template;
+        Ok(self)
+    }
+
+    // Set the format of a kind of message sent from Minecraft
+    pub fn with_platform(mut self, kind: EventKind, template: &str) -> anyhow::Result<Self> {
+        let template = Template::new(template)?;
+        match kind {
+            EventKind::Chat => self.chat = template,
+            EventKind::Join => self.join = template,
+            EventKind::Leave => self.leave = template,
+            EventKind::Death => self.death = template,
+        }
+        Ok(self)
+    }
+
+    pub(crate) fn minecraft(&self) -> &Template {
+        &self.to_minecraft
+    }
+
+    pub(crate) fn platform(&self, kind: EventKind) -> &Template {
+        match kind {
+            EventKind::Chat => &self.chat,
+            EventKind::Join => &self.join,
+            EventKind::Leave => &self.leave,
+            EventKind::Death => &self.death,
+        }
+    }
+}
+
+// The player a message is about, like the player who joined
+pub(crate) fn subject(packet: &ChatPacket) -> Option<String> {
+    let ChatPacket::System(system) = packet else {
+        return packet.username();
+    };
+    let FormattedText::Translatable(translatable) = &system.content else {
+        return None;
+    };
+
+    match translatable.args.first()? {
+        StringOrComponent::String(name) => Some(name.clone()),
+        StringOrComponent::FormattedText(name) => Some(name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minecraft_rejects_section_sign() {
+        assert!(Templates::new()
+            .with_minecraft("§9[M]§r {name}: {message}")
+            .is_err());
+    }
+
+    #[test]
+    fn minecraft_rejects_control_characters() {
+        assert!(Templates::new()
+            .with_minecraft("{name}:\n{message}")
+            .is_err());
+        assert!(Templates::new()
+            .with_minecraft("\u{7}{name}: {message}")
+            .is_err());
+    }
+
+    #[test]
+    fn minecraft_accepts_plain_text() {
+        let templates = Templates::new()
+            .with_minecraft("[M] {name}: {message}")
+            .unwrap();
+        let values = TemplateValues {
+            name: "Steve",
+            message: "§4hi",
+            platform: "discord",
+        };
+        let rendered = templates
+            .minecraft()
+            .render(&values, |value| value.replace('§', ""));
+        assert_eq!(rendered, "[M] Steve: 4hi");
+    }
+
+    #[test]
+    fn platform_templates_allow_anything() {
+        assert!(Templates::new()
+            .with_platform(EventKind::Chat, "**{name}**\n{message}")
+            .is_ok());
+    }
+}
@@ -0,0 +1,84 @@
+use azalea_bridge::{AzaleaEvent, FakeClient, PluginBridge, Replay, Route, Routes};
+
+const DISCORD_CHANNEL: &str = "1000000000000000000";
+const MATRIX_ROOM: &str = "!bridged:example.org";
+
+#[derive(Debug, Clone)]
+struct Plugin;
+
+// Sessions written by hand in the format `azalea_bridge::record` uses,
+// with the ids and channels Discord and Matrix would give
+fn replay(name: &str) -> Replay {
+    let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    Replay::open(path).unwrap()
+}
+
+// Messages from Discord show up in Minecraft the same way every time
+#[test]
+fn discord_chat_in_minecraft() {
+    let bridge = PluginBridge::<Plugin>::new(Vec::new());
+    let client = bridge
+        .client
+        .with_routes(Routes::new().with(Route::new(DISCORD_CHANNEL)));
+    let mut client = FakeClient::new(client);
+
+    replay("discord.jsonl")
+        .play_from_plugin(&bridge.plugin)
+        .unwrap();
+
+    // Replies aren't joined to the messages before them, or their own attachments,
+    // and the message from the channel that isn't bridged is dropped
+    client.assert_sent(&[
+        "Alex: anyone online?",
+        "Alex: [reply to Notch: \"yes, at spawn\"] on my way",
+        "Alex: [image: map.png] https://cdn.example.com/map.png",
+    ]);
+}
+
+// Minecraft chat for Discord is played back with the channels it was sent to
+#[test]
+fn minecraft_chat_for_discord() {
+    let bridge = PluginBridge::<Plugin>::new(Vec::new());
+    replay("discord.jsonl")
+        .play_to_plugin(&bridge.client)
+        .unwrap();
+
+    let events: Vec<AzaleaEvent> = bridge.plugin.rx.try_iter().collect();
+    assert!(matches!(
+        &events[..],
+        [AzaleaEvent::Chat(message, targets)]
+            if message.author.name == "Notch" && *targets == [DISCORD_CHANNEL]
+    ));
+}
+
+// Edits from Matrix are sent to Minecraft as a follow-up, and deletions can't be shown
+#[test]
+fn matrix_edits_in_minecraft() {
+    let bridge = PluginBridge::<Plugin>::new(Vec::new());
+    let routes = Routes::new().with(Route::new(MATRIX_ROOM));
+    let mut client = FakeClient::new(bridge.client.with_routes(routes));
+
+    replay("matrix.jsonl")
+        .play_from_plugin(&bridge.plugin)
+        .unwrap();
+
+    client.assert_sent(&[
+        "alice: hello from matrix",
+        "alice (edited): hello from Matrix!",
+    ]);
+}
+
+// Deletions relayed from other plugins are played back for Matrix to apply
+#[test]
+fn deletions_for_matrix() {
+    let bridge = PluginBridge::<Plugin>::new(Vec::new());
+    replay("matrix.jsonl")
+        .play_to_plugin(&bridge.client)
+        .unwrap();
+
+    let events: Vec<AzaleaEvent> = bridge.plugin.rx.try_iter().collect();
+    assert!(matches!(
+        &events[..],
+        [AzaleaEvent::Delete(id)] if id.platform == "discord"
+    ));
+}
@@ -0,0 +1,232 @@
+use azalea_bridge::{Author, BridgeMessage, MessageId, PluginEvent};
+use flume::Sender;
+use log::error;
+use rustyline::{error::ReadlineError, DefaultEditor};
+use uuid::Uuid;
+
+use crate::{output::notice_line, Console, ConsoleConfig};
+
+pub(crate) const PLATFORM: &str = "console";
+
+const HELP: &str = "/bots lists bots, /switch <name or number> changes the active bot, \
+                    start a line with // to send a command to Minecraft";
+
+impl Console {
+    // Read lines until the terminal closes, then let the program know
+    pub(crate) fn read_lines(
+        &self,
+        mut editor: DefaultEditor,
+        config: ConsoleConfig,
+        closed: Sender<()>,
+    ) {
+        loop {
+            let line = match editor.readline(&config.prompt) {
+                Ok(line) => line,
+                // The terminal catches Ctrl-C, so it closes the console like Ctrl-D
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                Err(e) => {
+                    error!("Console unable to read line: {e}");
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            drop(editor.add_history_entry(line.as_str()));
+
+            match line.strip_prefix('/') {
+                // `//say hi` sends `/say hi`
+                Some(command) if command.starts_with('/') => self.send(command),
+                Some(command) => self.command(command),
+                None => self.send(&line),
+            }
+        }
+
+        if let Some(history_file) = &config.history_file {
+            if let Err(e) = editor.save_history(history_file) {
+                error!("Console unable to save history: {e}");
+            }
+        }
+
+        // Stop sending to the bots, exiting is up to the program
+        self.state.lock().unwrap().bots.clear();
+        drop(closed);
+    }
+
+    // Run a local command
+    fn command(&self, command: &str) {
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let reply = match name {
+            "bots" => self.list_bots(),
+            "switch" => self.switch(argument.trim()),
+            "help" => HELP.to_string(),
+            _ => format!("Unknown command /{name}, try /help"),
+        };
+        self.print(notice_line(&reply));
+    }
+
+    fn list_bots(&self) -> String {
+        let state = self.state.lock().unwrap();
+        if state.bots.is_empty() {
+            return "No bots are connected to the console".to_string();
+        }
+
+        let bots: Vec<String> = state
+            .bots
+            .iter()
+            .enumerate()
+            .map(|(index, bot)| {
+                let active = if index == state.active {
+                    " (active)"
+                } else {
+                    ""
+                };
+                format!("{}. {}{active}", index + 1, bot.name)
+            })
+            .collect();
+        format!("Bots: {}", bots.join(", "))
+    }
+
+    fn switch(&self, bot: &str) -> String {
+        if bot.is_empty() {
+            return "Usage: /switch <name or number>".to_string();
+        }
+        let mut state = self.state.lock().unwrap();
+
+        // Bots can be chosen by name or by their number in /bots
+        let index = match bot.parse::<usize>() {
+            Ok(number) => number.checked_sub(1),
+            Err(_) => state
+                .bots
+                .iter()
+                .position(|b| b.name.eq_ignore_ascii_case(bot)),
+        };
+        match index.filter(|index| *index < state.bots.len()) {
+            Some(index) => {
+                state.active = index;
+                format!("Switched to {}", state.bots[index].name)
+            }
+            None => format!("There is no bot called {bot}, see /bots"),
+        }
+    }
+
+    // Send a line to Minecraft as the active bot
+    fn send(&self, line: &str) {
+        let state = self.state.lock().unwrap();
+        let Some(bot) = state.bots.get(state.active) else {
+            self.print(notice_line("No bots are connected to the console"));
+            return;
+        };
+
+        let message = BridgeMessage::new(
+            MessageId::new(PLATFORM, Uuid::new_v4()),
+            String::new(),
+            Author::new(PLATFORM, &bot.name),
+            line.to_string(),
+        );
+
+        // Send message to Azalea
+        if let Err(e) = bot.tx.send(PluginEvent::Chat(message)) {
+            error!("Console unable to send message to {}: {e}", bot.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bot, State};
+    use flume::Receiver;
+    use std::sync::{Arc, Mutex};
+
+    // A console with bots, without a terminal, and the lines it prints
+    fn console(names: &[&str]) -> (Console, Receiver<String>, Vec<Receiver<PluginEvent>>) {
+        let mut state = State::default();
+        let mut bots = Vec::new();
+        for name in names {
+            let (tx, rx) = flume::unbounded();
+            state.bots.push(Bot {
+                name: name.to_string(),
+                tx,
+            });
+            bots.push(rx);
+        }
+
+        let (output, printed) = flume::unbounded();
+        let console = Console {
+            state: Arc::new(Mutex::new(state)),
+            output,
+            closed: flume::bounded(0).1,
+        };
+        (console, printed, bots)
+    }
+
+    fn reply(console: &Console, printed: &Receiver<String>, command: &str) -> String {
+        console.command(command);
+        printed.try_recv().unwrap()
+    }
+
+    #[test]
+    fn lists_bots() {
+        let (empty, printed, _) = console(&[]);
+        assert_eq!(
+            reply(&empty, &printed, "bots"),
+            notice_line("No bots are connected to the console")
+        );
+
+        let (console, printed, _bots) = console(&["Alpha", "Beta"]);
+        assert_eq!(
+            reply(&console, &printed, "bots"),
+            notice_line("Bots: 1. Alpha (active), 2. Beta")
+        );
+    }
+
+    #[test]
+    fn switches_bots() {
+        let (console, printed, _bots) = console(&["Alpha", "Beta"]);
+        let switch = |argument: &str| reply(&console, &printed, &format!("switch {argument}"));
+
+        assert_eq!(switch("2"), notice_line("Switched to Beta"));
+        assert_eq!(switch("alpha"), notice_line("Switched to Alpha"));
+        assert_eq!(switch(" beta "), notice_line("Switched to Beta"));
+        for missing in ["0", "3", "Gamma"] {
+            let error = format!("There is no bot called {missing}, see /bots");
+            assert_eq!(switch(missing), notice_line(&error));
+        }
+        assert_eq!(console.state.lock().unwrap().active, 1);
+    }
+
+    #[test]
+    fn switch_needs_a_bot() {
+        let (console, printed, _bots) = console(&["Alpha"]);
+        let usage = notice_line("Usage: /switch <name or number>");
+        assert_eq!(reply(&console, &printed, "switch"), usage);
+        assert_eq!(reply(&console, &printed, "switch  "), usage);
+    }
+
+    #[test]
+    fn unknown_commands() {
+        let (console, printed, _bots) = console(&["Alpha"]);
+        assert_eq!(
+            reply(&console, &printed, "say hi"),
+            notice_line("Unknown command /say, try /help")
+        );
+        assert_eq!(reply(&console, &printed, "help"), notice_line(HELP));
+    }
+
+    #[test]
+    fn sends_as_the_active_bot() {
+        let (console, printed, bots) = console(&["Alpha", "Beta"]);
+        console.command("switch Beta");
+        printed.try_recv().unwrap();
+
+        console.send("hello");
+        assert!(bots[0].is_empty());
+        let Ok(PluginEvent::Chat(message)) = bots[1].try_recv() else {
+            panic!("nothing was sent as Beta");
+        };
+        assert_eq!(message.id.platform, PLATFORM);
+        assert_eq!(message.author.name, "Beta");
+        assert_eq!(message.content, "hello");
+    }
+}
@@ -0,0 +1,153 @@
+use azalea_bridge::{ClientSide, PluginBridge, PluginEvent, Templates};
+use flume::{Receiver, Sender};
+use log::error;
+use rustyline::{DefaultEditor, ExternalPrinter};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+mod input;
+mod output;
+
+/// How the console looks and where its history is kept.
+#[derive(Debug, Clone)]
+pub struct ConsoleConfig {
+    pub prompt: String,
+    // Lines typed in earlier runs, saved when the console closes
+    pub history_file: Option<PathBuf>,
+}
+
+impl Default for ConsoleConfig {
+    fn default() -> Self {
+        Self {
+            prompt: "> ".to_string(),
+            history_file: None,
+        }
+    }
+}
+
+impl ConsoleConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn prompt(mut self, prompt: &str) -> Self {
+        self.prompt = prompt.to_string();
+        self
+    }
+
+    pub fn history_file(mut self, history_file: impl Into<PathBuf>) -> Self {
+        self.history_file = Some(history_file.into());
+        self
+    }
+}
+
+// The type the console's 'ClientSide' plugins are for
+#[derive(Debug, Clone)]
+pub struct ConsolePlugin;
+
+/// Reads and types chat from the terminal, for one bot at a time.
+#[derive(Debug, Clone)]
+pub struct Console {
+    state: Arc<Mutex<State>>,
+    // Lines to print above the prompt
+    output: Sender<String>,
+    // Disconnected once Ctrl-C or Ctrl-D is pressed
+    closed: Receiver<()>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    bots: Vec<Bot>,
+    // The bot whose chat is shown and typed in
+    active: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct Bot {
+    name: String,
+    tx: Sender<PluginEvent>,
+}
+
+impl Console {
+    /// Starts reading lines from stdin, which needs a terminal.
+    pub fn new(config: ConsoleConfig) -> anyhow::Result<Self> {
+        let mut editor = DefaultEditor::new()?;
+        if let Some(history_file) = &config.history_file {
+            // There is no history the first time
+            drop(editor.load_history(history_file));
+        }
+
+        // Print lines above the prompt instead of over it
+        let mut printer = editor.create_external_printer()?;
+        let (output, output_rx) = flume::unbounded::<String>();
+        std::thread::spawn(move || {
+            while let Ok(line) = output_rx.recv() {
+                if let Err(e) = printer.print(line) {
+                    error!("Console unable to print: {e}");
+                }
+            }
+        });
+
+        let (closed_tx, closed) = flume::bounded(0);
+        let console = Self {
+            state: Arc::default(),
+            output,
+            closed,
+        };
+
+        let input = console.clone();
+        std::thread::spawn(move || input.read_lines(editor, config, closed_tx));
+        Ok(console)
+    }
+
+    /// Waits until the console is closed with Ctrl-C or Ctrl-D.
+    ///
+    /// Typed lines are no longer sent after that, stopping the program is up to you.
+    pub async fn closed(&self) {
+        // Nothing is ever sent, the channel is only disconnected
+        let _ = self.closed.recv_async().await;
+    }
+
+    /// Connects a bot to the console, returning a plugin to insert into its Azalea client.
+    ///
+    /// The first bot is active until another is chosen with `/switch`.
+    pub fn bot(&self, name: &str) -> ClientSide<ConsolePlugin> {
+        let bridge = PluginBridge::<ConsolePlugin>::new(Vec::new());
+
+        let index = {
+            let mut state = self.state.lock().unwrap();
+            state.bots.push(Bot {
+                name: name.to_string(),
+                tx: bridge.plugin.tx,
+            });
+            state.bots.len() - 1
+        };
+
+        // Print chat from the bot while it's active
+        let console = self.clone();
+        let rx = bridge.plugin.rx;
+        std::thread::spawn(move || {
+            while let Ok(event) = rx.recv() {
+                if console.state.lock().unwrap().active != index {
+                    continue;
+                }
+                if let Some(line) = output::format_event(event) {
+                    console.print(line);
+                }
+            }
+        });
+
+        // Lines are sent as typed, so commands work too
+        let templates = Templates::new()
+            .with_minecraft("{message}")
+            .expect("Console template is valid");
+        bridge.client.with_templates(templates)
+    }
+
+    pub(crate) fn print(&self, line: String) {
+        // The printer only stops if the terminal is gone
+        drop(self.output.send(line));
+    }
+}
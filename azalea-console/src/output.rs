@@ -0,0 +1,98 @@
+use azalea_bridge::{AzaleaEvent, BridgeMessage, MINECRAFT_PLATFORM};
+
+// ANSI escape codes for what the console adds itself
+const RESET: &str = "\x1b[0m";
+const GRAY: &str = "\x1b[90m";
+const YELLOW: &str = "\x1b[33m";
+
+// The line to print for an event, or None to print nothing
+pub(crate) fn format_event(event: AzaleaEvent) -> Option<String> {
+    match event {
+        AzaleaEvent::Chat(message, _) if message.id.platform == MINECRAFT_PLATFORM => {
+            // Fall back to plain text if the formatting was removed
+            let line = message.ansi.unwrap_or(message.content);
+            Some(format!("{line}{RESET}"))
+        }
+        // Relayed from another plugin
+        AzaleaEvent::Chat(message, _) => Some(relayed(&message, "")),
+        AzaleaEvent::Edit(message, _) => Some(relayed(&message, " (edited)")),
+        AzaleaEvent::Delete(_) => None,
+        AzaleaEvent::Notice(notice, _) => Some(notice_line(&notice)),
+    }
+}
+
+// Something the console says itself, like the reply to a command
+pub(crate) fn notice_line(notice: &str) -> String {
+    format!("{YELLOW}* {notice}{RESET}")
+}
+
+fn relayed(message: &BridgeMessage, suffix: &str) -> String {
+    format!(
+        "{GRAY}[{}]{RESET} {}{suffix}: {}",
+        message.id.platform,
+        message.author.name,
+        strip_escapes(&message.content)
+    )
+}
+
+// Other platforms could send escape codes to mess with the terminal
+fn strip_escapes(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\n')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::{Author, MessageId};
+
+    fn message(platform: &str, content: &str) -> BridgeMessage {
+        let id = MessageId::new(platform, "1");
+        BridgeMessage::new(
+            id,
+            String::new(),
+            Author::new("1", "Notch"),
+            content.to_string(),
+        )
+    }
+
+    #[test]
+    fn minecraft_chat_keeps_its_colours() {
+        let mut chat = message(MINECRAFT_PLATFORM, "<Notch> hi");
+        chat.ansi = Some("\x1b[33m<Notch> hi".to_string());
+        let line = format_event(AzaleaEvent::Chat(chat.clone(), Vec::new()));
+        assert_eq!(line.unwrap(), format!("\x1b[33m<Notch> hi{RESET}"));
+
+        chat.ansi = None;
+        let line = format_event(AzaleaEvent::Chat(chat, Vec::new()));
+        assert_eq!(line.unwrap(), format!("<Notch> hi{RESET}"));
+    }
+
+    #[test]
+    fn relayed_messages_show_their_platform() {
+        let chat = message("discord", "hi\x1b[2J\nthere");
+        let line = format_event(AzaleaEvent::Chat(chat.clone(), Vec::new()));
+        assert_eq!(
+            line.unwrap(),
+            format!("{GRAY}[discord]{RESET} Notch: hi[2J\nthere")
+        );
+
+        let line = format_event(AzaleaEvent::Edit(chat, Vec::new()));
+        assert_eq!(
+            line.unwrap(),
+            format!("{GRAY}[discord]{RESET} Notch (edited): hi[2J\nthere")
+        );
+    }
+
+    #[test]
+    fn notices_and_deletions() {
+        let notice = AzaleaEvent::Notice("Linked".to_string(), String::new());
+        assert_eq!(
+            format_event(notice).unwrap(),
+            format!("{YELLOW}* Linked{RESET}")
+        );
+        let delete = AzaleaEvent::Delete(MessageId::new("discord", "1"));
+        assert_eq!(format_event(delete), None);
+    }
+}
@@ -0,0 +1,332 @@
+use azalea_bridge::{BridgeMessage, EventKind, NIL_UUID};
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, fmt::Write};
+
+use crate::DigestConfig;
+
+// Most lines to list for keywords and for kicks and bans
+const MAX_LINES: usize = 50;
+
+// What happened in chat since the last digest
+#[derive(Debug)]
+pub(crate) struct Digest {
+    since: DateTime<Utc>,
+    messages: usize,
+    joins: usize,
+    leaves: usize,
+    deaths: usize,
+    // Messages sent by each player
+    players: HashMap<String, usize>,
+    mentions: Lines,
+    moderation: Lines,
+}
+
+// Lines to list, and how many more there were
+#[derive(Debug, Default)]
+struct Lines {
+    lines: Vec<String>,
+    skipped: usize,
+}
+
+impl Default for Digest {
+    fn default() -> Self {
+        Self {
+            since: Utc::now(),
+            messages: 0,
+            joins: 0,
+            leaves: 0,
+            deaths: 0,
+            players: HashMap::new(),
+            mentions: Lines::default(),
+            moderation: Lines::default(),
+        }
+    }
+}
+
+impl Digest {
+    pub fn add(&mut self, message: &BridgeMessage, config: &DigestConfig) {
+        match message.kind {
+            EventKind::Join => self.joins += 1,
+            EventKind::Leave => self.leaves += 1,
+            EventKind::Death => self.deaths += 1,
+            EventKind::Chat if message.author.id == NIL_UUID => {
+                if config.moderation.is_match(&message.content) {
+                    self.moderation.push(line(message));
+                }
+            }
+            EventKind::Chat => {
+                self.messages += 1;
+                *self.players.entry(message.author.name.clone()).or_default() += 1;
+
+                let content = message.content.to_lowercase();
+                if config
+                    .keywords
+                    .iter()
+                    .any(|keyword| content.contains(keyword))
+                {
+                    self.mentions.push(line(message));
+                }
+            }
+        }
+    }
+
+    // Put back a digest that couldn't be sent, before anything added since
+    pub fn restore(&mut self, earlier: Digest) {
+        let later = std::mem::replace(self, earlier);
+        self.messages += later.messages;
+        self.joins += later.joins;
+        self.leaves += later.leaves;
+        self.deaths += later.deaths;
+        for (name, count) in later.players {
+            *self.players.entry(name).or_default() += count;
+        }
+        self.mentions.extend(later.mentions);
+        self.moderation.extend(later.moderation);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages == 0
+            && self.joins == 0
+            && self.leaves == 0
+            && self.deaths == 0
+            && self.moderation.lines.is_empty()
+    }
+
+    pub fn subject(&self) -> String {
+        format!(
+            "Minecraft chat digest for {}",
+            self.since.format("%Y-%m-%d")
+        )
+    }
+
+    // The body of the email, in plain text
+    pub fn render(&self, config: &DigestConfig) -> String {
+        let mut body = String::new();
+        let now = Utc::now();
+        let format = "%Y-%m-%d %H:%M";
+        writeln!(
+            body,
+            "Chat from {} to {} UTC\n",
+            self.since.format(format),
+            now.format(format)
+        )
+        .unwrap();
+        writeln!(
+            body,
+            "{} messages from {} players, {} joins, {} leaves and {} deaths",
+            self.messages,
+            self.players.len(),
+            self.joins,
+            self.leaves,
+            self.deaths
+        )
+        .unwrap();
+
+        // Most messages first, then by name so the order doesn't change
+        let mut players: Vec<(&String, &usize)> = self.players.iter().collect();
+        players.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        if !players.is_empty() {
+            writeln!(body, "\nMost active players").unwrap();
+            for (name, count) in players.into_iter().take(config.top_players) {
+                writeln!(body, "  {name}: {count}").unwrap();
+            }
+        }
+
+        if !config.keywords.is_empty() {
+            self.mentions.render(
+                &mut body,
+                "Keyword mentions",
+                "No messages mentioned a keyword",
+            );
+        }
+        self.moderation.render(
+            &mut body,
+            "Kicks and bans",
+            "No players were kicked or banned",
+        );
+        body
+    }
+}
+
+impl Lines {
+    fn push(&mut self, line: String) {
+        if self.lines.len() < MAX_LINES {
+            self.lines.push(line);
+        } else {
+            self.skipped += 1;
+        }
+    }
+
+    fn extend(&mut self, later: Lines) {
+        for line in later.lines {
+            self.push(line);
+        }
+        self.skipped += later.skipped;
+    }
+
+    fn render(&self, body: &mut String, title: &str, empty: &str) {
+        if self.lines.is_empty() {
+            writeln!(body, "\n{empty}").unwrap();
+            return;
+        }
+
+        writeln!(body, "\n{title}").unwrap();
+        for line in &self.lines {
+            writeln!(body, "  {line}").unwrap();
+        }
+        if self.skipped > 0 {
+            writeln!(body, "  and {} more", self.skipped).unwrap();
+        }
+    }
+}
+
+// A message with the time it was sent
+fn line(message: &BridgeMessage) -> String {
+    let time = DateTime::<Utc>::from(message.timestamp).format("%H:%M");
+    match message.author.id.as_str() {
+        NIL_UUID => format!("[{time}] {}", message.content),
+        _ => format!("[{time}] {}: {}", message.author.name, message.content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::{Author, MessageId, MINECRAFT_PLATFORM};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn config() -> DigestConfig {
+        DigestConfig::new("localhost", "bot@localhost", "admin@localhost")
+            .keyword("grief")
+            .top_players(2)
+    }
+
+    // A message sent a number of minutes after midnight
+    fn message(kind: EventKind, name: &str, content: &str, minute: u64) -> BridgeMessage {
+        let id = MessageId::new(MINECRAFT_PLATFORM, minute);
+        let author = Author::new(name, name);
+        let mut message = BridgeMessage::new(id, String::new(), author, content.to_string());
+        message.kind = kind;
+        message.timestamp = UNIX_EPOCH + Duration::from_secs(minute * 60);
+        message
+    }
+
+    fn server(content: &str, minute: u64) -> BridgeMessage {
+        let mut message = message(EventKind::Chat, "Server", content, minute);
+        message.author.id = NIL_UUID.to_string();
+        message
+    }
+
+    // Everything after the time range, which changes
+    fn render(digest: &Digest) -> String {
+        let body = digest.render(&config());
+        body.split_once("\n\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn counts_events() {
+        let config = config();
+        let mut digest = Digest::default();
+        assert!(digest.is_empty());
+
+        for (kind, name, content) in [
+            (EventKind::Chat, "Alex", "hi"),
+            (EventKind::Chat, "Steve", "hello"),
+            (EventKind::Chat, "Alex", "how are you?"),
+            (EventKind::Chat, "Notch", "good"),
+            (EventKind::Join, "Alex", "Alex joined the game"),
+            (EventKind::Leave, "Alex", "Alex left the game"),
+            (EventKind::Death, "Steve", "Steve fell from a high place"),
+        ] {
+            digest.add(&message(kind, name, content, 1), &config);
+        }
+        assert!(!digest.is_empty());
+
+        assert_eq!(
+            render(&digest),
+            "4 messages from 3 players, 1 joins, 1 leaves and 1 deaths\n\
+            \n\
+            Most active players\n  \
+              Alex: 2\n  \
+              Notch: 1\n\
+            \n\
+            No messages mentioned a keyword\n\
+            \n\
+            No players were kicked or banned\n"
+        );
+    }
+
+    #[test]
+    fn lists_mentions_and_moderation() {
+        let config = config();
+        let mut digest = Digest::default();
+        digest.add(
+            &message(EventKind::Chat, "Alex", "GRIEFING at spawn", 61),
+            &config,
+        );
+        digest.add(&server("Steve was kicked", 62), &config);
+        // Other server messages aren't counted
+        digest.add(&server("Restarting soon", 63), &config);
+
+        let body = render(&digest);
+        assert!(body.starts_with("1 messages from 1 players"));
+        assert!(body.contains("Keyword mentions\n  [01:01] Alex: GRIEFING at spawn\n"));
+        assert!(body.contains("Kicks and bans\n  [01:02] Steve was kicked\n"));
+    }
+
+    #[test]
+    fn server_messages_alone_are_empty() {
+        let mut digest = Digest::default();
+        digest.add(&server("Restarting soon", 1), &config());
+        assert!(digest.is_empty());
+    }
+
+    #[test]
+    fn players_called_server_are_players() {
+        let config = config();
+        let mut digest = Digest::default();
+        digest.add(
+            &message(EventKind::Chat, "Server", "was kicked", 1),
+            &config,
+        );
+
+        let body = render(&digest);
+        assert!(body.starts_with("1 messages from 1 players"));
+        assert!(body.contains("Most active players\n  Server: 1\n"));
+        assert!(body.contains("No players were kicked or banned"));
+    }
+
+    #[test]
+    fn limits_lines() {
+        let config = config();
+        let mut digest = Digest::default();
+        for minute in 0..MAX_LINES as u64 + 2 {
+            digest.add(&message(EventKind::Chat, "Alex", "grief", minute), &config);
+        }
+
+        let body = render(&digest);
+        assert!(body.contains("  [00:49] Alex: grief\n  and 2 more\n"));
+        assert!(!body.contains("[00:50]"));
+    }
+
+    #[test]
+    fn restore_keeps_both_digests() {
+        let config = config();
+        let mut earlier = Digest::default();
+        earlier.since -= chrono::Duration::days(1);
+        let since = earlier.since;
+        earlier.add(&message(EventKind::Chat, "Alex", "grief here", 1), &config);
+        earlier.add(&message(EventKind::Join, "Alex", "", 1), &config);
+
+        let mut digest = Digest::default();
+        digest.add(&message(EventKind::Chat, "Alex", "more grief", 2), &config);
+        digest.add(&message(EventKind::Chat, "Steve", "hi", 3), &config);
+        digest.restore(earlier);
+
+        assert_eq!(digest.since, since);
+        let body = render(&digest);
+        assert!(body.starts_with("3 messages from 2 players, 1 joins"));
+        assert!(body.contains("  Alex: 2\n  Steve: 1\n"));
+        assert!(body.contains("  [00:01] Alex: grief here\n  [00:02] Alex: more grief\n"));
+    }
+}
@@ -0,0 +1,214 @@
+use async_trait::async_trait;
+use azalea_bridge::{AzaleaEvent, BridgePlatform, Capabilities, PluginEvent, MINECRAFT_PLATFORM};
+use flume::Sender;
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+use log::{error, info};
+
+use crate::{digest::Digest, DigestPlugin};
+
+pub(crate) const PLATFORM: &str = "digest";
+
+#[async_trait]
+impl BridgePlatform for DigestPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    // Only the text of messages is summarized
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    // Nothing is sent to Minecraft, digests are sent on a timer
+    async fn start(&self, _tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let mut interval = tokio::time::interval(self.config.interval);
+        // The first tick is straight away
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.send_digest().await {
+                error!("DigestPlugin unable to send digest: {e}");
+            }
+        }
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        // Only Minecraft chat is summarized
+        if let AzaleaEvent::Chat(message, _) = event {
+            if message.id.platform == MINECRAFT_PLATFORM {
+                self.digest.lock().unwrap().add(&message, &self.config);
+            }
+        }
+        Ok(())
+    }
+
+    // Send what was collected so far, instead of losing it
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        self.send_digest().await
+    }
+}
+
+impl DigestPlugin {
+    async fn send_digest(&self) -> anyhow::Result<()> {
+        let digest = std::mem::take(&mut *self.digest.lock().unwrap());
+        if digest.is_empty() {
+            info!("Nothing happened in chat, not sending a digest");
+            return Ok(());
+        }
+
+        // Keep the digest to send with the next one
+        if let Err(e) = self.send(&digest).await {
+            self.digest.lock().unwrap().restore(digest);
+            return Err(e);
+        }
+        info!("Sent chat digest to {}", self.config.to.join(", "));
+        Ok(())
+    }
+
+    async fn send(&self, digest: &Digest) -> anyhow::Result<()> {
+        let config = &self.config;
+        let mut email = Message::builder()
+            .from(config.from.parse()?)
+            .subject(digest.subject());
+        for to in &config.to {
+            email = email.to(to.parse()?);
+        }
+        let email = email.body(digest.render(config))?;
+
+        let mut mailer = match config.insecure {
+            true => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            false => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+        }
+        .port(config.port);
+        if let Some((username, password)) = &config.credentials {
+            mailer = mailer.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        mailer.build().send(email).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DigestConfig;
+    use azalea_bridge::{Author, BridgeMessage, MessageId};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+    };
+
+    // A local SMTP sink, sending on the data of each email it accepts
+    async fn smtp_sink(
+        listener: TcpListener,
+        emails: flume::Sender<String>,
+        reject: Arc<AtomicBool>,
+    ) {
+        while let Ok((socket, _)) = listener.accept().await {
+            let (emails, reject) = (emails.clone(), reject.load(Ordering::SeqCst));
+            tokio::spawn(session(socket, emails, reject));
+        }
+    }
+
+    async fn session(socket: TcpStream, emails: flume::Sender<String>, reject: bool) {
+        let (read, mut write) = socket.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let reply = match line.get(..4).unwrap_or_default().to_uppercase().as_str() {
+                "MAIL" if reject => "451 Try again later\r\n",
+                "DATA" => {
+                    write
+                        .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                        .await
+                        .unwrap();
+                    let mut data = String::new();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    let _ = emails.send(data);
+                    "250 Accepted\r\n"
+                }
+                "QUIT" => {
+                    write.write_all(b"221 Bye\r\n").await.unwrap();
+                    return;
+                }
+                _ => "250 OK\r\n",
+            };
+            write.write_all(reply.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn chat(name: &str, content: &str) -> AzaleaEvent {
+        let id = MessageId::new(MINECRAFT_PLATFORM, content);
+        let author = Author::new(name, name);
+        AzaleaEvent::Chat(
+            BridgeMessage::new(id, String::new(), author, content.to_string()),
+            Vec::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn keeps_digests_that_fail_to_send() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (emails_tx, emails) = flume::unbounded();
+        let reject = Arc::new(AtomicBool::new(true));
+        tokio::spawn(smtp_sink(listener, emails_tx, reject.clone()));
+
+        let config = DigestConfig::new("127.0.0.1", "bot@localhost", "admin@localhost")
+            .port(port)
+            .insecure();
+        let plugin = DigestPlugin {
+            config: Arc::new(config),
+            digest: Arc::default(),
+        };
+
+        plugin.handle(chat("Alex", "hi")).await.unwrap();
+        assert!(plugin.send_digest().await.is_err());
+        assert!(!plugin.digest.lock().unwrap().is_empty());
+
+        // The next digest has the messages of both
+        reject.store(false, Ordering::SeqCst);
+        plugin.handle(chat("Steve", "hello")).await.unwrap();
+        plugin.send_digest().await.unwrap();
+
+        let email = tokio::time::timeout(Duration::from_secs(5), emails.recv_async())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(email.contains("Subject: Minecraft chat digest for"));
+        assert!(email.contains("2 messages from 2 players"));
+        assert!(plugin.digest.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn empty_digests_are_not_sent() {
+        // Nothing listens on this port, so sending would fail
+        let config = DigestConfig::new("127.0.0.1", "bot@localhost", "admin@localhost")
+            .port(9)
+            .insecure();
+        let plugin = DigestPlugin {
+            config: Arc::new(config),
+            digest: Arc::default(),
+        };
+        plugin.send_digest().await.unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use azalea_bridge::{ClientSide, PlatformBuilder};
+use regex::Regex;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+mod digest;
+mod email;
+
+/// Where digests are sent, how often and what they mention.
+#[derive(Debug, Clone)]
+pub struct DigestConfig {
+    pub host: String,
+    pub port: u16,
+    // Username and password
+    pub credentials: Option<(String, String)>,
+    // Send without TLS, for local SMTP sinks
+    pub insecure: bool,
+    pub from: String,
+    pub to: Vec<String>,
+    pub interval: Duration,
+    // Words to list the messages of, like the names of staff
+    pub keywords: Vec<String>,
+    // Server messages about players being kicked or banned
+    pub moderation: Regex,
+    // How many players to list as the most active
+    pub top_players: usize,
+}
+
+impl DigestConfig {
+    // Send a digest each day from one address to another, over STARTTLS
+    pub fn new(host: &str, from: &str, to: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: 587,
+            credentials: None,
+            insecure: false,
+            from: from.to_string(),
+            to: vec![to.to_string()],
+            interval: Duration::from_secs(24 * 60 * 60),
+            keywords: Vec::new(),
+            moderation: Regex::new(r"(?i)\b(kicked|banned|ban)\b").unwrap(),
+            top_players: 10,
+        }
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    // Don't use TLS, which should only be used for testing
+    pub fn insecure(mut self) -> Self {
+        self.insecure = true;
+        self
+    }
+
+    // Also send the digest to another address
+    pub fn to(mut self, to: &str) -> Self {
+        self.to.push(to.to_string());
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // Matched case-insensitively
+    pub fn keyword(mut self, keyword: &str) -> Self {
+        self.keywords.push(keyword.to_lowercase());
+        self
+    }
+
+    pub fn moderation(mut self, moderation: Regex) -> Self {
+        self.moderation = moderation;
+        self
+    }
+
+    pub fn top_players(mut self, top_players: usize) -> Self {
+        self.top_players = top_players;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DigestPlugin {
+    config: Arc<DigestConfig>,
+    digest: Arc<Mutex<digest::Digest>>,
+}
+
+impl DigestPlugin {
+    /// Collects Minecraft chat and emails a digest of it every interval.
+    pub async fn new(config: DigestConfig, ignore_list: Vec<&str>) -> ClientSide<DigestPlugin> {
+        let plugin = DigestPlugin {
+            config: Arc::new(config),
+            digest: Arc::default(),
+        };
+
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .build()
+    }
+}
@@ -1,78 +1,265 @@
-use azalea_bridge::{AzaleaEvent, PluginEvent, PluginSide};
-use flume::{Receiver, Sender};
+use async_trait::async_trait;
+use azalea_bridge::{
+    parse_link_command, split_mentions, Attachment, AttachmentKind, Author, Avatars, AzaleaEvent,
+    BridgeMessage, BridgePlatform, Capabilities, Identities, LinkCodes, MessageId, MessageMap,
+    PluginEvent, Reply, Routes, Segment,
+};
+use flume::Sender;
 use log::{error, info, warn};
-use std::error::Error;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH},
+};
 use twilight_cache_inmemory::{InMemoryCache, ResourceType};
 use twilight_gateway::{Event, Intents, Shard, ShardId};
 use twilight_http::Client as HttpClient;
-use twilight_model::id::{
-    marker::{ChannelMarker, WebhookMarker},
-    Id,
+use twilight_model::{
+    channel::{
+        message::{sticker::StickerFormatType, Mention},
+        Message,
+    },
+    http::attachment::Attachment as DiscordAttachment,
+    id::{
+        marker::{ChannelMarker, MessageMarker, RoleMarker, UserMarker, WebhookMarker},
+        Id,
+    },
 };
 
 use crate::DiscordPlugin;
 
-pub(crate) async fn main(
+pub(crate) const PLATFORM: &str = "discord";
+
+// Largest file to upload instead of sending a link
+const MAX_UPLOAD_SIZE: usize = 8 * 1024 * 1024;
+
+// The name of webhooks created for bridged channels
+const WEBHOOK_NAME: &str = "Azalea Bridge";
+
+// Bridged channels and the messages relayed to them
+pub(crate) type Channels = Arc<HashMap<Id<ChannelMarker>, MessageMap>>;
+
+// Set up the bot for the channels of each route
+pub(crate) fn connect(
     bot_token: String,
-    channel_id: u64,
-    webhook_token: String,
-    webhook_id: u64,
-    plugin: PluginSide<DiscordPlugin>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    // Create a single shard.
-    let mut shard = Shard::new(
-        ShardId::ONE,
-        bot_token.clone(),
-        Intents::GUILD_MESSAGES | Intents::MESSAGE_CONTENT,
+    http: HttpClient,
+    routes: &Routes,
+    webhooks: Vec<(u64, u64, String)>,
+    avatars: Avatars,
+) -> DiscordPlugin {
+    // Only cache messages, and the roles and channels they mention.
+    let cache = Arc::new(
+        InMemoryCache::builder()
+            .resource_types(ResourceType::MESSAGE | ResourceType::ROLE | ResourceType::CHANNEL)
+            .build(),
     );
 
-    // The http client is separate from the gateway, so startup a new one.
-    let http = HttpClient::new(bot_token);
-
-    // Since we only care about messages, make the cache only process messages.
-    let cache = InMemoryCache::builder()
-        .resource_types(ResourceType::MESSAGE)
-        .build();
-
-    // Handle events from Azalea
-    tokio::spawn(handle_mc_event(
-        http,
-        webhook_token,
-        Id::new(webhook_id),
-        plugin.rx,
-    ));
-
-    // Startup the event loop to process each event in the event stream as they
-    // come in.
-    let channel_id: Id<ChannelMarker> = Id::new(channel_id);
-    loop {
-        let event = match shard.next_event().await {
-            Ok(event) => event,
-            Err(source) => {
-                warn!("Error receiving event: {source}");
-                if source.is_fatal() {
-                    break;
-                }
-                continue;
-            }
+    // Messages relayed from other plugins, for each channel
+    let mut channels = HashMap::new();
+    for target in routes.targets() {
+        let Ok(channel_id) = target.parse::<Id<ChannelMarker>>() else {
+            error!("Invalid Discord channel id: {target}");
+            continue;
         };
-        // Update the cache.
-        cache.update(&event);
+        let relayed = MessageMap::open_for("discord-messages", &target).unwrap_or_else(|e| {
+            error!("Unable to load relayed messages: {e}");
+            MessageMap::default()
+        });
+        channels.insert(channel_id, relayed);
+    }
 
-        // Spawn a new task to handle the event
-        tokio::spawn(handle_discord_event(
-            event,
-            channel_id.clone(),
-            plugin.tx.clone(),
-        ));
+    // Webhooks that were given instead of created
+    let known = Webhooks::default();
+    for (channel_id, webhook_id, webhook_token) in webhooks {
+        known.insert(Id::new(channel_id), Id::new(webhook_id), webhook_token);
     }
 
-    Ok(())
+    DiscordPlugin {
+        bot_token,
+        http: Arc::new(http),
+        cache,
+        channels: Arc::new(channels),
+        webhooks: known,
+        avatars,
+    }
+}
+
+#[async_trait]
+impl BridgePlatform for DiscordPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        // Create a single shard.
+        let mut shard = Shard::new(
+            ShardId::ONE,
+            self.bot_token.clone(),
+            Intents::GUILDS | Intents::GUILD_MESSAGES | Intents::MESSAGE_CONTENT,
+        );
+
+        // Startup the event loop to process each event in the event stream as they
+        // come in.
+        loop {
+            let event = match shard.next_event().await {
+                Ok(event) => event,
+                Err(source) => {
+                    warn!("Error receiving event: {source}");
+                    if source.is_fatal() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            // Update the cache.
+            self.cache.update(&event);
+
+            // Spawn a new task to handle the event
+            tokio::spawn(handle_discord_event(
+                event,
+                self.channels.clone(),
+                self.http.clone(),
+                self.cache.clone(),
+                tx.clone(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        let (http, channels, webhooks) = (&self.http, &self.channels, &self.webhooks);
+
+        match event {
+            AzaleaEvent::Chat(origin, targets) => {
+                let mut message = to_discord(&origin.content);
+
+                // Webhooks can't reply, so quote the message instead
+                if let Some(reply) = &origin.reply {
+                    message = format!(
+                        "> **{}**: {}\n{message}",
+                        escape_markdown(&reply.username),
+                        escape_markdown(&reply.snippet)
+                    );
+                }
+
+                // Players are shown with their heads
+                let avatar = self.avatars.url(&origin.author, &origin.id.platform);
+
+                for channel_id in get_targets(channels, &targets) {
+                    let webhook = match webhooks.get(http, channel_id).await {
+                        Ok(webhook) => webhook,
+                        Err(e) => {
+                            error!("Unable to get webhook for channel {channel_id}: {e}");
+                            continue;
+                        }
+                    };
+
+                    // Upload relayed files, or link them if that fails
+                    let mut message = message.clone();
+                    let mut files = Vec::new();
+                    for attachment in &origin.attachments {
+                        match upload_attachment(attachment, files.len() as u64).await {
+                            Some(file) => files.push(file),
+                            None => message.push_str(&format!("\n{attachment}")),
+                        }
+                    }
+
+                    // Remember the message so it can be edited and deleted
+                    let author = (origin.author.name.as_str(), avatar.as_deref());
+                    match execute_webhook(http, &webhook, author, &message, &files, true).await {
+                        Ok(Some(sent)) => {
+                            if let Some(relayed) = channels.get(&channel_id) {
+                                relayed.insert(origin.id.clone(), sent);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => error!("Unable to send message: {e}"),
+                    }
+                }
+            }
+            AzaleaEvent::Edit(message, _) => {
+                let content = to_discord(&message.content);
+
+                // Edit every copy of the message
+                for (channel_id, relayed) in channels.iter() {
+                    let Some(local) = get_message_id(relayed, &message.id) else {
+                        continue;
+                    };
+                    let (webhook_id, webhook_token) = match webhooks.get(http, *channel_id).await {
+                        Ok(webhook) => webhook,
+                        Err(e) => {
+                            error!("Unable to get webhook for channel {channel_id}: {e}");
+                            continue;
+                        }
+                    };
+
+                    if let Ok(update) = http
+                        .update_webhook_message(webhook_id, &webhook_token, local)
+                        .content(Some(&content))
+                    {
+                        if let Err(e) = update.await {
+                            error!("Unable to edit message: {e}");
+                        }
+                    } else {
+                        error!("Unable to set message content: {content}");
+                    }
+                }
+            }
+            AzaleaEvent::Delete(id) => {
+                // Delete every copy of the message
+                for (channel_id, relayed) in channels.iter() {
+                    let Some(local) = get_message_id(relayed, &id) else {
+                        continue;
+                    };
+                    relayed.remove(&id);
+
+                    let (webhook_id, webhook_token) = match webhooks.get(http, *channel_id).await {
+                        Ok(webhook) => webhook,
+                        Err(e) => {
+                            error!("Unable to get webhook for channel {channel_id}: {e}");
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = http
+                        .delete_webhook_message(webhook_id, &webhook_token, local)
+                        .await
+                    {
+                        error!("Unable to delete message: {e}");
+                    }
+                }
+            }
+            AzaleaEvent::Notice(notice, channel) => {
+                let Ok(channel_id) = channel.parse::<Id<ChannelMarker>>() else {
+                    warn!("Notice for unknown channel {channel}: {notice}");
+                    return Ok(());
+                };
+
+                let notice = escape_markdown(&notice);
+                match http.create_message(channel_id).content(&notice) {
+                    Ok(message) => {
+                        if let Err(e) = message.await {
+                            error!("Unable to send notice: {e}");
+                        }
+                    }
+                    Err(e) => error!("Unable to set notice content: {e}"),
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 async fn handle_discord_event(
     event: Event,
-    channel_id: Id<ChannelMarker>,
+    channels: Channels,
+    http: Arc<HttpClient>,
+    cache: Arc<InMemoryCache>,
     tx: Sender<PluginEvent>,
 ) -> anyhow::Result<()> {
     match event {
@@ -80,25 +267,103 @@ async fn handle_discord_event(
             info!("Discord bot is ready!");
         }
         Event::MessageCreate(event) => {
-            // Only listen on one channel
-            if channel_id != event.channel_id {
+            // Only listen on bridged channels
+            let Some(relayed) = channels.get(&event.channel_id) else {
                 return Ok(());
-            }
+            };
 
             // Don't send messages from bots
             if event.author.bot {
                 return Ok(());
             }
 
+            // Link accounts instead of sending the code
+            if let Some(code) = parse_link_command(&event.content) {
+                let reply = match LinkCodes::global().redeem(code) {
+                    Some(player) => {
+                        Identities::global().link(&player, PLATFORM, event.author.id);
+                        info!("Linked {} to {player}", event.author.name);
+                        format!("Linked to {}", escape_markdown(&player))
+                    }
+                    None => "That code is invalid or has expired".to_string(),
+                };
+
+                http.create_message(event.channel_id)
+                    .reply(event.id)
+                    .content(&reply)?
+                    .await?;
+                return Ok(());
+            }
+
+            // Replies to relayed messages refer to the original message
+            let reply = event.referenced_message.as_ref().map(|replied| {
+                let id = relayed
+                    .origin(&replied.id.to_string())
+                    .unwrap_or_else(|| MessageId::new(PLATFORM, replied.id));
+                let content = replace_mentions(&replied.content, &replied.mentions, &cache);
+                Reply::new(id, replied.author.name.clone(), &content)
+            });
+
+            let mut message = BridgeMessage::new(
+                MessageId::new(PLATFORM, event.id),
+                event.channel_id.to_string(),
+                Author::new(event.author.id, &event.author.name),
+                replace_mentions(&event.content, &event.mentions, &cache),
+            );
+            message.reply = reply;
+            message.attachments = get_attachments(&event);
+            message.timestamp =
+                UNIX_EPOCH + Duration::from_micros(event.timestamp.as_micros() as u64);
+
             // Send message to Azalea
+            if let Err(e) = tx.send_async(PluginEvent::Chat(message)).await {
+                error!("DiscordPlugin unable to send message to Azalea: {e}");
+            }
+        }
+        Event::MessageUpdate(event) => {
+            // Only listen on bridged channels
+            if !channels.contains_key(&event.channel_id) {
+                return Ok(());
+            }
+
+            // Embed updates don't include the author or content
+            let (Some(author), Some(content)) = (event.author, event.content) else {
+                return Ok(());
+            };
+
+            // Don't send edits from bots
+            if author.bot {
+                return Ok(());
+            }
+
+            let mentions = event.mentions.unwrap_or_default();
+            let content = replace_mentions(&content, &mentions, &cache);
+
+            // Send edit to Azalea
             if let Err(e) = tx
-                .send_async(PluginEvent::Chat(
-                    event.author.name.clone(),
-                    event.content.clone(),
-                ))
+                .send_async(PluginEvent::Edit(BridgeMessage::new(
+                    MessageId::new(PLATFORM, event.id),
+                    event.channel_id.to_string(),
+                    Author::new(author.id, author.name),
+                    content,
+                )))
                 .await
             {
-                error!("DiscordPlugin unable to send message to Azalea: {e}");
+                error!("DiscordPlugin unable to send edit to Azalea: {e}");
+            }
+        }
+        Event::MessageDelete(event) => {
+            // Only listen on bridged channels
+            if !channels.contains_key(&event.channel_id) {
+                return Ok(());
+            }
+
+            // Send deletion to Azalea
+            if let Err(e) = tx
+                .send_async(PluginEvent::Delete(MessageId::new(PLATFORM, event.id)))
+                .await
+            {
+                error!("DiscordPlugin unable to send deletion to Azalea: {e}");
             }
         }
         _ => {}
@@ -106,53 +371,434 @@ async fn handle_discord_event(
     Ok(())
 }
 
-async fn handle_mc_event(
-    http: HttpClient,
-    webhook_token: String,
-    webhook_id: Id<WebhookMarker>,
-    rx: Receiver<AzaleaEvent>,
-) -> anyhow::Result<()> {
-    loop {
-        let Ok(event) = rx.recv_async().await else {
-            error!("DiscordPlugin Minecraft listener closed");
-            return Err(anyhow::Error::msg("DiscordPlugin Minecraft listener closed"));        
+// Get the channels to send a message to, which is all of them if none are given
+fn get_targets(channels: &Channels, targets: &[String]) -> Vec<Id<ChannelMarker>> {
+    if targets.is_empty() {
+        return channels.keys().copied().collect();
+    }
+
+    targets
+        .iter()
+        .filter_map(|target| match target.parse() {
+            Ok(channel_id) if channels.contains_key(&channel_id) => Some(channel_id),
+            _ => {
+                warn!("Message sent to unknown channel {target}");
+                None
+            }
+        })
+        .collect()
+}
+
+// Webhooks used to send messages as players, one for each channel
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Webhooks {
+    inner: Arc<Mutex<HashMap<Id<ChannelMarker>, (Id<WebhookMarker>, String)>>>,
+}
+
+impl Webhooks {
+    fn insert(&self, channel_id: Id<ChannelMarker>, id: Id<WebhookMarker>, token: String) {
+        self.inner.lock().unwrap().insert(channel_id, (id, token));
+    }
+
+    // Get the webhook for a channel, finding or creating one if needed
+    async fn get(
+        &self,
+        http: &HttpClient,
+        channel_id: Id<ChannelMarker>,
+    ) -> anyhow::Result<(Id<WebhookMarker>, String)> {
+        let cached = self.inner.lock().unwrap().get(&channel_id).cloned();
+        if let Some(webhook) = cached {
+            return Ok(webhook);
+        }
+
+        // Reuse a webhook from an earlier run if there is one
+        let existing = http.channel_webhooks(channel_id).await?.models().await?;
+        let webhook = match existing.into_iter().find(|webhook| {
+            webhook.name.as_deref() == Some(WEBHOOK_NAME) && webhook.token.is_some()
+        }) {
+            Some(webhook) => webhook,
+            None => {
+                info!("Creating webhook for channel {channel_id}");
+                http.create_webhook(channel_id, WEBHOOK_NAME)?
+                    .await?
+                    .model()
+                    .await?
+            }
         };
-        match event {
-            AzaleaEvent::Chat(profile, packet) => {
-                let username = if let Some(user) = packet.username() {
-                    user
-                } else {
-                    profile.name
-                };
 
-                // Attempt to escape formatting
-                let message = packet
-                    .content()
-                    .replace('\\', "\\*")
-                    .replace('*', "\\*")
-                    .replace('_', "\\_")
-                    .replace('`', "\\`")
-                    .replace('>', "\\>");
-
-                if let Ok(message) = http
-                    .execute_webhook(webhook_id, &webhook_token)
-                    .content(&message)
-                {
-                    if let Ok(message) = message.username(&username) {
-                        if let Err(e) = message.await {
-                            error!("Unable to send message: {e}");
-                            continue;
-                        }
-                    } else {
-                        error!("Unable to set message username: {username}");
-                        continue;
-                    }
-                } else {
-                    error!("Unable to set message content: {message}");
-                    continue;
-                }
+        let Some(token) = webhook.token else {
+            return Err(anyhow::Error::msg("Webhook has no token"));
+        };
+        self.insert(channel_id, webhook.id, token.clone());
+        Ok((webhook.id, token))
+    }
+}
+
+// Send a message as a player
+async fn execute_webhook(
+    http: &HttpClient,
+    (webhook_id, webhook_token): &(Id<WebhookMarker>, String),
+    (username, avatar_url): (&str, Option<&str>),
+    content: &str,
+    files: &[DiscordAttachment],
+    wait: bool,
+) -> anyhow::Result<Option<Id<MessageMarker>>> {
+    let mut request = http
+        .execute_webhook(*webhook_id, webhook_token)
+        .content(content)?
+        .username(username)?
+        .attachments(files)?;
+    if let Some(avatar_url) = avatar_url {
+        request = request.avatar_url(avatar_url);
+    }
+
+    if !wait {
+        request.await?;
+        return Ok(None);
+    }
+
+    let sent = request.wait().await?.model().await?;
+    Ok(Some(sent.id))
+}
+
+// Get the files, stickers and embeds of a message
+fn get_attachments(message: &Message) -> Vec<Attachment> {
+    let mut attachments = Vec::new();
+
+    for file in &message.attachments {
+        attachments.push(Attachment {
+            kind: AttachmentKind::from_mime(file.content_type.as_deref()),
+            name: file.filename.clone(),
+            url: file.url.clone(),
+            mime: file.content_type.clone(),
+        });
+    }
+
+    for sticker in &message.sticker_items {
+        let extension = match sticker.format_type {
+            StickerFormatType::Lottie => "json",
+            StickerFormatType::Gif => "gif",
+            _ => "png",
+        };
+        attachments.push(Attachment {
+            kind: AttachmentKind::Sticker,
+            name: sticker.name.clone(),
+            url: format!(
+                "https://media.discordapp.net/stickers/{}.{extension}",
+                sticker.id
+            ),
+            mime: None,
+        });
+    }
+
+    // Links in the message already show up in the content
+    for embed in &message.embeds {
+        let Some(url) = &embed.url else { continue };
+        if message.content.contains(url.as_str()) {
+            continue;
+        }
+        attachments.push(Attachment {
+            kind: AttachmentKind::Embed,
+            name: embed.title.clone().unwrap_or_default(),
+            url: url.clone(),
+            mime: None,
+        });
+    }
+
+    attachments
+}
+
+// Download a relayed file so it can be uploaded to Discord
+async fn upload_attachment(attachment: &Attachment, id: u64) -> Option<DiscordAttachment> {
+    if matches!(
+        attachment.kind,
+        AttachmentKind::Sticker | AttachmentKind::Embed
+    ) {
+        return None;
+    }
+
+    let response = match reqwest::get(&attachment.url)
+        .await
+        .and_then(|r| r.error_for_status())
+    {
+        Ok(response) => response,
+        Err(e) => {
+            warn!("Unable to download attachment {}: {e}", attachment.url);
+            return None;
+        }
+    };
+    if response.content_length().unwrap_or(0) as usize > MAX_UPLOAD_SIZE {
+        return None;
+    }
+
+    let bytes = response.bytes().await.ok()?;
+    if bytes.len() > MAX_UPLOAD_SIZE {
+        return None;
+    }
+
+    Some(DiscordAttachment::from_bytes(
+        attachment.name.clone(),
+        bytes.to_vec(),
+        id,
+    ))
+}
+
+// Replace user, role and channel mentions with readable names
+fn replace_mentions(content: &str, mentions: &[Mention], cache: &InMemoryCache) -> String {
+    let mut result = String::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find('<') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find('>') else { break };
+        match mention_name(&rest[1..end], mentions, cache) {
+            Some(name) => {
+                result.push_str(&name);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+// Get the name of a mention like `@&123` or `#123`
+fn mention_name(mention: &str, mentions: &[Mention], cache: &InMemoryCache) -> Option<String> {
+    if let Some(id) = mention.strip_prefix("@&") {
+        let id: Id<RoleMarker> = id.parse().ok()?;
+        let role = cache.role(id)?;
+        return Some(format!("@{}", role.resource().name));
+    }
+
+    if let Some(id) = mention.strip_prefix('@') {
+        let id: Id<UserMarker> = id.trim_start_matches('!').parse().ok()?;
+
+        // Prefer the name of a linked player
+        if let Some(player) = Identities::global().player(PLATFORM, &id.to_string()) {
+            return Some(format!("@{player}"));
+        }
+
+        let mention = mentions.iter().find(|mention| mention.id == id)?;
+        let name = mention
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.as_ref())
+            .unwrap_or(&mention.name);
+        return Some(format!("@{name}"));
+    }
+
+    if let Some(id) = mention.strip_prefix('#') {
+        let id: Id<ChannelMarker> = id.parse().ok()?;
+        let name = cache.channel(id)?.name.clone()?;
+        return Some(format!("#{name}"));
+    }
+
+    None
+}
+
+// Escape a message and mention linked players
+fn to_discord(message: &str) -> String {
+    let mut result = String::new();
+    for segment in split_mentions(message) {
+        match segment {
+            Segment::Text(text) => result.push_str(&escape_markdown(text)),
+            Segment::Mention(name) => match Identities::global().user_id(PLATFORM, name) {
+                Some(id) => result.push_str(&format!("<@{id}>")),
+                None => result.push_str(&escape_markdown(&format!("@{name}"))),
+            },
+        }
+    }
+    result
+}
+
+// Attempt to escape formatting
+fn escape_markdown(message: &str) -> String {
+    message
+        .replace('\\', "\\\\")
+        .replace('*', "\\*")
+        .replace('_', "\\_")
+        .replace('`', "\\`")
+        .replace('>', "\\>")
+}
+
+// Find the Discord message a relayed message was sent as
+fn get_message_id(relayed: &MessageMap, origin: &MessageId) -> Option<Id<MessageMarker>> {
+    let local = relayed.get(origin)?;
+    match local.parse() {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!("Invalid Discord message id {local}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_AVATAR_URL;
+    use azalea_bridge::Route;
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    // The http method, the path and the JSON body
+    type Request = (String, String, Value);
+
+    const UUID: &str = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+
+    // Answer REST calls like Discord, sending each request on to the test
+    async fn stub() -> (String, flume::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (tx, rx) = flume::unbounded();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let request = read_request(&mut socket).await;
+                    let body = respond(&request).to_string();
+                    let _ = tx.send(request);
+
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    socket.write_all(head.as_bytes()).await.unwrap();
+                    socket.write_all(body.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        (address, rx)
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> Request {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "request ended early");
+            data.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&data);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse().ok())
+                .unwrap_or(0);
+            if body.len() >= length {
+                let mut request_line = head.split(' ');
+                let method = request_line.next().unwrap_or_default().to_string();
+                let path = request_line.next().unwrap_or_default().to_string();
+                return (method, path, serde_json::from_str(body).unwrap_or_default());
             }
-            _ => {}
         }
     }
+
+    // Every call the bridge makes is answered with a message
+    fn respond((_, path, body): &Request) -> Value {
+        let channel_id = path
+            .strip_prefix("/api/v10/channels/")
+            .and_then(|rest| rest.split('/').next())
+            .unwrap_or("1");
+        json!({
+            "id": "500",
+            "channel_id": channel_id,
+            "author": { "id": "2", "username": "Azalea Bridge", "discriminator": "0000", "avatar": null, "bot": true },
+            "content": body["content"].as_str().unwrap_or_default(),
+            "timestamp": "2023-03-16T10:00:00.000000+00:00",
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "mention_channels": [],
+            "attachments": [],
+            "embeds": [],
+            "reactions": [],
+            "sticker_items": [],
+            "components": [],
+            "pinned": false,
+            "type": 0,
+        })
+    }
+
+    async fn next(requests: &flume::Receiver<Request>) -> Request {
+        tokio::time::timeout(Duration::from_secs(5), requests.recv_async())
+            .await
+            .expect("Nothing was sent to Discord")
+            .unwrap()
+    }
+
+    // Each event reaches the channel through its webhook
+    #[tokio::test]
+    async fn conformance() {
+        let (address, requests) = stub().await;
+        let http = HttpClient::builder()
+            .proxy(address, true)
+            .token("token".to_string())
+            .build();
+
+        let channel = "1000000000000000001";
+        let routes = Routes::new().with(Route::new(channel));
+        let webhooks = vec![(channel.parse().unwrap(), 2, "secret".to_string())];
+        let avatars = Avatars::new(Some(DEFAULT_AVATAR_URL));
+        let plugin = connect("token".to_string(), http, &routes, webhooks, avatars);
+        azalea_bridge::check_platform(plugin, channel).await;
+
+        // The reply is quoted and the link added, since webhooks can't do either
+        let (method, path, body) = next(&requests).await;
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/api/v10/webhooks/2/secret?wait=true");
+        assert_eq!(
+            body["content"],
+            "> **jeb\\_**: an earlier message\nhello\n[embed: Example] https://example.com/"
+        );
+        assert_eq!(body["username"], "Notch");
+        let avatar_url = format!("https://crafatar.com/avatars/{UUID}?size=128&overlay");
+        assert_eq!(body["avatar_url"], avatar_url);
+
+        // Messages for every channel
+        let (_, path, body) = next(&requests).await;
+        assert_eq!(path, "/api/v10/webhooks/2/secret?wait=true");
+        assert_eq!(body["content"], "hello");
+
+        let (method, path, body) = next(&requests).await;
+        assert_eq!(method, "PATCH");
+        assert_eq!(path, "/api/v10/webhooks/2/secret/messages/500");
+        assert_eq!(body["content"], "hello");
+
+        let (method, path, _) = next(&requests).await;
+        assert_eq!(method, "DELETE");
+        assert_eq!(path, "/api/v10/webhooks/2/secret/messages/500");
+
+        // Notices are sent by the bot itself
+        let (method, path, body) = next(&requests).await;
+        assert_eq!(method, "POST");
+        assert_eq!(path, format!("/api/v10/channels/{channel}/messages"));
+        assert_eq!(body["content"], "notice");
+        assert!(requests.is_empty());
+    }
+
+    #[test]
+    fn escapes_markdown() {
+        assert_eq!(
+            escape_markdown(r"a\b *c* _d_ `e` > f"),
+            r"a\\b \*c\* \_d\_ \`e\` \> f"
+        );
+    }
 }
@@ -1,9 +1,21 @@
-use azalea_bridge::{ClientSide, PluginBridge};
+use azalea_bridge::{Avatars, ClientSide, PlatformBuilder, Route, Routes};
+use std::sync::Arc;
+use twilight_cache_inmemory::InMemoryCache;
+use twilight_http::Client as HttpClient;
 
 mod discord;
 
+pub use azalea_bridge::DEFAULT_AVATAR_URL;
+
 #[derive(Debug, Clone)]
-pub struct DiscordPlugin;
+pub struct DiscordPlugin {
+    bot_token: String,
+    http: Arc<HttpClient>,
+    cache: Arc<InMemoryCache>,
+    channels: discord::Channels,
+    webhooks: discord::Webhooks,
+    avatars: Avatars,
+}
 
 impl DiscordPlugin {
     pub async fn new(
@@ -13,21 +25,52 @@ impl DiscordPlugin {
         webhook_id: u64,
         ignore_list: Vec<&str>,
     ) -> ClientSide<DiscordPlugin> {
-        let ignore = ignore_list.iter().map(|s| s.to_string()).collect();
-
-        // Create commucation channel
-        let bridge = PluginBridge::<DiscordPlugin>::new(ignore);
-
-        // Spawn Discord bot
-        tokio::spawn(discord::main(
-            bot_token.to_string(),
-            channel_id,
-            webhook_token.to_string(),
-            webhook_id,
-            bridge.plugin,
-        ));
-
-        // Return a 'ClientSide' Plugin to insert into Azalea
-        bridge.client
+        let routes = Routes::new().with(Route::new(channel_id));
+        let webhooks = vec![(channel_id, webhook_id, webhook_token.to_string())];
+
+        let avatars = Avatars::new(Some(DEFAULT_AVATAR_URL));
+        Self::build(bot_token, routes, webhooks, avatars, ignore_list)
+    }
+
+    /// Bridges the channels of each route, which need to have targets.
+    ///
+    /// Webhooks are created in each channel, so the bot needs permission to manage them.
+    pub async fn routed(
+        bot_token: &str,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<DiscordPlugin> {
+        let avatars = Avatars::new(Some(DEFAULT_AVATAR_URL));
+        Self::build(bot_token, routes, Vec::new(), avatars, ignore_list)
+    }
+
+    /// Like [`DiscordPlugin::routed`], but players' avatars come from another head renderer.
+    ///
+    /// The url can use `{uuid}`, `{name}` and `{texture}`, the id of the player's skin.
+    /// Messages from players are sent without avatars if it's `None`.
+    pub async fn routed_with_avatars(
+        bot_token: &str,
+        routes: Routes,
+        avatar_url: Option<&str>,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<DiscordPlugin> {
+        let avatars = Avatars::new(avatar_url);
+        Self::build(bot_token, routes, Vec::new(), avatars, ignore_list)
+    }
+
+    fn build(
+        bot_token: &str,
+        routes: Routes,
+        webhooks: Vec<(u64, u64, String)>,
+        avatars: Avatars,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<DiscordPlugin> {
+        // The http client is separate from the gateway, so startup a new one.
+        let http = HttpClient::new(bot_token.to_string());
+        let plugin = discord::connect(bot_token.to_string(), http, &routes, webhooks, avatars);
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build()
     }
 }
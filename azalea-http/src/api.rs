@@ -0,0 +1,451 @@
+use async_trait::async_trait;
+use azalea_bridge::{
+    Author, AzaleaEvent, BridgeMessage, BridgePlatform, Capabilities, MessageId, PluginEvent,
+};
+use flume::Sender;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{io::Read, time::Duration};
+use tiny_http::{Header, Method, Request, Response, Server};
+use uuid::Uuid;
+
+use crate::{history::Entry, HttpPlugin};
+
+pub(crate) const PLATFORM: &str = "http";
+
+// The JSON Schema for requests and responses, served at `GET /schema`
+const SCHEMA: &str = include_str!("../schema.json");
+
+// Larger bodies are rejected instead of read
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+// How often to check if Azalea has stopped
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// The body of `POST /messages`
+#[derive(Debug, Deserialize)]
+struct PostMessage {
+    // Defaults to the client name
+    name: Option<String>,
+    content: String,
+    channel: Option<String>,
+}
+
+// The response to `GET /messages`
+#[derive(Debug, Serialize)]
+struct Messages {
+    messages: Vec<Entry>,
+    // The `since` to use for the next request
+    next: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Error<'a> {
+    error: &'a str,
+}
+
+#[async_trait]
+impl BridgePlatform for HttpPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let server = Server::http(self.config.address).map_err(|e| anyhow::anyhow!(e))?;
+        info!("HTTP server listening on {}", self.config.address);
+
+        // tiny_http blocks, so serve requests on their own thread
+        let plugin = self.clone();
+        tokio::task::spawn_blocking(move || {
+            while !tx.is_disconnected() {
+                match server.recv_timeout(POLL_INTERVAL) {
+                    Ok(Some(request)) => plugin.respond(request, &tx),
+                    Ok(None) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        let mut history = self.history.lock().unwrap();
+        match event {
+            AzaleaEvent::Chat(message, targets) => {
+                history.push(message, targets);
+            }
+            AzaleaEvent::Edit(message, _) => history.edit(message),
+            AzaleaEvent::Delete(id) => history.delete(&id),
+            // Notices are replies to commands, which can't be sent over HTTP
+            AzaleaEvent::Notice(..) => {}
+        }
+        Ok(())
+    }
+}
+
+impl HttpPlugin {
+    fn respond(&self, mut request: Request, tx: &Sender<PluginEvent>) {
+        let response = match self.route(&mut request, tx) {
+            Ok(response) => response,
+            Err((code, message)) => json(code, &Error { error: message }),
+        };
+
+        if let Err(e) = request.respond(response) {
+            warn!("HttpPlugin unable to respond to request: {e}");
+        }
+    }
+
+    fn route(&self, request: &mut Request, tx: &Sender<PluginEvent>) -> Result<Reply, Failure> {
+        let (path, query) = match request.url().split_once('?') {
+            Some((path, query)) => (path.to_string(), query.to_string()),
+            None => (request.url().to_string(), String::new()),
+        };
+
+        // The schema is public, everything else needs a token
+        match (request.method(), path.trim_end_matches('/')) {
+            (Method::Get, "/schema") => {
+                return Ok(Response::from_string(SCHEMA).with_header(content_type()))
+            }
+            (_, "/messages") => {}
+            _ => return Err((404, "Not found")),
+        }
+
+        let client = self.authenticate(request).ok_or((401, "Invalid token"))?;
+        match request.method() {
+            Method::Get => self.get_messages(&query),
+            Method::Post => self.post_messages(request, &client, tx),
+            _ => Err((405, "Method not allowed")),
+        }
+    }
+
+    // The client name for the request's bearer token
+    fn authenticate(&self, request: &Request) -> Option<String> {
+        let header = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))?;
+        let token = header.value.as_str().strip_prefix("Bearer ")?;
+        self.config.tokens.get(token.trim()).cloned()
+    }
+
+    fn get_messages(&self, query: &str) -> Result<Reply, Failure> {
+        let mut since = 0;
+        let mut channel = None;
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "since" => since = value.parse().map_err(|_| (400, "Invalid since"))?,
+                "channel" => channel = Some(decode(value)),
+                _ => {}
+            }
+        }
+
+        let history = self.history.lock().unwrap();
+        let messages = Messages {
+            messages: history.since(since, channel.as_deref()),
+            next: history.last_seq(),
+        };
+        Ok(json(200, &messages))
+    }
+
+    fn post_messages(
+        &self,
+        request: &mut Request,
+        client: &str,
+        tx: &Sender<PluginEvent>,
+    ) -> Result<Reply, Failure> {
+        let mut body = String::new();
+        request
+            .as_reader()
+            .take(MAX_BODY_BYTES + 1)
+            .read_to_string(&mut body)
+            .map_err(|_| (400, "Invalid body"))?;
+        if body.len() as u64 > MAX_BODY_BYTES {
+            return Err((413, "Body too large"));
+        }
+
+        let post: PostMessage =
+            serde_json::from_str(&body).map_err(|_| (400, "Invalid message"))?;
+        if post.content.trim().is_empty() {
+            return Err((400, "Empty message"));
+        }
+        let channel = match post.channel {
+            Some(channel) if self.channels.contains(&channel) => channel,
+            None if !self.channels.is_empty() => self.channels[0].clone(),
+            _ => return Err((404, "Unknown channel")),
+        };
+
+        let (id, name) = match post.name {
+            Some(name) => (format!("{client}/{name}"), name),
+            None => (client.to_string(), client.to_string()),
+        };
+        let message = BridgeMessage::new(
+            MessageId::new(PLATFORM, Uuid::new_v4()),
+            channel.clone(),
+            Author::new(id, name),
+            post.content,
+        );
+
+        // Posted messages can be read back like any other
+        let entry = self
+            .history
+            .lock()
+            .unwrap()
+            .push(message.clone(), vec![channel]);
+
+        // Send message to Azalea
+        if let Err(e) = tx.send(PluginEvent::Chat(message)) {
+            error!("HttpPlugin unable to send message to Azalea: {e}");
+            return Err((503, "Unable to send message"));
+        }
+        Ok(json(201, &entry))
+    }
+}
+
+type Reply = Response<std::io::Cursor<Vec<u8>>>;
+
+// A status code and error message
+type Failure = (u16, &'static str);
+
+fn json(code: u16, body: &impl Serialize) -> Reply {
+    let body = serde_json::to_string(body).unwrap_or_default();
+    Response::from_string(body)
+        .with_status_code(code)
+        .with_header(content_type())
+}
+
+fn content_type() -> Header {
+    Header::from_bytes("Content-Type", "application/json").unwrap()
+}
+
+// Undo percent-encoding in a query value
+fn decode(value: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut input = value.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next().unwrap_or(b'0'), input.next().unwrap_or(b'0')];
+                let hex = std::str::from_utf8(&hex).unwrap_or("00");
+                bytes.push(u8::from_str_radix(hex, 16).unwrap_or(b'?'));
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{history::History, HttpConfig};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    // Start a server on a free port with the token `secret`
+    async fn server(channels: &[&str]) -> (HttpPlugin, String, flume::Receiver<PluginEvent>) {
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config = HttpConfig::new(address).token("secret", "script");
+        let plugin = HttpPlugin {
+            history: Arc::new(Mutex::new(History::new(config.history))),
+            config: Arc::new(config),
+            channels: channels.iter().map(|s| s.to_string()).collect(),
+        };
+
+        let (tx, rx) = flume::unbounded();
+        let started = plugin.clone();
+        tokio::spawn(async move { started.start(tx).await });
+        (plugin, address.to_string(), rx)
+    }
+
+    // Send a request, retrying until the server is listening, and return the status and body
+    async fn request(
+        address: &str,
+        method: &str,
+        path: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> (u16, Value) {
+        let mut stream = loop {
+            match TcpStream::connect(address).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+
+        let mut head = format!("{method} {path} HTTP/1.1\r\nhost: {address}\r\n");
+        if let Some(token) = token {
+            head.push_str(&format!("authorization: Bearer {token}\r\n"));
+        }
+        head.push_str(&format!("content-length: {}\r\n", body.len()));
+        head.push_str("connection: close\r\n\r\n");
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(body.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    async fn post(address: &str, body: Value) -> (u16, Value) {
+        let body = body.to_string();
+        request(address, "POST", "/messages", Some("secret"), &body).await
+    }
+
+    async fn get(address: &str, path: &str) -> Value {
+        let (status, body) = request(address, "GET", path, Some("secret"), "").await;
+        assert_eq!(status, 200, "{body}");
+        body
+    }
+
+    fn message(id: &str, content: &str) -> BridgeMessage {
+        let id = MessageId::new("discord", id);
+        BridgeMessage::new(
+            id,
+            "1".to_string(),
+            Author::new("1", "Notch"),
+            content.to_string(),
+        )
+    }
+
+    fn contents(body: &Value) -> Vec<&str> {
+        let messages = body["messages"].as_array().unwrap();
+        messages
+            .iter()
+            .map(|entry| entry["message"]["content"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn needs_a_token() {
+        let (_, address, _rx) = server(&["http"]).await;
+
+        let invalid = json!({"error": "Invalid token"});
+        let missing = request(&address, "GET", "/messages", None, "").await;
+        assert_eq!(missing, (401, invalid.clone()));
+        let wrong = request(&address, "GET", "/messages", Some("wrong"), "").await;
+        assert_eq!(wrong, (401, invalid.clone()));
+        let body = json!({"content": "hello"}).to_string();
+        let post = request(&address, "POST", "/messages", Some("wrong"), &body).await;
+        assert_eq!(post, (401, invalid));
+
+        // The schema is public
+        let (status, _) = request(&address, "GET", "/schema", None, "").await;
+        assert_eq!(status, 200);
+    }
+
+    #[tokio::test]
+    async fn posts_messages() {
+        let (_, address, rx) = server(&["http", "staff"]).await;
+
+        let (status, entry) = post(&address, json!({"name": "Alice", "content": "hello"})).await;
+        assert_eq!(status, 201);
+        assert_eq!(entry["channels"], json!(["http"]));
+        let Ok(PluginEvent::Chat(message)) = rx.try_recv() else {
+            panic!("no chat sent to Azalea");
+        };
+        assert_eq!(message.id.platform, PLATFORM);
+        assert_eq!(message.channel, "http");
+        assert_eq!(message.author.id, "script/Alice");
+        assert_eq!(message.author.name, "Alice");
+        assert_eq!(message.content, "hello");
+
+        // Without a name, messages are from the client
+        let body = json!({"content": "hello", "channel": "staff"});
+        let (status, entry) = post(&address, body).await;
+        assert_eq!(status, 201);
+        assert_eq!(entry["message"]["author"]["name"], "script");
+        assert_eq!(entry["message"]["channel"], "staff");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_messages() {
+        let (_, address, rx) = server(&["http"]).await;
+
+        let large = "a".repeat(MAX_BODY_BYTES as usize + 1);
+        let too_large = post(&address, json!({ "content": large })).await;
+        assert_eq!(too_large, (413, json!({"error": "Body too large"})));
+        let invalid = request(&address, "POST", "/messages", Some("secret"), "{").await;
+        assert_eq!(invalid, (400, json!({"error": "Invalid message"})));
+        let empty = post(&address, json!({"content": "  "})).await;
+        assert_eq!(empty, (400, json!({"error": "Empty message"})));
+        let unknown = post(&address, json!({"content": "hello", "channel": "staff"})).await;
+        assert_eq!(unknown, (404, json!({"error": "Unknown channel"})));
+
+        assert!(rx.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pages_messages() {
+        let (plugin, address, _rx) = server(&["http"]).await;
+        for (id, content) in [("1", "one"), ("2", "two")] {
+            plugin
+                .handle(AzaleaEvent::Chat(message(id, content), Vec::new()))
+                .await
+                .unwrap();
+        }
+
+        let first = get(&address, "/messages").await;
+        assert_eq!(contents(&first), ["one", "two"]);
+        assert_eq!(first["next"], 2);
+
+        plugin
+            .handle(AzaleaEvent::Chat(message("3", "three"), Vec::new()))
+            .await
+            .unwrap();
+        let second = get(&address, "/messages?since=2").await;
+        assert_eq!(contents(&second), ["three"]);
+        assert_eq!(second["next"], 3);
+        let none = get(&address, "/messages?since=3").await;
+        assert!(contents(&none).is_empty());
+        assert_eq!(none["next"], 3);
+
+        let path = "/messages?since=soon";
+        let invalid = request(&address, "GET", path, Some("secret"), "").await;
+        assert_eq!(invalid, (400, json!({"error": "Invalid since"})));
+    }
+
+    #[tokio::test]
+    async fn filters_messages_by_channel() {
+        let (plugin, address, _rx) = server(&["http", "staff room"]).await;
+        let targets = [("1", "http"), ("2", "staff room")];
+        for (id, channel) in targets {
+            let chat = AzaleaEvent::Chat(message(id, channel), vec![channel.to_string()]);
+            plugin.handle(chat).await.unwrap();
+        }
+        plugin
+            .handle(AzaleaEvent::Chat(message("3", "everywhere"), Vec::new()))
+            .await
+            .unwrap();
+
+        let staff = get(&address, "/messages?channel=staff%20room").await;
+        assert_eq!(contents(&staff), ["staff room", "everywhere"]);
+        let http = get(&address, "/messages?since=1&channel=http").await;
+        assert_eq!(contents(&http), ["everywhere"]);
+    }
+
+    #[test]
+    fn decodes_query_values() {
+        assert_eq!(decode("staff"), "staff");
+        assert_eq!(decode("staff+room"), "staff room");
+        assert_eq!(decode("staff%20room%2Fa"), "staff room/a");
+        assert_eq!(decode("%E2%9C%93"), "✓");
+        // Broken escapes don't fail the request
+        assert_eq!(decode("%zz"), "?");
+    }
+}
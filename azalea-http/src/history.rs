@@ -0,0 +1,161 @@
+use azalea_bridge::{BridgeMessage, MessageId};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// A message returned by `GET /messages`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Entry {
+    // Increases with every message, to poll with `since`
+    pub seq: u64,
+    // The channels the message was sent to, or empty for all of them
+    pub channels: Vec<String>,
+    pub message: BridgeMessage,
+}
+
+// The most recent messages, oldest first
+#[derive(Debug)]
+pub(crate) struct History {
+    entries: VecDeque<Entry>,
+    capacity: usize,
+    last_seq: u64,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            last_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, message: BridgeMessage, channels: Vec<String>) -> Entry {
+        self.last_seq += 1;
+        let entry = Entry {
+            seq: self.last_seq,
+            channels,
+            message,
+        };
+
+        self.entries.push_back(entry.clone());
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+        entry
+    }
+
+    // Edited messages are moved to the end, so clients polling with `since` see them again
+    pub fn edit(&mut self, message: BridgeMessage) {
+        if let Some(index) = self.position(&message.id) {
+            if let Some(entry) = self.entries.remove(index) {
+                self.push(message, entry.channels);
+            }
+        }
+    }
+
+    pub fn delete(&mut self, id: &MessageId) {
+        if let Some(index) = self.position(id) {
+            self.entries.remove(index);
+        }
+    }
+
+    // Messages after `since`, optionally only those sent to a channel
+    pub fn since(&self, since: u64, channel: Option<&str>) -> Vec<Entry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.seq > since)
+            .filter(|entry| match channel {
+                Some(channel) => {
+                    entry.channels.is_empty() || entry.channels.iter().any(|c| c == channel)
+                }
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    fn position(&self, id: &MessageId) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| &entry.message.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::Author;
+
+    fn message(id: &str, content: &str) -> BridgeMessage {
+        let id = MessageId::new("discord", id);
+        BridgeMessage::new(
+            id,
+            "1".to_string(),
+            Author::new("1", "Notch"),
+            content.to_string(),
+        )
+    }
+
+    fn contents(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|e| e.message.content.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_the_latest_messages() {
+        let mut history = History::new(2);
+        for (id, content) in [("1", "one"), ("2", "two"), ("3", "three")] {
+            history.push(message(id, content), Vec::new());
+        }
+
+        assert_eq!(contents(&history.since(0, None)), ["two", "three"]);
+        assert_eq!(contents(&history.since(2, None)), ["three"]);
+        assert!(history.since(3, None).is_empty());
+        assert_eq!(history.last_seq(), 3);
+    }
+
+    #[test]
+    fn filters_by_channel() {
+        let mut history = History::new(10);
+        history.push(message("1", "web"), vec!["web".to_string()]);
+        history.push(message("2", "staff"), vec!["staff".to_string()]);
+        history.push(message("3", "everywhere"), Vec::new());
+
+        let web = history.since(0, Some("web"));
+        assert_eq!(contents(&web), ["web", "everywhere"]);
+        assert_eq!(contents(&history.since(0, None)).len(), 3);
+    }
+
+    #[test]
+    fn edited_messages_move_to_the_end() {
+        let mut history = History::new(10);
+        history.push(message("1", "hello"), vec!["web".to_string()]);
+        history.push(message("2", "world"), Vec::new());
+
+        history.edit(message("1", "hello again"));
+        let entries = history.since(0, None);
+        assert_eq!(contents(&entries), ["world", "hello again"]);
+        assert_eq!(entries[1].seq, 3);
+        assert_eq!(entries[1].channels, ["web"]);
+        // Clients that have already seen the original see the edit too
+        assert_eq!(contents(&history.since(2, None)), ["hello again"]);
+
+        // Unknown messages aren't added
+        history.edit(message("4", "new"));
+        assert_eq!(history.since(0, None).len(), 2);
+    }
+
+    #[test]
+    fn deletes_messages() {
+        let mut history = History::new(10);
+        history.push(message("1", "hello"), Vec::new());
+        history.push(message("2", "world"), Vec::new());
+
+        history.delete(&MessageId::new("discord", "1"));
+        history.delete(&MessageId::new("discord", "3"));
+        assert_eq!(contents(&history.since(0, None)), ["world"]);
+    }
+}
@@ -0,0 +1,78 @@
+use azalea_bridge::{ClientSide, PlatformBuilder, Route, Routes};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+mod api;
+mod history;
+
+// The channel messages are posted to if they don't choose one
+pub const DEFAULT_CHANNEL: &str = "http";
+
+/// Where to listen and who can post messages.
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub address: SocketAddr,
+    // Client names, by the bearer token they authenticate with
+    pub tokens: HashMap<String, String>,
+    // How many messages `GET /messages` can return
+    pub history: usize,
+}
+
+impl HttpConfig {
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            tokens: HashMap::new(),
+            history: 256,
+        }
+    }
+
+    // Let a client connect with a bearer token
+    pub fn token(mut self, token: &str, name: &str) -> Self {
+        self.tokens.insert(token.to_string(), name.to_string());
+        self
+    }
+
+    pub fn history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpPlugin {
+    config: Arc<HttpConfig>,
+    channels: Vec<String>,
+    history: Arc<Mutex<history::History>>,
+}
+
+impl HttpPlugin {
+    /// Starts the HTTP server, see the README for the endpoints.
+    pub async fn new(config: HttpConfig, ignore_list: Vec<&str>) -> ClientSide<HttpPlugin> {
+        let routes = Routes::new().with(Route::new(DEFAULT_CHANNEL));
+        Self::routed(config, routes, ignore_list).await
+    }
+
+    /// Like [`HttpPlugin::new`], but messages can be posted to the channel of any route.
+    ///
+    /// Messages are posted to the first channel if they don't choose one.
+    pub async fn routed(
+        config: HttpConfig,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<HttpPlugin> {
+        let plugin = HttpPlugin {
+            history: Arc::new(Mutex::new(history::History::new(config.history))),
+            config: Arc::new(config),
+            channels: routes.targets(),
+        };
+
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build()
+    }
+}
@@ -0,0 +1,469 @@
+use async_trait::async_trait;
+use azalea_bridge::{
+    Author, AzaleaEvent, BridgeMessage, BridgePlatform, Capabilities, MessageId, PluginEvent,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flume::Sender;
+use futures::StreamExt;
+use irc::{
+    client::prelude::{Capability, Client, Command, Config, Message, Response},
+    proto::CapSubCommand,
+};
+use log::{error, info, warn};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{format, IrcPlugin};
+
+pub(crate) const PLATFORM: &str = "irc";
+
+// Lines are limited to 512 bytes, including the prefix the server adds
+const MAX_LINE_BYTES: usize = 400;
+
+// Most characters of the nickname to keep in the fallback, leaving room for the digits
+const FALLBACK_NICK_LENGTH: usize = 12;
+
+// How long to wait before reconnecting, doubling after each failed attempt
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+
+// mIRC colours that can be read on light and dark backgrounds
+const NICK_COLOURS: [u8; 10] = [2, 3, 4, 5, 6, 7, 10, 11, 12, 13];
+
+#[async_trait]
+impl BridgePlatform for IrcPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    // IRC only has plain messages
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            match self.connect(&tx, &mut delay).await {
+                Ok(()) => warn!("Disconnected from IRC"),
+                Err(e) => error!("IRC connection failed: {e}"),
+            }
+            *self.sender.lock().unwrap() = None;
+
+            // Stop once Azalea is gone
+            if tx.is_disconnected() {
+                return Ok(());
+            }
+
+            info!("Reconnecting to IRC in {}s", delay.as_secs());
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        let Some(sender) = self.sender.lock().unwrap().clone() else {
+            return Err(anyhow::Error::msg("Not connected to IRC"));
+        };
+
+        match event {
+            AzaleaEvent::Chat(message, targets) => {
+                let name = colour_nick(&message.author.name);
+                let width = MAX_LINE_BYTES.saturating_sub(name.len() + 3);
+
+                // Keep the colours of Minecraft messages
+                let content = message
+                    .ansi
+                    .as_deref()
+                    .and_then(|ansi| format::from_minecraft(ansi, &message.content))
+                    .unwrap_or_else(|| {
+                        message
+                            .content
+                            .lines()
+                            .map(to_irc)
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    });
+
+                for channel in self.targets(&targets) {
+                    for line in content.lines().filter(|line| !line.is_empty()) {
+                        for part in split_line(line, width) {
+                            sender.send_privmsg(channel, format!("<{name}> {part}"))?;
+                        }
+                    }
+                }
+            }
+            AzaleaEvent::Notice(notice, channel) => {
+                sender.send_notice(&channel, to_irc(&notice))?;
+            }
+            // Edits are sent as new messages and deletions are dropped by the bridge
+            AzaleaEvent::Edit(..) | AzaleaEvent::Delete(_) => {}
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        if let Some(sender) = self.sender.lock().unwrap().take() {
+            sender.send_quit("Bridge stopped")?;
+        }
+        Ok(())
+    }
+}
+
+impl IrcPlugin {
+    // Stay connected until the server closes the connection
+    async fn connect(&self, tx: &Sender<PluginEvent>, delay: &mut Duration) -> anyhow::Result<()> {
+        let config = &self.config;
+        let nickname = config.nickname.clone();
+
+        let mut client = Client::from_config(Config {
+            nickname: Some(nickname.clone()),
+            // Used in order when the nickname is taken
+            alt_nicks: (1..=3)
+                .map(|i| format!("{nickname}{}", "_".repeat(i)))
+                .chain([fallback_nick(&nickname)])
+                .collect(),
+            server: Some(config.server.clone()),
+            port: Some(config.port),
+            use_tls: Some(config.tls),
+            password: config.password.clone(),
+            channels: self.channels.clone(),
+            ..Config::default()
+        })
+        .await?;
+        let mut stream = client.stream()?;
+
+        match &config.sasl {
+            // Registering normally ends capability negotiation before SASL can be used
+            Some(_) => {
+                client.send_cap_req(&[Capability::Sasl])?;
+                if let Some(password) = &config.password {
+                    client.send(Command::PASS(password.clone()))?;
+                }
+                client.send(Command::NICK(nickname.clone()))?;
+                client.send(Command::USER(nickname.clone(), "0".to_string(), nickname))?;
+            }
+            None => client.identify()?,
+        }
+        *self.sender.lock().unwrap() = Some(client.sender());
+
+        while let Some(message) = stream.next().await.transpose()? {
+            match &message.command {
+                Command::CAP(_, CapSubCommand::ACK, _, _) => {
+                    client.send(Command::AUTHENTICATE("PLAIN".to_string()))?;
+                }
+                Command::CAP(_, CapSubCommand::NAK, _, _) => {
+                    return Err(anyhow::Error::msg("Server does not support SASL"));
+                }
+                Command::AUTHENTICATE(data) if data == "+" => {
+                    if let Some((account, password)) = &config.sasl {
+                        let credentials = format!("{account}\0{account}\0{password}");
+                        client.send(Command::AUTHENTICATE(STANDARD.encode(credentials)))?;
+                    }
+                }
+                Command::Response(Response::RPL_SASLSUCCESS, _) => {
+                    client.send(Command::CAP(None, CapSubCommand::END, None, None))?;
+                }
+                Command::Response(Response::ERR_SASLFAIL, _) => {
+                    return Err(anyhow::Error::msg("SASL authentication failed"));
+                }
+                Command::Response(Response::RPL_WELCOME, _) => {
+                    info!("Connected to IRC as {}", client.current_nickname());
+                    if client.current_nickname() != config.nickname {
+                        warn!("The nickname {} is taken", config.nickname);
+                    }
+                    *delay = MIN_RECONNECT_DELAY;
+                }
+                Command::PRIVMSG(target, text) => {
+                    let own_nick = client.current_nickname().to_string();
+                    self.relay(&own_nick, &message, target, text, tx).await;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Send a channel message to Azalea
+    async fn relay(
+        &self,
+        own_nick: &str,
+        message: &Message,
+        target: &str,
+        text: &str,
+        tx: &Sender<PluginEvent>,
+    ) {
+        let Some(nick) = message.source_nickname() else {
+            return;
+        };
+
+        // Don't send our own messages or private messages
+        if nick == own_nick {
+            return;
+        }
+        let Some(channel) = self
+            .channels
+            .iter()
+            .find(|channel| channel.eq_ignore_ascii_case(target))
+        else {
+            return;
+        };
+
+        // Actions are the only CTCP messages shown
+        let content = if let Some(action) = text.strip_prefix("\x01ACTION ") {
+            format!("* {nick} {}", action.trim_end_matches('\x01'))
+        } else if text.starts_with('\x01') {
+            return;
+        } else {
+            text.to_string()
+        };
+
+        let message = BridgeMessage::new(
+            MessageId::new(PLATFORM, Uuid::new_v4()),
+            channel.clone(),
+            Author::new(nick, nick),
+            strip_formatting(&content),
+        );
+
+        // Send message to Azalea
+        if let Err(e) = tx.send_async(PluginEvent::Chat(message)).await {
+            error!("IrcPlugin unable to send message to Azalea: {e}");
+        }
+    }
+
+    // Get the channels to send a message to, which is all of them if none are given
+    fn targets<'a>(&'a self, targets: &'a [String]) -> impl Iterator<Item = &'a String> {
+        self.channels.iter().filter(move |channel| {
+            targets.is_empty()
+                || targets
+                    .iter()
+                    .any(|target| target.eq_ignore_ascii_case(channel))
+        })
+    }
+}
+
+// A nickname that probably isn't taken, for when the nickname and its alternatives are
+fn fallback_nick(nickname: &str) -> String {
+    let nickname: String = nickname.chars().take(FALLBACK_NICK_LENGTH).collect();
+    let digits = Uuid::new_v4().as_u128() % 10_000;
+    format!("{nickname}{digits:04}")
+}
+
+// Colour a name so it's easier to follow, without pinging anyone with the same nick
+fn colour_nick(name: &str) -> String {
+    let sum: usize = name.bytes().map(usize::from).sum();
+    let colour = NICK_COLOURS[sum % NICK_COLOURS.len()];
+
+    let mut chars = to_irc(name).chars().collect::<Vec<_>>();
+    if !chars.is_empty() {
+        chars.insert(1, '\u{200B}');
+    }
+    format!(
+        "\x03{colour:02}{}\x03",
+        chars.into_iter().collect::<String>()
+    )
+}
+
+// Remove control characters, so messages can't add formatting or CTCP commands
+fn to_irc(message: &str) -> String {
+    message.chars().filter(|c| !c.is_control()).collect()
+}
+
+// Remove bold, colours and other formatting, which can't be shown in Minecraft
+fn strip_formatting(message: &str) -> String {
+    let mut result = String::new();
+    let mut chars = message.chars().peekable();
+
+    // Skip up to `max` characters matching `valid`
+    let skip = |chars: &mut std::iter::Peekable<std::str::Chars>, max, valid: fn(&char) -> bool| {
+        for _ in 0..max {
+            if chars.next_if(valid).is_none() {
+                break;
+            }
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Colours look like `\x03FG,BG`, with up to two digits each
+            '\x03' => {
+                skip(&mut chars, 2, char::is_ascii_digit);
+                if chars.peek() == Some(&',') {
+                    chars.next();
+                    skip(&mut chars, 2, char::is_ascii_digit);
+                }
+            }
+            // Hex colours look like `\x04RRGGBB,RRGGBB`
+            '\x04' => {
+                skip(&mut chars, 6, char::is_ascii_hexdigit);
+                if chars.peek() == Some(&',') {
+                    chars.next();
+                    skip(&mut chars, 6, char::is_ascii_hexdigit);
+                }
+            }
+            '\x02' | '\x0F' | '\x11' | '\x16' | '\x1D' | '\x1E' | '\x1F' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+// Split a line to fit in a message, preferring to break between words
+fn split_line(line: &str, max_bytes: usize) -> Vec<String> {
+    let max_bytes = max_bytes.max(4);
+    let mut parts = Vec::new();
+    let mut rest = line.trim();
+
+    while rest.len() > max_bytes {
+        // Don't split a character
+        let mut end = max_bytes;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let split = rest[..end].rfind(' ').filter(|&i| i > 0).unwrap_or(end);
+
+        parts.push(rest[..split].to_string());
+        rest = rest[split..].trim_start();
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IrcConfig;
+    use std::sync::Arc;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    const NICKNAME: &str = "azalea";
+    const CHANNEL: &str = "#test";
+
+    // A small IRC server, where the nickname and its alternatives are taken
+    async fn ircd(listener: TcpListener, received: flume::Sender<String>) {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, mut write) = socket.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let (mut nick, mut user) = (None, false);
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+            let argument = argument.trim_start_matches(':').to_string();
+
+            let mut reply = String::new();
+            match command {
+                "NICK" if argument == NICKNAME || argument.ends_with('_') => {
+                    reply = format!(":irc.test 433 * {argument} :Nickname is already in use\r\n");
+                }
+                "NICK" => nick = Some(argument),
+                "USER" => user = true,
+                "JOIN" => {
+                    let nick = nick.as_deref().unwrap_or_default();
+                    reply = format!(
+                        ":{nick}!bot@test JOIN {argument}\r\n\
+                        :alex!alex@test PRIVMSG {argument} :\x02hello\x02 \x0304there\r\n"
+                    );
+                }
+                _ => {}
+            }
+            // Welcome the bot once it has a nickname
+            if let (Some(nick), true, "NICK" | "USER") = (&nick, user, command) {
+                reply = format!(
+                    ":irc.test 001 {nick} :Welcome\r\n:irc.test 376 {nick} :End of MOTD\r\n"
+                );
+            }
+
+            write.write_all(reply.as_bytes()).await.unwrap();
+            let _ = received.send(line);
+        }
+    }
+
+    // Wait for the bot to send a command
+    async fn next_command(received: &flume::Receiver<String>, command: &str) -> String {
+        let wait = async {
+            loop {
+                let line = received.recv_async().await.unwrap();
+                if line.starts_with(command) {
+                    return line;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .unwrap_or_else(|_| panic!("{command} wasn't sent"))
+    }
+
+    #[test]
+    fn fallback_nick_is_short() {
+        let nick = fallback_nick("AVeryLongNicknameIndeed");
+        assert!(nick.starts_with("AVeryLongNic"));
+        assert_eq!(nick.len(), FALLBACK_NICK_LENGTH + 4);
+        assert!(nick[FALLBACK_NICK_LENGTH..]
+            .chars()
+            .all(|c| c.is_ascii_digit()));
+    }
+
+    #[tokio::test]
+    async fn relays_through_a_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (received_tx, received) = flume::unbounded();
+        tokio::spawn(ircd(listener, received_tx));
+
+        let plugin = IrcPlugin {
+            config: Arc::new(IrcConfig::new("127.0.0.1", NICKNAME).port(port)),
+            channels: vec![CHANNEL.to_string()],
+            sender: Arc::default(),
+        };
+        let (tx, rx) = flume::unbounded();
+        tokio::spawn({
+            let plugin = plugin.clone();
+            async move { plugin.start(tx).await }
+        });
+
+        // Every alternative is taken, so the fallback is used
+        for _ in 0..4 {
+            next_command(&received, "NICK").await;
+        }
+        let nick = next_command(&received, "NICK").await;
+        let nick = nick.trim_start_matches("NICK ").trim_start_matches(':');
+        assert!(nick.starts_with(NICKNAME) && !nick.ends_with('_'));
+        next_command(&received, "JOIN").await;
+
+        // Messages from IRC are sent without their formatting
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv_async())
+            .await
+            .unwrap()
+            .unwrap();
+        let PluginEvent::Chat(message) = event else {
+            panic!("Expected a chat message");
+        };
+        assert_eq!(message.author.name, "alex");
+        assert_eq!(message.channel, CHANNEL);
+        assert_eq!(message.content, "hello there");
+
+        // Messages from Minecraft keep their colours
+        let author = Author::new("069a79f4-44e9-4726-a5be-fca90e38aaf5", "Notch");
+        let id = MessageId::new("minecraft", "1");
+        let mut message = BridgeMessage::new(id, String::new(), author, "hi".to_string());
+        message.ansi = Some("<Notch> \x1b[38;2;255;85;85mhi".to_string());
+        plugin
+            .handle(AzaleaEvent::Chat(message, Vec::new()))
+            .await
+            .unwrap();
+        let line = next_command(&received, "PRIVMSG").await;
+        let expected = format!("<{}> \x0304hi", colour_nick("Notch"));
+        assert_eq!(
+            line.split_once(" :").map(|(_, text)| text),
+            Some(&*expected)
+        );
+
+        plugin.shutdown().await.unwrap();
+        next_command(&received, "QUIT").await;
+    }
+}
@@ -0,0 +1,225 @@
+// Minecraft's colours and styles, read from the ANSI escape codes Azalea gives its messages
+
+// Minecraft's colours and the mIRC colours closest to them
+const COLOURS: [(u32, u8); 16] = [
+    (0x000000, 1),
+    (0x0000AA, 2),
+    (0x00AA00, 3),
+    (0x00AAAA, 10),
+    (0xAA0000, 5),
+    (0xAA00AA, 6),
+    (0xFFAA00, 7),
+    (0xAAAAAA, 15),
+    (0x555555, 14),
+    (0x5555FF, 12),
+    (0x55FF55, 9),
+    (0x55FFFF, 11),
+    (0xFF5555, 4),
+    (0xFF55FF, 13),
+    (0xFFFF55, 8),
+    (0xFFFFFF, 0),
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    // A mIRC colour, or the client's own text colour
+    colour: Option<u8>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+}
+
+// Format a message from Minecraft with IRC control codes,
+// or None if a template or filter changed it so the colours no longer line up
+pub(crate) fn from_minecraft(ansi: &str, content: &str) -> Option<String> {
+    let styled = parse_ansi(ansi);
+
+    // Player messages are usually shown as `<Name> message`, so only keep the end
+    let skip = styled.len().checked_sub(content.chars().count())?;
+    let matches = styled[skip..].iter().map(|(c, _)| *c).eq(content.chars());
+    if content.is_empty() || !matches {
+        return None;
+    }
+    Some(render(&styled[skip..]))
+}
+
+// Get each character of a message with its style, leaving out any other control characters
+fn parse_ansi(ansi: &str) -> Vec<(char, Style)> {
+    let mut styled = Vec::new();
+    let mut style = Style::default();
+    let mut chars = ansi.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Escape codes look like `\x1b[1;2m`
+            '\x1b' if chars.as_str().starts_with('[') => {
+                let rest = &chars.as_str()[1..];
+                let Some(end) = rest.find(|c: char| c.is_ascii_alphabetic()) else {
+                    break;
+                };
+                if rest[end..].starts_with('m') {
+                    apply(&mut style, &rest[..end]);
+                }
+                chars = rest[end + 1..].chars();
+            }
+            '\n' => styled.push((c, style)),
+            c if c.is_control() => {}
+            c => styled.push((c, style)),
+        }
+    }
+    styled
+}
+
+// Change a style with the parameters of an escape code
+fn apply(style: &mut Style, parameters: &str) {
+    let mut codes = parameters
+        .split(';')
+        .map(|code| code.parse::<u32>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        match code {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            3 => style.italic = true,
+            4 => style.underlined = true,
+            9 => style.strikethrough = true,
+            22 => style.bold = false,
+            23 => style.italic = false,
+            24 => style.underlined = false,
+            29 => style.strikethrough = false,
+            // Azalea only uses 24-bit colours
+            38 => {
+                if codes.next() != Some(2) {
+                    continue;
+                }
+                let rgb = codes
+                    .by_ref()
+                    .take(3)
+                    .fold(0, |rgb, c| (rgb << 8) | c.min(255));
+                style.colour = closest_colour(rgb);
+            }
+            39 => style.colour = None,
+            _ => {}
+        }
+    }
+}
+
+// White is left to the client, so it can be read on light backgrounds
+fn closest_colour(rgb: u32) -> Option<u8> {
+    let channels = |rgb: u32| [rgb >> 16, (rgb >> 8) & 0xFF, rgb & 0xFF].map(|c| c as i32);
+    let distance = |other: u32| {
+        let (a, b) = (channels(rgb), channels(other));
+        (0..3).map(|i| (a[i] - b[i]).pow(2)).sum::<i32>()
+    };
+
+    let (_, colour) = COLOURS.iter().min_by_key(|(other, _)| distance(*other))?;
+    Some(*colour).filter(|&colour| colour != 0)
+}
+
+fn render(styled: &[(char, Style)]) -> String {
+    let mut result = String::new();
+    let mut current = Style::default();
+
+    for &(c, style) in styled {
+        // Every line is sent on its own, so its style starts over
+        if c == '\n' {
+            result.push(c);
+            current = Style::default();
+            continue;
+        }
+
+        if style != current {
+            if current != Style::default() {
+                result.push('\x0F');
+            }
+            let mut codes = String::new();
+            if let Some(colour) = style.colour {
+                codes.push_str(&format!("\x03{colour:02}"));
+            }
+            for (on, code) in [
+                (style.bold, '\x02'),
+                (style.italic, '\x1D'),
+                (style.underlined, '\x1F'),
+                (style.strikethrough, '\x1E'),
+            ] {
+                if on {
+                    codes.push(code);
+                }
+            }
+            // A comma right after a colour would be read as a background colour
+            if c == ',' && codes.ends_with(|c: char| c.is_ascii_digit()) {
+                codes.push_str("\x02\x02");
+            }
+            result.push_str(&codes);
+            current = style;
+        }
+        result.push(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: &str = "\x1b[38;2;255;85;85m";
+    const RESET: &str = "\x1b[m";
+
+    #[test]
+    fn keeps_colours_and_styles() {
+        let ansi = format!("{RED}red\x1b[1m bold{RESET} plain");
+        assert_eq!(
+            from_minecraft(&ansi, "red bold plain"),
+            Some("\x0304red\x0F\x0304\x02 bold\x0F plain".to_string())
+        );
+    }
+
+    #[test]
+    fn only_formats_the_message() {
+        let ansi = format!("<Notch> {RED}hi");
+        assert_eq!(from_minecraft(&ansi, "hi"), Some("\x0304hi".to_string()));
+    }
+
+    #[test]
+    fn changed_messages_are_not_formatted() {
+        let ansi = format!("<Notch> {RED}hello");
+        assert_eq!(from_minecraft(&ansi, "h***o"), None);
+        assert_eq!(from_minecraft(&ansi, "Notch: hello"), None);
+        assert_eq!(from_minecraft(&ansi, ""), None);
+    }
+
+    #[test]
+    fn colours_are_the_closest_irc_colour() {
+        assert_eq!(closest_colour(0xFFFF55), Some(8));
+        assert_eq!(closest_colour(0x1010A0), Some(2));
+        // White is the default text colour
+        assert_eq!(closest_colour(0xFEFEFE), None);
+    }
+
+    #[test]
+    fn comma_after_colour() {
+        let ansi = format!("{RED},5");
+        assert_eq!(
+            from_minecraft(&ansi, ",5"),
+            Some("\x0304\x02\x02,5".to_string())
+        );
+    }
+
+    #[test]
+    fn lines_start_over() {
+        let ansi = format!("{RED}one\ntwo");
+        assert_eq!(
+            from_minecraft(&ansi, "one\ntwo"),
+            Some("\x0304one\n\x0304two".to_string())
+        );
+    }
+
+    #[test]
+    fn drops_other_control_characters() {
+        let ansi = "a\x01ACTION\x07b\x1b[2Jc";
+        assert_eq!(
+            from_minecraft(ansi, "aACTIONbc"),
+            Some("aACTIONbc".to_string())
+        );
+    }
+}
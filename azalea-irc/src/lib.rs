@@ -0,0 +1,90 @@
+use azalea_bridge::{ClientSide, PlatformBuilder, Route, Routes};
+use irc::client::Sender;
+use std::sync::{Arc, Mutex};
+
+mod connection;
+mod format;
+
+/// How to connect to an IRC server.
+#[derive(Debug, Clone)]
+pub struct IrcConfig {
+    pub server: String,
+    pub port: u16,
+    pub tls: bool,
+    pub nickname: String,
+    // The server password, if there is one
+    pub password: Option<String>,
+    // The account and password to log in with using SASL
+    pub sasl: Option<(String, String)>,
+}
+
+impl IrcConfig {
+    pub fn new(server: &str, nickname: &str) -> Self {
+        Self {
+            server: server.to_string(),
+            port: 6667,
+            tls: false,
+            nickname: nickname.to_string(),
+            password: None,
+            sasl: None,
+        }
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    // Connect with TLS, which is usually on port 6697
+    pub fn tls(mut self) -> Self {
+        self.tls = true;
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    pub fn sasl(mut self, account: &str, password: &str) -> Self {
+        self.sasl = Some((account.to_string(), password.to_string()));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IrcPlugin {
+    config: Arc<IrcConfig>,
+    channels: Vec<String>,
+    // Only set while connected
+    sender: Arc<Mutex<Option<Sender>>>,
+}
+
+impl IrcPlugin {
+    pub async fn new(
+        config: IrcConfig,
+        channel: &str,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<IrcPlugin> {
+        let routes = Routes::new().with(Route::new(channel));
+        Self::routed(config, routes, ignore_list).await
+    }
+
+    /// Joins the channels of each route, which need to have targets.
+    pub async fn routed(
+        config: IrcConfig,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<IrcPlugin> {
+        let plugin = IrcPlugin {
+            config: Arc::new(config),
+            channels: routes.targets(),
+            sender: Arc::default(),
+        };
+
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build()
+    }
+}
@@ -1,10 +1,16 @@
-use azalea_bridge::{ClientSide, PluginBridge};
-use matrix_sdk_appservice::{AppServiceBuilder, AppServiceRegistration};
+use azalea_bridge::{ClientSide, PlatformBuilder, Route, Routes};
+use matrix_sdk::{room::Room, ruma::api::appservice::Namespace};
+use matrix_sdk_appservice::{AppService, AppServiceBuilder, AppServiceRegistration};
 
 mod matrix;
 
 #[derive(Debug, Clone)]
-pub struct MatrixPlugin;
+pub struct MatrixPlugin {
+    appservice: AppService,
+    namespace: Namespace,
+    rooms: Vec<Room>,
+    relayed: matrix::Relayed,
+}
 
 impl MatrixPlugin {
     /// Starts the Matrix bot and gives you a plugin piece to give to Azalea.
@@ -16,13 +22,30 @@ impl MatrixPlugin {
         ignore_list: Vec<&str>,
         bot_name: Option<String>,
         bot_image: Option<String>,
-    ) -> Result<ClientSide<MatrixPlugin>, matrix_sdk_appservice::Error> {
-        let room = room_id.to_string();
-        let list: Vec<String> = ignore_list.iter().map(|s| s.to_string()).collect();
-
-        // Create commucation channel
-        let bridge = PluginBridge::<MatrixPlugin>::new(list);
+    ) -> anyhow::Result<ClientSide<MatrixPlugin>> {
+        let routes = Routes::new().with(Route::new(room_id));
+        Self::routed(
+            server_url,
+            server_name,
+            registration,
+            routes,
+            ignore_list,
+            bot_name,
+            bot_image,
+        )
+        .await
+    }
 
+    /// Like [`MatrixPlugin::new`], but bridges the rooms of each route.
+    pub async fn routed(
+        server_url: &str,
+        server_name: &str,
+        registration: &str,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+        bot_name: Option<String>,
+        bot_image: Option<String>,
+    ) -> anyhow::Result<ClientSide<MatrixPlugin>> {
         // Create the AppService
         let appservice = AppServiceBuilder::new(
             server_url.parse()?,
@@ -32,16 +55,11 @@ impl MatrixPlugin {
         .build()
         .await?;
 
-        // Spawn Matrix bot
-        tokio::spawn(matrix::startup(
-            bot_name,
-            bot_image,
-            room,
-            appservice,
-            bridge.plugin,
-        ));
-
-        // Return a 'ClientSide' Plugin to insert into Azalea
-        Ok(bridge.client)
+        // Join the rooms before starting
+        let plugin = matrix::connect(bot_name, bot_image, &routes, appservice).await?;
+        Ok(PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build())
     }
 }
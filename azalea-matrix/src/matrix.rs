@@ -1,29 +1,67 @@
-use azalea_bridge::{AzaleaEvent, PluginSide, PluginEvent};
-use flume::{Receiver, Sender};
-use log::{error, warn, info};
+use async_trait::async_trait;
+use azalea_bridge::{
+    parse_link_command, split_mentions, storage, Attachment, AttachmentKind, Author, AzaleaEvent,
+    BridgeMessage, BridgePlatform, Capabilities, Identities, LinkCodes, MessageId, MessageMap,
+    PluginEvent, Reply, Routes, Segment, MINECRAFT_PLATFORM,
+};
+use flume::Sender;
+use log::{error, info, warn};
 use matrix_sdk::{
+    attachment::AttachmentConfig,
+    config::SyncSettings,
     event_handler::Ctx,
-    room::Room,
-    ruma::{events::room::{message::{MessageType, OriginalSyncRoomMessageEvent, RoomMessageEventContent, TextMessageEventContent}, member::{OriginalSyncRoomMemberEvent, MembershipState}, join_rules::JoinRule}, UserId, api::{client::error::ErrorKind, appservice::{Namespace, Namespaces}}}, config::SyncSettings,
+    room::{Joined, Room},
+    ruma::{
+        api::{
+            appservice::{Namespace, Namespaces},
+            client::error::ErrorKind,
+        },
+        events::room::{
+            join_rules::JoinRule,
+            member::{MembershipState, OriginalSyncRoomMemberEvent},
+            message::{
+                InReplyTo, MessageType, OriginalSyncRoomMessageEvent, Relation, Replacement,
+                RoomMessageEventContent, TextMessageEventContent,
+            },
+            redaction::OriginalSyncRoomRedactionEvent,
+            MediaSource,
+        },
+        EventId, UserId,
+    },
 };
 use matrix_sdk_appservice::AppService;
+use std::collections::HashMap;
+use uuid::Uuid;
 
 use crate::MatrixPlugin;
 
-pub(crate) async fn startup(
+pub(crate) const PLATFORM: &str = "matrix";
+
+// Registered users and their display names
+const USER_NAMESPACE: &str = "matrix-users";
+
+// Largest file to upload instead of sending a link
+const MAX_UPLOAD_SIZE: usize = 8 * 1024 * 1024;
+
+// Messages relayed from other plugins, for each room id
+pub(crate) type Relayed = HashMap<String, MessageMap>;
+
+// Set up the bot and join the rooms of each route
+pub(crate) async fn connect(
     bot_name: Option<String>,
     _bot_image: Option<String>,
-    target_room: String,
+    routes: &Routes,
     appservice: AppService,
-    plugin: PluginSide<MatrixPlugin>
-) -> anyhow::Result<()> {
+) -> anyhow::Result<MatrixPlugin> {
     appservice
-        .register_user_query(Box::new(|_, req| Box::pin(async move {
-            info!("Got request for {}", req.user_id);
-            true
-         })))
+        .register_user_query(Box::new(|_, req| {
+            Box::pin(async move {
+                info!("Got request for {}", req.user_id);
+                true
+            })
+        }))
         .await;
- 
+
     let client = appservice.user(None).await?;
     client.sync_once(SyncSettings::default()).await?;
 
@@ -43,163 +81,608 @@ pub(crate) async fn startup(
     // let url = client.account().upload_avatar(Mime, Vec::new()).await?;
     // client.account().set_avatar_url(Some(url)).await?;
 
-    // Add context for later
-    client.add_event_handler_context(appservice.clone());
-    client.add_event_handler_context(plugin.tx);
+    // Get target rooms
+    let mut rooms = Vec::new();
+    let mut relayed = Relayed::new();
+    for target in routes.targets() {
+        let room = match get_room(target.clone(), client.clone()) {
+            Ok(room) => room,
+            Err(e) => {
+                error!("{e:?}");
+                continue;
+            }
+        };
+
+        // Join if invited
+        if let Some(invited) = client.get_invited_room(room.room_id()) {
+            client.join_room_by_id(invited.room_id()).await?;
+        }
 
-    // Handle room invites
-    client.add_event_handler(mx_room_handler);
+        // Messages relayed from other plugins
+        let map = MessageMap::open_for("matrix-messages", &target).unwrap_or_else(|e| {
+            error!("Unable to load relayed messages: {e}");
+            MessageMap::default()
+        });
+        relayed.insert(room.room_id().to_string(), map);
+        rooms.push(room);
+    }
+
+    // Get namespace
+    let namespace = get_namespace(appservice.registration().namespaces.clone()).unwrap();
 
-    // Get target room
-    let room = get_room(target_room, client.clone()).unwrap();
+    Ok(MatrixPlugin {
+        appservice,
+        namespace,
+        rooms,
+        relayed,
+    })
+}
 
-    // Join if invited
-    if let Some(invited) = client.get_invited_room(room.room_id()) {
-        client.join_room_by_id(invited.room_id()).await?;
+#[async_trait]
+impl BridgePlatform for MatrixPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
     }
 
-    client.add_event_handler_context(room.clone());
+    // Puppets don't have profile pictures yet
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            avatars: false,
+            ..Capabilities::all()
+        }
+    }
 
-    // Handle room events
-    room.add_event_handler(mx_message_handler);
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let appservice = &self.appservice;
+        let client = appservice.user(None).await?;
 
-    // Get namespace
-    let namespace = get_namespace(appservice.registration().namespaces.clone()).unwrap();
+        // Add context for later
+        client.add_event_handler_context(appservice.clone());
+        client.add_event_handler_context(tx);
+        client.add_event_handler_context(self.relayed.clone());
+        client.add_event_handler_context(self.rooms.clone());
 
-    // Listen for events from channel
-    tokio::spawn(mc_message_handler(appservice.clone(), namespace, room, plugin.rx));
+        // Handle room invites
+        client.add_event_handler(mx_room_handler);
 
-    // Run AppService
-    let (host, port) = appservice.registration().get_host_and_port().unwrap();
-    appservice.run(host, port).await?;
-    
-    Ok(())
-}
+        // Handle room events
+        for room in &self.rooms {
+            room.add_event_handler(mx_message_handler);
+            room.add_event_handler(mx_redaction_handler);
+        }
 
-async fn mc_message_handler(appservice: AppService, namespace: Namespace, room: Room, rx: Receiver<AzaleaEvent>) -> anyhow::Result<()> {
-    // Listen for messages from Plugin
-    while let Ok(event) = rx.recv_async().await {
-        match event {
-            // Chat messages
-            AzaleaEvent::Chat(profile, packet) => {
-                let username = if let Some(username) = packet.username() { username } else { profile.name };
-                let uuid = if let Some(uuid) = packet.uuid() { uuid } else { profile.uuid };
+        // Run AppService
+        let (host, port) = appservice.registration().get_host_and_port().unwrap();
+        appservice.run(host, port).await?;
 
-                // Kind of gross but does the job?
-                let localpart = format!("{}{}", namespace.regex.trim_start_matches('@').trim_end_matches(".*"), uuid.to_string().replace('-', "_"));
+        Ok(())
+    }
 
-                // Register the user if using for the first time
-                if !appservice.users().contains_key(&localpart) {
-                    if let Err(e) = appservice.register_user(&localpart, None).await {
-                        // Do not error if the user is already in use
-                        if let Err(e) = should_error(e) {
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        let MatrixPlugin {
+            appservice,
+            namespace,
+            rooms,
+            relayed,
+        } = self;
+
+        match event {
+            // Chat messages, sent as their own users
+            AzaleaEvent::Chat(origin, targets) => {
+                for room in target_rooms(rooms, &targets) {
+                    let Some(relayed) = relayed.get(room.room_id().as_str()) else {
+                        continue;
+                    };
+
+                    let room = match get_user_room(
+                        appservice,
+                        namespace,
+                        room,
+                        puppet_uuid(&origin),
+                        &origin.author.name,
+                    )
+                    .await
+                    {
+                        Ok(room) => room,
+                        Err(e) => {
                             error!("{e:?}");
                             continue;
                         }
-                    }    
-                }
+                    };
 
-                // Get the user
-                let user = appservice.user(Some(&localpart)).await.unwrap();
+                    let mut content = origin.content.clone();
+                    let mut reply_to = None;
 
-                // Sync the first time the user is used
-                if user.rooms().is_empty() {
-                    user.sync_once(SyncSettings::default()).await.unwrap();
-
-                    // Set profile picture on sync
-                    let account = user.account();
-                    if let Ok(icon) = account.get_avatar_url().await {
-                        if matches!(icon, None) {
-                        // TODO: Get property, convert to Vec<u8>, upload, and set avatar url
-                        // info!("{:?}", profile);
+                    // Reply to the message if it was relayed here, otherwise quote it
+                    if let Some(reply) = &origin.reply {
+                        match relayed
+                            .local_id(PLATFORM, &reply.id)
+                            .and_then(|id| EventId::parse(id).ok())
+                        {
+                            Some(event_id) => {
+                                content = format!(
+                                    "> <{}> {}\n\n{content}",
+                                    reply.username, reply.snippet
+                                );
+                                reply_to = Some(event_id);
+                            }
+                            None => content = format!("{reply} {content}"),
                         }
                     }
-                }
 
-                // Set username if different
-                {
-                    let account = user.account();
-                    if let Some(current) = account.get_display_name().await.unwrap() {
-                        if username != current {
-                            account.set_display_name(Some(&username)).await.unwrap();
+                    // Send message
+                    let mut event =
+                        RoomMessageEventContent::new(MessageType::Text(to_matrix(&content)));
+                    if let Some(event_id) = reply_to {
+                        event.relates_to = Some(Relation::Reply {
+                            in_reply_to: InReplyTo::new(event_id),
+                        });
+                    }
+                    let response = match room.send(event, None).await {
+                        Ok(response) => response,
+                        Err(e) => {
+                            error!("Unable to send message: {e}");
+                            continue;
+                        }
+                    };
+
+                    // Remember the message so it can be edited and deleted
+                    relayed.insert(origin.id.clone(), response.event_id);
+
+                    // Upload relayed files, or link them if that fails
+                    for attachment in &origin.attachments {
+                        if let Err(e) = send_attachment(&room, attachment).await {
+                            warn!("Unable to upload attachment {}: {e}", attachment.url);
+
+                            let event = RoomMessageEventContent::text_plain(attachment.to_string());
+                            if let Err(e) = room.send(event, None).await {
+                                error!("Unable to send attachment link: {e}");
+                            }
                         }
-                    } else {
-                        account.set_display_name(Some(&username)).await.unwrap();
                     }
                 }
+            }
+            // Edits from other plugins, applied to every copy of the message
+            AzaleaEvent::Edit(message, _) => {
+                for room in rooms {
+                    let Some(local) = relayed
+                        .get(room.room_id().as_str())
+                        .and_then(|relayed| relayed.get(&message.id))
+                    else {
+                        continue;
+                    };
+                    let Ok(event_id) = EventId::parse(local) else {
+                        continue;
+                    };
 
-                // If user hasn't joined the room
-                if matches!(user.get_joined_room(room.room_id()), None) {
-                    // If the room is not public and the user has not been invited
-                    if room.join_rule() != JoinRule::Public && matches!(user.get_invited_room(room.room_id()), None) {
-                        // Send invite
-                        let Room::Joined(room) = room.clone() else {
-                            error!("Bot has not joined room!");
-                            continue;
-                        };
-
-                        if let Err(e) = room.invite_user_by_id(user.user_id().unwrap()).await {
-                            error!("Unable to send bot user an invite to the room: {e}");
+                    let room = match get_user_room(
+                        appservice,
+                        namespace,
+                        room,
+                        puppet_uuid(&message),
+                        &message.author.name,
+                    )
+                    .await
+                    {
+                        Ok(room) => room,
+                        Err(e) => {
+                            error!("{e:?}");
                             continue;
-                        };
+                        }
+                    };
 
-                        // Wait a tiny amount of time
-                        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+                    // Send a replacement for the relayed message
+                    let mut event =
+                        RoomMessageEventContent::text_plain(format!("* {}", message.content));
+                    event.relates_to = Some(Relation::Replacement(Replacement::new(
+                        event_id,
+                        Box::new(RoomMessageEventContent::new(MessageType::Text(to_matrix(
+                            &message.content,
+                        )))),
+                    )));
 
-                        // Sync again, just to make sure the invite was received
-                        user.sync_once(SyncSettings::default()).await.unwrap();
+                    if let Err(e) = room.send(event, None).await {
+                        error!("Unable to edit message: {e}");
                     }
+                }
+            }
+            // Deletions from other plugins, applied to every copy of the message
+            AzaleaEvent::Delete(id) => {
+                for room in rooms {
+                    let Some(local) = relayed
+                        .get(room.room_id().as_str())
+                        .and_then(|relayed| relayed.remove(&id))
+                    else {
+                        continue;
+                    };
+                    let Ok(event_id) = EventId::parse(local) else {
+                        continue;
+                    };
 
-                    // Join the room / accept invite
-                    user.join_room_by_id(room.room_id()).await.unwrap();
-               }
+                    // Deletions don't say who sent the message, so the bot needs to be able to redact it
+                    let Room::Joined(room) = room else {
+                        error!("Bot has not joined room!");
+                        continue;
+                    };
 
-                // The room has been joined
-                let Room::Joined(room) = user.get_room(room.room_id()).unwrap() else {
-                    error!("Bot user has not joined room!");
-                    continue;
+                    if let Err(e) = room.redact(&event_id, None, None).await {
+                        error!("Unable to redact message: {e}");
+                    }
+                }
+            }
+            // Notices are sent by the bot itself
+            AzaleaEvent::Notice(notice, target) => {
+                let Some(Room::Joined(room)) = rooms.iter().find(|room| *room.room_id() == target)
+                else {
+                    error!("Bot has not joined room {target}!");
+                    return Ok(());
                 };
 
-                // Send message
-                let event = RoomMessageEventContent::new(MessageType::Text(TextMessageEventContent::plain(packet.content())));
-                room.send(event, None).await.unwrap();
+                if let Err(e) = room
+                    .send(RoomMessageEventContent::notice_plain(notice), None)
+                    .await
+                {
+                    error!("Unable to send notice: {e}");
+                }
             }
-            _ => {}
         }
+        Ok(())
+    }
+}
+
+// Get the rooms to send a message to, which is all of them if none are given
+fn target_rooms<'a>(rooms: &'a [Room], targets: &'a [String]) -> impl Iterator<Item = &'a Room> {
+    rooms.iter().filter(move |room| {
+        targets.is_empty() || targets.iter().any(|target| *room.room_id() == *target)
+    })
+}
+
+// The user messages are sent as, players keep their own uuid and anyone else gets one for their platform
+fn puppet_uuid(message: &BridgeMessage) -> Uuid {
+    if message.id.platform == MINECRAFT_PLATFORM {
+        if let Ok(uuid) = message.author.id.parse() {
+            return uuid;
+        }
+    }
+    Uuid::new_v5(
+        &Uuid::NAMESPACE_URL,
+        format!("{}:{}", message.id.platform, message.author.id).as_bytes(),
+    )
+}
+
+// Get the room as the user for a player, registering and joining as needed
+async fn get_user_room(
+    appservice: &AppService,
+    namespace: &Namespace,
+    room: &Room,
+    uuid: Uuid,
+    username: &str,
+) -> anyhow::Result<Joined> {
+    // Kind of gross but does the job?
+    let localpart = format!(
+        "{}{}",
+        namespace
+            .regex
+            .trim_start_matches('@')
+            .trim_end_matches(".*"),
+        uuid.to_string().replace('-', "_")
+    );
+
+    // The display name last set for the user, if it was registered before
+    let saved_name = storage().get(USER_NAMESPACE, &localpart)?;
+
+    // Register the user if using for the first time
+    if saved_name.is_none() && !appservice.users().contains_key(&localpart) {
+        if let Err(e) = appservice.register_user(&localpart, None).await {
+            // Do not error if the user is already in use
+            should_error(e)?;
+        }
+        storage().set(USER_NAMESPACE, &localpart, "")?;
+    }
+
+    // Get the user
+    let user = appservice.user(Some(&localpart)).await?;
+
+    // Sync the first time the user is used
+    if user.rooms().is_empty() {
+        user.sync_once(SyncSettings::default()).await?;
+
+        // Set profile picture on sync
+        let account = user.account();
+        if let Ok(icon) = account.get_avatar_url().await {
+            if matches!(icon, None) {
+                // TODO: Get property, convert to Vec<u8>, upload, and set avatar url
+                // info!("{:?}", profile);
+            }
+        }
+    }
+
+    // Set username if different
+    if saved_name.as_deref() != Some(username) {
+        let account = user.account();
+        if let Some(current) = account.get_display_name().await? {
+            if username != current {
+                account.set_display_name(Some(username)).await?;
+            }
+        } else {
+            account.set_display_name(Some(username)).await?;
+        }
+        storage().set(USER_NAMESPACE, &localpart, username)?;
+    }
+
+    // If user hasn't joined the room
+    if matches!(user.get_joined_room(room.room_id()), None) {
+        // If the room is not public and the user has not been invited
+        if room.join_rule() != JoinRule::Public
+            && matches!(user.get_invited_room(room.room_id()), None)
+        {
+            // Send invite
+            let Room::Joined(room) = room.clone() else {
+                return Err(anyhow::Error::msg("Bot has not joined room!"));
+            };
+
+            if let Err(e) = room.invite_user_by_id(user.user_id().unwrap()).await {
+                return Err(anyhow::Error::msg(format!(
+                    "Unable to send bot user an invite to the room: {e}"
+                )));
+            };
+
+            // Wait a tiny amount of time
+            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+
+            // Sync again, just to make sure the invite was received
+            user.sync_once(SyncSettings::default()).await?;
+        }
+
+        // Join the room / accept invite
+        user.join_room_by_id(room.room_id()).await?;
     }
-    error!("MatrixPlugin event listener exited!");
-    Err(anyhow::Error::msg("Event listener exited!"))
+
+    // The room has been joined
+    let Some(Room::Joined(room)) = user.get_room(room.room_id()) else {
+        return Err(anyhow::Error::msg("Bot user has not joined room!"));
+    };
+    Ok(room)
 }
 
 async fn mx_message_handler(
     event: OriginalSyncRoomMessageEvent,
     room: Room,
     appservice: Ctx<AppService>,
+    relayed: Ctx<Relayed>,
     tx: Ctx<Sender<PluginEvent>>,
 ) {
     if appservice.user_id_is_in_namespace(&event.sender) {
         return;
     }
-    match event.content.msgtype {
-        MessageType::Text(message) => {
-            // Get the sender
-            let Ok(Some(sender)) = room.get_member(&event.sender).await else { 
-                warn!("MatrixPlugin was unable to get message sender");
-                return
-            };
 
-            // Get a username
-            let username = if let Some(displayname) = sender.display_name() {
-                displayname
-            } else {
-                sender.name()
-            }.to_string();
+    // Edits replace the content of an earlier message
+    let (id, msgtype, edit, reply_to) = match event.content.relates_to {
+        Some(Relation::Replacement(replacement)) => (
+            replacement.event_id,
+            replacement.new_content.msgtype,
+            true,
+            None,
+        ),
+        Some(Relation::Reply { in_reply_to }) => (
+            event.event_id,
+            event.content.msgtype,
+            false,
+            Some(in_reply_to.event_id),
+        ),
+        _ => (event.event_id, event.content.msgtype, false, None),
+    };
+
+    let (body, attachment) = match msgtype {
+        MessageType::Text(message) => (message.body, None),
+        MessageType::Image(image) => {
+            let mime = image.info.as_ref().and_then(|info| info.mimetype.clone());
+            (
+                String::new(),
+                get_attachment(
+                    &room,
+                    AttachmentKind::Image,
+                    image.body,
+                    &image.source,
+                    mime,
+                )
+                .await,
+            )
+        }
+        MessageType::Video(video) => {
+            let mime = video.info.as_ref().and_then(|info| info.mimetype.clone());
+            (
+                String::new(),
+                get_attachment(
+                    &room,
+                    AttachmentKind::Video,
+                    video.body,
+                    &video.source,
+                    mime,
+                )
+                .await,
+            )
+        }
+        MessageType::Audio(audio) => {
+            let mime = audio.info.as_ref().and_then(|info| info.mimetype.clone());
+            (
+                String::new(),
+                get_attachment(
+                    &room,
+                    AttachmentKind::Audio,
+                    audio.body,
+                    &audio.source,
+                    mime,
+                )
+                .await,
+            )
+        }
+        MessageType::File(file) => {
+            let mime = file.info.as_ref().and_then(|info| info.mimetype.clone());
+            let name = file.filename.unwrap_or(file.body);
+            (
+                String::new(),
+                get_attachment(&room, AttachmentKind::File, name, &file.source, mime).await,
+            )
+        }
+        _ => return,
+    };
+
+    // Link accounts instead of sending the code
+    if let (Some(code), false) = (parse_link_command(&body), edit) {
+        let reply = match LinkCodes::global().redeem(code) {
+            Some(player) => {
+                Identities::global().link(&player, PLATFORM, &event.sender);
+                info!("Linked {} to {player}", event.sender);
+                format!("Linked to {player}")
+            }
+            None => "That code is invalid or has expired".to_string(),
+        };
+
+        if let Room::Joined(room) = &room {
+            if let Err(e) = room
+                .send(RoomMessageEventContent::text_plain(reply), None)
+                .await
+            {
+                error!("Unable to reply to link command: {e}");
+            }
+        }
+        return;
+    }
+
+    // Get the sender
+    let Ok(Some(sender)) = room.get_member(&event.sender).await else {
+        warn!("MatrixPlugin was unable to get message sender");
+        return;
+    };
+
+    // Get a username
+    let username = if let Some(displayname) = sender.display_name() {
+        displayname
+    } else {
+        sender.name()
+    }
+    .to_string();
+
+    let (fallback, content) = strip_reply_fallback(&body);
+    let content = replace_user_ids(&room, &content).await;
+
+    // Replies to relayed messages refer to the original message
+    let mut reply = None;
+    if let Some(event_id) = reply_to {
+        let origin = relayed
+            .get(room.room_id().as_str())
+            .and_then(|relayed| relayed.origin(event_id.as_str()));
+        let id = origin.unwrap_or_else(|| MessageId::new(PLATFORM, &event_id));
+        let (sender, quoted) = fallback.unwrap_or_default();
 
-            // Send message to Plugin
-            drop(tx.send_async(PluginEvent::Chat(username, message.body)).await);
+        // Try to use the display name of the quoted user
+        let mut name = sender.clone();
+        if let Ok(user_id) = UserId::parse(sender.as_str()) {
+            if let Ok(Some(member)) = room.get_member(&user_id).await {
+                name = member.display_name().unwrap_or(member.name()).to_string();
+            }
         }
-        _ => {}
+
+        reply = Some(Reply::new(id, name, &quoted));
+    }
+
+    let mut message = BridgeMessage::new(
+        MessageId::new(PLATFORM, id),
+        room.room_id().to_string(),
+        Author::new(&event.sender, username),
+        content,
+    );
+    message.reply = reply;
+    message.attachments.extend(attachment);
+    if let Some(timestamp) = event.origin_server_ts.to_system_time() {
+        message.timestamp = timestamp;
+    }
+
+    // Send message to Plugin
+    if edit {
+        drop(tx.send_async(PluginEvent::Edit(message)).await);
+    } else {
+        drop(tx.send_async(PluginEvent::Chat(message)).await);
+    }
+}
+
+// Get a link to an uploaded file
+async fn get_attachment(
+    room: &Room,
+    kind: AttachmentKind,
+    name: String,
+    source: &MediaSource,
+    mime: Option<String>,
+) -> Option<Attachment> {
+    // Encrypted files can't be linked
+    let MediaSource::Plain(uri) = source else {
+        return None;
+    };
+    let (server_name, media_id) = uri.parts().ok()?;
+
+    let homeserver = room.client().homeserver().await;
+    let url = format!(
+        "{}/_matrix/media/v3/download/{server_name}/{media_id}",
+        homeserver.as_str().trim_end_matches('/')
+    );
+
+    Some(Attachment {
+        kind,
+        name,
+        url,
+        mime,
+    })
+}
+
+// Download a relayed file and upload it to the room
+async fn send_attachment(room: &Joined, attachment: &Attachment) -> anyhow::Result<()> {
+    if matches!(
+        attachment.kind,
+        AttachmentKind::Sticker | AttachmentKind::Embed
+    ) {
+        return Err(anyhow::Error::msg("Attachment is not a file"));
+    }
+
+    let response = reqwest::get(&attachment.url).await?.error_for_status()?;
+    if response.content_length().unwrap_or(0) as usize > MAX_UPLOAD_SIZE {
+        return Err(anyhow::Error::msg("Attachment is too large"));
+    }
+
+    let data = response.bytes().await?;
+    if data.len() > MAX_UPLOAD_SIZE {
+        return Err(anyhow::Error::msg("Attachment is too large"));
+    }
+
+    let mime = attachment
+        .mime
+        .as_deref()
+        .and_then(|mime| mime.parse().ok())
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    room.send_attachment(
+        &attachment.name,
+        &mime,
+        data.to_vec(),
+        AttachmentConfig::new(),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn mx_redaction_handler(
+    event: OriginalSyncRoomRedactionEvent,
+    appservice: Ctx<AppService>,
+    tx: Ctx<Sender<PluginEvent>>,
+) {
+    if appservice.user_id_is_in_namespace(&event.sender) {
+        return;
     }
+
+    // Send deletion to Plugin
+    drop(
+        tx.send_async(PluginEvent::Delete(MessageId::new(PLATFORM, event.redacts)))
+            .await,
+    );
 }
 
 // Sending an invitation doesn't seem to trigger an event
@@ -207,12 +690,14 @@ async fn mx_message_handler(
 async fn mx_room_handler(
     event: OriginalSyncRoomMemberEvent,
     room: Room,
-    target_room: Ctx<Room>,
-    appservice: Ctx<AppService>
+    target_rooms: Ctx<Vec<Room>>,
+    appservice: Ctx<AppService>,
 ) -> anyhow::Result<()> {
-
-    if room.room_id() != target_room.room_id() {
-        // Check to make sure bot only responds to room id specified in PluginBuilder
+    if !target_rooms
+        .iter()
+        .any(|target| target.room_id() == room.room_id())
+    {
+        // Check to make sure bot only responds to rooms specified in the routes
         warn!("Got event for wrong room: {:?}", room.room_id());
     } else if !appservice.user_id_is_in_namespace(&event.state_key) {
         // Check if the AppService manages this user
@@ -232,31 +717,128 @@ async fn mx_room_handler(
                             error!("{e:?}");
                             return Err(e);
                         }
-                    }    
+                    }
                 }
 
                 // Get the client
                 let client = appservice.user(Some(user_id.localpart())).await.unwrap();
 
                 // Join the room
-                client.join_room_by_id(room.room_id()).await.unwrap();         
-            },
-            _ => {},
-        }    
+                client.join_room_by_id(room.room_id()).await.unwrap();
+            }
+            _ => {}
+        }
     }
     Ok(())
 }
 
-// Do not error if the user is in use
-fn should_error(error: matrix_sdk_appservice::Error) -> anyhow::Result<()> {
-        if let matrix_sdk_appservice::Error::Matrix(error) = error {
-            return if error.client_api_error_kind() == Some(&ErrorKind::UserInUse) {
-                Ok(())
-            } else {
-                Err(error.into())
+// Mention linked players with pills
+fn to_matrix(message: &str) -> TextMessageEventContent {
+    let mut html = String::new();
+    let mut mentioned = false;
+
+    for segment in split_mentions(message) {
+        match segment {
+            Segment::Text(text) => html.push_str(&escape_html(text)),
+            Segment::Mention(name) => match Identities::global().user_id(PLATFORM, name) {
+                Some(user_id) => {
+                    html.push_str(&format!(
+                        "<a href=\"https://matrix.to/#/{}\">{}</a>",
+                        escape_html(&user_id),
+                        escape_html(name)
+                    ));
+                    mentioned = true;
+                }
+                None => html.push_str(&escape_html(&format!("@{name}"))),
+            },
+        }
+    }
+
+    if mentioned {
+        TextMessageEventContent::html(message, html)
+    } else {
+        TextMessageEventContent::plain(message)
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Replace raw user ids like `@user:server` with display names
+async fn replace_user_ids(room: &Room, content: &str) -> String {
+    let mut words = Vec::new();
+    for word in content.split(' ') {
+        // Ignore trailing punctuation
+        let user = word.trim_end_matches(|c: char| matches!(c, ',' | '.' | '!' | '?' | ';'));
+
+        if let Ok(user_id) = UserId::parse(user) {
+            // Prefer the name of a linked player
+            let name = match Identities::global().player(PLATFORM, user_id.as_str()) {
+                Some(player) => Some(player),
+                None => match room.get_member(&user_id).await {
+                    Ok(Some(member)) => {
+                        Some(member.display_name().unwrap_or(member.name()).to_string())
+                    }
+                    _ => None,
+                },
+            };
+
+            if let Some(name) = name {
+                words.push(format!("@{name}{}", &word[user.len()..]));
+                continue;
             }
         }
-        Err(error.into())
+        words.push(word.to_string());
+    }
+    words.join(" ")
+}
+
+// Replies start with a quote of the original message,
+// returns the quoted sender and text along with the actual reply
+fn strip_reply_fallback(body: &str) -> (Option<(String, String)>, String) {
+    if !body.starts_with("> ") {
+        return (None, body.to_string());
+    }
+
+    let mut quoted = Vec::new();
+    let mut lines = body.lines();
+    for line in lines.by_ref() {
+        match line.strip_prefix("> ") {
+            Some(line) => quoted.push(line),
+            None => break,
+        }
+    }
+    let content = lines.collect::<Vec<_>>().join("\n");
+
+    // The first line looks like `> <@user:server> text`
+    let first = quoted.first().copied().unwrap_or_default();
+    let (sender, first) = match first
+        .strip_prefix('<')
+        .and_then(|rest| rest.split_once("> "))
+    {
+        Some((sender, text)) => (sender.to_string(), text),
+        None => (String::new(), first),
+    };
+
+    let mut text = vec![first];
+    text.extend(quoted.iter().skip(1));
+    (Some((sender, text.join(" "))), content)
+}
+
+// Do not error if the user is in use
+fn should_error(error: matrix_sdk_appservice::Error) -> anyhow::Result<()> {
+    if let matrix_sdk_appservice::Error::Matrix(error) = error {
+        return if error.client_api_error_kind() == Some(&ErrorKind::UserInUse) {
+            Ok(())
+        } else {
+            Err(error.into())
+        };
+    }
+    Err(error.into())
 }
 
 // Yeah, it's not a great solution is it?
@@ -305,4 +887,206 @@ fn get_room(target: String, client: matrix_sdk::Client) -> Result<Room, anyhow::
 
     error!("Bot is not in target room");
     Err(anyhow::Error::msg("Bot is not in target room"))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::Route;
+    use matrix_sdk_appservice::{AppServiceBuilder, AppServiceRegistration};
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    // The http method, the path and the JSON body
+    type Request = (String, String, Value);
+
+    const REGISTRATION: &str = "
+id: azalea
+url: http://localhost:9000
+as_token: as_token
+hs_token: hs_token
+sender_localpart: azalea
+namespaces:
+  users:
+    - exclusive: true
+      regex: '@azalea_.*'
+  aliases: []
+  rooms: []
+";
+
+    const ROOM: &str = "!bridged:localhost";
+
+    // Answer client-server API calls like a homeserver, sending each request on to the test
+    async fn stub() -> (String, flume::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = flume::unbounded();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let request = read_request(&mut socket).await;
+                    let (status, body) = respond(&request);
+                    let body = body.to_string();
+                    let _ = tx.send(request);
+
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    socket.write_all(head.as_bytes()).await.unwrap();
+                    socket.write_all(body.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> Request {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "request ended early");
+            data.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&data);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse().ok())
+                .unwrap_or(0);
+            if body.len() >= length {
+                let mut request_line = head.split(' ');
+                let method = request_line.next().unwrap_or_default().to_string();
+                let path = request_line.next().unwrap_or_default().to_string();
+                return (method, path, serde_json::from_str(body).unwrap_or_default());
+            }
+        }
+    }
+
+    // Every user is already in the bridged room, which isn't encrypted
+    fn respond((method, path, body): &Request) -> (&'static str, Value) {
+        let path = path.split('?').next().unwrap_or_default();
+        let not_found = json!({ "errcode": "M_NOT_FOUND", "error": "Not found" });
+
+        if path.ends_with("/versions") {
+            return (
+                "200 OK",
+                json!({ "versions": ["v1.1", "v1.2", "v1.3", "v1.4", "v1.5"] }),
+            );
+        }
+        if path.ends_with("/sync") {
+            let join_rules = json!({
+                "type": "m.room.join_rules",
+                "state_key": "",
+                "content": { "join_rule": "public" },
+                "sender": "@admin:localhost",
+                "event_id": "$join_rules",
+                "origin_server_ts": 0,
+            });
+            let rooms = json!({ "join": { ROOM: { "state": { "events": [join_rules] } } } });
+            return ("200 OK", json!({ "next_batch": "1", "rooms": rooms }));
+        }
+        if path.ends_with("/register") {
+            let user_id = format!(
+                "@{}:localhost",
+                body["username"].as_str().unwrap_or_default()
+            );
+            return ("200 OK", json!({ "user_id": user_id }));
+        }
+        // Puppets don't have display names until they are set
+        if path.contains("/profile/") {
+            return ("200 OK", json!({}));
+        }
+        if path.contains("/send/") {
+            return ("200 OK", json!({ "event_id": "$sent" }));
+        }
+        if path.contains("/redact/") {
+            return ("200 OK", json!({ "event_id": "$redaction" }));
+        }
+        match method.as_str() {
+            "GET" => ("404 Not Found", not_found),
+            _ => ("200 OK", json!({})),
+        }
+    }
+
+    async fn next(requests: &flume::Receiver<Request>, what: &str) -> Request {
+        loop {
+            let request =
+                tokio::time::timeout(std::time::Duration::from_secs(5), requests.recv_async())
+                    .await
+                    .expect("Nothing was sent to the homeserver")
+                    .unwrap();
+            if request.1.contains(what) {
+                return request;
+            }
+        }
+    }
+
+    // Each event reaches the room, sent by the player's puppet where it can be
+    #[tokio::test]
+    async fn conformance() {
+        let (url, requests) = stub().await;
+        let registration = AppServiceRegistration::try_from_yaml_str(REGISTRATION).unwrap();
+        let appservice = AppServiceBuilder::new(
+            url.parse().unwrap(),
+            "localhost".parse().unwrap(),
+            registration,
+        )
+        .build()
+        .await
+        .unwrap();
+
+        let routes = Routes::new().with(Route::new(ROOM));
+        let plugin = connect(None, None, &routes, appservice).await.unwrap();
+        azalea_bridge::check_platform(plugin, ROOM).await;
+
+        // The reply is quoted, since it wasn't relayed here
+        let puppet = "user_id=%40azalea_069a79f4_44e9_4726_a5be_fca90e38aaf5%3Alocalhost";
+        let (method, path, body) = next(&requests, "/send/m.room.message/").await;
+        assert_eq!(method, "PUT");
+        assert!(path.contains(puppet), "Not sent by the puppet: {path}");
+        assert_eq!(
+            body["body"],
+            "[reply to jeb_: \"an earlier message\"] hello"
+        );
+
+        // Links can't be uploaded, so they are sent after the message
+        let (_, _, body) = next(&requests, "/send/m.room.message/").await;
+        assert_eq!(body["body"], "[embed: Example] https://example.com/");
+
+        // Messages for every room
+        let (_, _, body) = next(&requests, "/send/m.room.message/").await;
+        assert_eq!(body["body"], "hello");
+
+        let (_, path, body) = next(&requests, "/send/m.room.message/").await;
+        assert!(path.contains(puppet), "Not edited by the puppet: {path}");
+        assert_eq!(body["body"], "* hello");
+        assert_eq!(body["m.relates_to"]["rel_type"], "m.replace");
+        assert_eq!(body["m.relates_to"]["event_id"], "$sent");
+        assert_eq!(body["m.new_content"]["body"], "hello");
+
+        // Deletions and notices are sent by the bot itself
+        let (method, path, _) = next(&requests, "/redact/").await;
+        assert_eq!(method, "PUT");
+        assert!(path.contains("/redact/$sent/") || path.contains("/redact/%24sent/"));
+        assert!(
+            !path.contains("%40azalea_"),
+            "Not redacted by the bot: {path}"
+        );
+
+        let (_, path, body) = next(&requests, "/send/m.room.message/").await;
+        assert!(!path.contains("%40azalea_"), "Not sent by the bot: {path}");
+        assert_eq!(body["msgtype"], "m.notice");
+        assert_eq!(body["body"], "notice");
+    }
+}
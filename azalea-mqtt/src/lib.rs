@@ -0,0 +1,207 @@
+use azalea_bridge::{ClientSide, PlatformBuilder, Route, Routes, Templates};
+use azalea_client::{packet_handling::KeepAliveEvent, GameProfileComponent};
+use bevy::prelude::{App, EventReader, Plugin, Query, Res, Resource};
+use log::error;
+use rumqttc::{AsyncClient, EventLoop};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+mod mqtt;
+pub use rumqttc::QoS;
+
+// The channel chat from the command topic is sent from
+pub const CHAT_CHANNEL: &str = "mqtt";
+// The channel commands from the command topic are sent from,
+// which is never sent Minecraft chat and never relayed to other plugins
+pub const COMMAND_CHANNEL: &str = "mqtt-commands";
+
+/// The broker to connect to and how to publish to it.
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    // Username and password
+    pub credentials: Option<(String, String)>,
+    pub qos: QoS,
+    pub topics: Topics,
+    // Commands the command topic can run, by name
+    pub commands: Vec<String>,
+}
+
+impl MqttConfig {
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: 1883,
+            client_id: "azalea".to_string(),
+            credentials: None,
+            qos: QoS::AtLeastOnce,
+            topics: Topics::new("azalea"),
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    // Every bot connected to the same broker needs its own id
+    pub fn client_id(mut self, client_id: &str) -> Self {
+        self.client_id = client_id.to_string();
+        self
+    }
+
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    pub fn topics(mut self, topics: Topics) -> Self {
+        self.topics = topics;
+        self
+    }
+
+    // Let the command topic run a command, like `spawn`, which none can by default
+    pub fn allow_command(mut self, command: &str) -> Self {
+        let command = command.trim_start_matches('/');
+        self.commands.push(command.to_string());
+        self
+    }
+}
+
+/// The topic for each kind of event.
+#[derive(Debug, Clone)]
+pub struct Topics {
+    pub chat: String,
+    pub join: String,
+    pub leave: String,
+    pub death: String,
+    // Published every time the server checks the bot is still connected
+    pub health: String,
+    // Retained `online` or `offline`
+    pub status: String,
+    // Subscribed to for chat and commands to send to Minecraft
+    pub command: String,
+}
+
+impl Topics {
+    // Every topic under a prefix, like `azalea/chat`
+    pub fn new(prefix: &str) -> Self {
+        let topic = |name: &str| format!("{}/{name}", prefix.trim_end_matches('/'));
+        Self {
+            chat: topic("chat"),
+            join: topic("join"),
+            leave: topic("leave"),
+            death: topic("death"),
+            health: topic("health"),
+            status: topic("status"),
+            command: topic("command"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MqttPlugin {
+    config: Arc<MqttConfig>,
+    client: AsyncClient,
+    // Taken when the plugin starts
+    event_loop: Arc<Mutex<Option<EventLoop>>>,
+}
+
+// The event loop can't be printed
+impl fmt::Debug for MqttPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttPlugin")
+            .field("config", &self.config)
+            .field("client", &self.client)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MqttPlugin {
+    /// Connects to the broker, publishing Minecraft chat and listening on the command topic.
+    pub async fn new(config: MqttConfig, ignore_list: Vec<&str>) -> MqttBridge {
+        // Commands are sent as typed instead of with a name in front
+        let commands = Templates::new()
+            .with_minecraft("{message}")
+            .expect("Command template is valid");
+        let routes = Routes::new().with(Route::new(CHAT_CHANNEL)).with(
+            Route::new(COMMAND_CHANNEL)
+                .classes([])
+                .local()
+                .with_templates(commands),
+        );
+
+        let plugin = mqtt::connect(config);
+        let health = Health {
+            client: plugin.client.clone(),
+            config: plugin.config.clone(),
+        };
+
+        // Spawn MQTT client and return a Plugin to insert into Azalea
+        let client = PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build();
+        MqttBridge { client, health }
+    }
+}
+
+/// The plugin to insert into Azalea, which also publishes the health of the bot.
+#[derive(Debug, Clone)]
+pub struct MqttBridge {
+    client: ClientSide<MqttPlugin>,
+    health: Health,
+}
+
+impl MqttBridge {
+    // The plugin's side of Azalea, for registering with a `BridgeHub`
+    pub fn client_mut(&mut self) -> &mut ClientSide<MqttPlugin> {
+        &mut self.client
+    }
+}
+
+impl Plugin for MqttBridge {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(self.client.clone())
+            .insert_resource(self.health.clone())
+            .add_system(publish_health);
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+struct Health {
+    client: AsyncClient,
+    config: Arc<MqttConfig>,
+}
+
+// Publish a message each time the server sends a keep alive
+fn publish_health(
+    health: Res<Health>,
+    mut events: EventReader<KeepAliveEvent>,
+    query: Query<&GameProfileComponent>,
+) {
+    for event in events.iter() {
+        let Ok(profile) = query.get(event.entity) else {
+            continue;
+        };
+
+        let payload = mqtt::health(&profile.name);
+        let topic = &health.config.topics.health;
+        if let Err(e) = health
+            .client
+            .try_publish(topic, health.config.qos, false, payload)
+        {
+            error!("MqttPlugin unable to publish health: {e}");
+        }
+    }
+}
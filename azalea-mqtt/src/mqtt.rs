@@ -0,0 +1,424 @@
+use async_trait::async_trait;
+use azalea_bridge::{
+    Author, AzaleaEvent, BridgeMessage, BridgePlatform, Capabilities, EventKind, MessageId,
+    PluginEvent,
+};
+use flume::Sender;
+use log::{error, info, warn};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, Publish};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+
+use crate::{MqttConfig, MqttPlugin, CHAT_CHANNEL, COMMAND_CHANNEL};
+
+pub(crate) const PLATFORM: &str = "mqtt";
+
+// Retained on the status topic
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+// How long to wait before reconnecting, doubling after each failed attempt
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+
+// A message on the command topic
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Command {
+    // Chat from someone, shown with their name in front
+    Chat { name: String, content: String },
+    // A command the bot runs, without the `/`
+    Command { command: String },
+}
+
+// Set up the client, which connects once the plugin is started
+pub(crate) fn connect(config: MqttConfig) -> MqttPlugin {
+    let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Some((username, password)) = &config.credentials {
+        options.set_credentials(username, password);
+    }
+    // The broker marks the bot offline if it disconnects without saying so
+    options.set_last_will(LastWill::new(
+        &config.topics.status,
+        OFFLINE,
+        config.qos,
+        true,
+    ));
+    let (client, event_loop) = AsyncClient::new(options, 64);
+
+    MqttPlugin {
+        config: Arc::new(config),
+        client,
+        event_loop: Arc::new(Mutex::new(Some(event_loop))),
+    }
+}
+
+#[async_trait]
+impl BridgePlatform for MqttPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    // Subscribers only get new messages, so edits are sent as new ones
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            replies: true,
+            attachments: true,
+            avatars: true,
+            ..Capabilities::default()
+        }
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let Some(mut event_loop) = self.event_loop.lock().unwrap().take() else {
+            return Err(anyhow::Error::msg("MqttPlugin was already started"));
+        };
+
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            // The event loop reconnects on its own the next time it's polled
+            let event = match event_loop.poll().await {
+                Ok(event) => event,
+                Err(e) => {
+                    error!("MQTT connection failed: {e}");
+                    if tx.is_disconnected() {
+                        return Ok(());
+                    }
+
+                    info!("Reconnecting to MQTT in {}s", delay.as_secs());
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    continue;
+                }
+            };
+
+            match event {
+                Event::Incoming(Packet::ConnAck(_)) => {
+                    info!("Connected to MQTT broker {}", self.config.host);
+                    delay = MIN_RECONNECT_DELAY;
+                    self.on_connect()?;
+                }
+                Event::Incoming(Packet::Publish(publish)) => {
+                    if publish.topic == self.config.topics.command {
+                        self.relay(&publish, &tx).await;
+                    }
+                }
+                _ => {}
+            }
+
+            // Stop once Azalea is gone
+            if tx.is_disconnected() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        let topics = &self.config.topics;
+        match event {
+            AzaleaEvent::Chat(message, _) => {
+                let topic = match message.kind {
+                    EventKind::Chat => &topics.chat,
+                    EventKind::Join => &topics.join,
+                    EventKind::Leave => &topics.leave,
+                    EventKind::Death => &topics.death,
+                };
+                let payload = serde_json::to_vec(&message)?;
+                self.client
+                    .publish(topic, self.config.qos, false, payload)
+                    .await?;
+            }
+            // Notices are for whoever sent a message, who isn't listening on a topic
+            AzaleaEvent::Notice(notice, _) => warn!("MqttPlugin notice: {notice}"),
+            // Edits are sent as new messages and deletions are dropped by the bridge
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // If the event loop stops before these are sent, the broker publishes the last will instead
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        let status = &self.config.topics.status;
+        self.client
+            .publish(status, self.config.qos, true, OFFLINE)
+            .await?;
+        self.client.disconnect().await?;
+        Ok(())
+    }
+}
+
+impl MqttPlugin {
+    // Subscribe and say the bot is online, which has to be done again after reconnecting
+    fn on_connect(&self) -> anyhow::Result<()> {
+        let topics = &self.config.topics;
+        let qos = self.config.qos;
+
+        // Sent without waiting, since the event loop isn't polled while waiting
+        self.client.try_subscribe(&topics.command, qos)?;
+        self.client.try_publish(&topics.status, qos, true, ONLINE)?;
+        Ok(())
+    }
+
+    // Send a message from the command topic to Azalea
+    async fn relay(&self, publish: &Publish, tx: &Sender<PluginEvent>) {
+        let command = match serde_json::from_slice::<Command>(&publish.payload) {
+            Ok(command) => command,
+            Err(e) => {
+                warn!("Invalid message on {}: {e}", publish.topic);
+                return;
+            }
+        };
+
+        let id = MessageId::new(PLATFORM, Uuid::new_v4());
+        let message = match command {
+            Command::Chat { name, content } => {
+                let author = Author::new(format!("{PLATFORM}/{name}"), name);
+                BridgeMessage::new(id, CHAT_CHANNEL.to_string(), author, content)
+            }
+            Command::Command { command } => {
+                let command = command.trim_start_matches('/');
+                let name = command.split_whitespace().next().unwrap_or_default();
+                if !self.config.commands.iter().any(|allowed| allowed == name) {
+                    warn!("Command {name:?} on {} isn't allowed", publish.topic);
+                    return;
+                }
+
+                let author = Author::new(PLATFORM, PLATFORM);
+                let content = format!("/{command}");
+                BridgeMessage::new(id, COMMAND_CHANNEL.to_string(), author, content)
+            }
+        };
+
+        // Send message to Azalea
+        if let Err(e) = tx.send_async(PluginEvent::Chat(message)).await {
+            error!("MqttPlugin unable to send message to Azalea: {e}");
+        }
+    }
+}
+
+// The payload of the health topic
+pub(crate) fn health(bot: &str) -> Vec<u8> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    json!({ "bot": bot, "alive": true, "time": time })
+        .to_string()
+        .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Topics;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    // What the broker was sent
+    #[derive(Debug, PartialEq, Eq)]
+    enum Received {
+        // The topic, payload and whether it's retained
+        Connect { will: (String, String, bool) },
+        Publish(String, String, bool),
+        Disconnect,
+    }
+
+    // Accept one client like an MQTT broker, sending it `commands` once it subscribes
+    async fn broker(commands: Vec<&'static str>) -> (u16, flume::Receiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = flume::unbounded();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            while let Some((header, body)) = read_packet(&mut socket).await {
+                let mut body = &body[..];
+                match header >> 4 {
+                    // Connect
+                    1 => {
+                        read_string(&mut body);
+                        let flags = body[1];
+                        body = &body[4..];
+                        read_string(&mut body);
+                        assert!(flags & 0x04 != 0, "no last will");
+                        let topic = read_string(&mut body);
+                        let payload = read_string(&mut body);
+                        let will = (topic, payload, flags & 0x20 != 0);
+                        tx.send(Received::Connect { will }).unwrap();
+                        socket.write_all(&[0x20, 2, 0, 0]).await.unwrap();
+                    }
+                    // Publish, acknowledged if it has a packet id
+                    3 => {
+                        let topic = read_string(&mut body);
+                        if header & 0x06 != 0 {
+                            let puback = [0x40, 2, body[0], body[1]];
+                            socket.write_all(&puback).await.unwrap();
+                            body = &body[2..];
+                        }
+                        let payload = String::from_utf8(body.to_vec()).unwrap();
+                        tx.send(Received::Publish(topic, payload, header & 1 != 0))
+                            .unwrap();
+                    }
+                    // Subscribe, granting QoS 0
+                    8 => {
+                        let packet_id = [body[0], body[1]];
+                        body = &body[2..];
+                        let topic = read_string(&mut body);
+                        let suback = [0x90, 3, packet_id[0], packet_id[1], 0];
+                        socket.write_all(&suback).await.unwrap();
+                        for command in &commands {
+                            socket.write_all(&publish(&topic, command)).await.unwrap();
+                        }
+                    }
+                    // Ping
+                    12 => socket.write_all(&[0xD0, 0]).await.unwrap(),
+                    14 => {
+                        tx.send(Received::Disconnect).unwrap();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        });
+        (port, rx)
+    }
+
+    async fn read_packet(socket: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+        let header = socket.read_u8().await.ok()?;
+
+        // The remaining length takes seven bits of each byte
+        let mut length = 0;
+        for shift in (0..28).step_by(7) {
+            let byte = socket.read_u8().await.ok()?;
+            length |= ((byte & 0x7F) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let mut body = vec![0; length];
+        socket.read_exact(&mut body).await.ok()?;
+        Some((header, body))
+    }
+
+    // Read a string or binary field, which starts with its length
+    fn read_string(body: &mut &[u8]) -> String {
+        let length = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let string = String::from_utf8(body[2..2 + length].to_vec()).unwrap();
+        *body = &body[2 + length..];
+        string
+    }
+
+    // A QoS 0 publish, for payloads shorter than 128 bytes
+    fn publish(topic: &str, payload: &str) -> Vec<u8> {
+        let mut packet = vec![0x30, (2 + topic.len() + payload.len()) as u8];
+        packet.extend_from_slice(&(topic.len() as u16).to_be_bytes());
+        packet.extend_from_slice(topic.as_bytes());
+        packet.extend_from_slice(payload.as_bytes());
+        packet
+    }
+
+    async fn recv<T>(rx: &flume::Receiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv_async())
+            .await
+            .expect("Nothing was received")
+            .unwrap()
+    }
+
+    // Connect to the broker, returning what the plugin sends to Azalea
+    async fn start(config: MqttConfig) -> (MqttPlugin, flume::Receiver<PluginEvent>) {
+        let plugin = connect(config);
+        let (tx, rx) = flume::unbounded();
+        let started = plugin.clone();
+        tokio::spawn(async move { started.start(tx).await });
+        (plugin, rx)
+    }
+
+    fn config(port: u16) -> MqttConfig {
+        MqttConfig::new("127.0.0.1")
+            .port(port)
+            .topics(Topics::new("test"))
+    }
+
+    fn status(payload: &str) -> Received {
+        Received::Publish("test/status".to_string(), payload.to_string(), true)
+    }
+
+    #[tokio::test]
+    async fn publishes_events_to_their_topics() {
+        let (port, received) = broker(Vec::new()).await;
+        let (plugin, _events) = start(config(port)).await;
+
+        // The broker says the bot is offline if it disconnects without saying so
+        let will = ("test/status".to_string(), OFFLINE.to_string(), true);
+        assert_eq!(recv(&received).await, Received::Connect { will });
+        assert_eq!(recv(&received).await, status(ONLINE));
+
+        for (kind, topic) in [
+            (EventKind::Chat, "test/chat"),
+            (EventKind::Join, "test/join"),
+            (EventKind::Leave, "test/leave"),
+            (EventKind::Death, "test/death"),
+        ] {
+            let id = MessageId::new("minecraft", "1");
+            let author = Author::new("069a79f4-44e9-4726-a5be-fca90e38aaf5", "Notch");
+            let mut message = BridgeMessage::new(id, String::new(), author, "hi".into());
+            message.kind = kind;
+            plugin
+                .handle(AzaleaEvent::Chat(message, Vec::new()))
+                .await
+                .unwrap();
+
+            let Received::Publish(published, payload, retain) = recv(&received).await else {
+                panic!("Nothing was published to {topic}");
+            };
+            assert_eq!(published, topic);
+            assert!(!retain);
+            let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+            assert_eq!(payload["author"]["name"], "Notch");
+            assert_eq!(payload["content"], "hi");
+        }
+
+        // Leaving on purpose replaces the retained status
+        plugin.shutdown().await.unwrap();
+        assert_eq!(recv(&received).await, status(OFFLINE));
+        assert_eq!(recv(&received).await, Received::Disconnect);
+    }
+
+    #[tokio::test]
+    async fn sends_chat_and_allowed_commands_to_minecraft() {
+        let (port, _received) = broker(vec![
+            r#"{"type": "chat", "name": "Doorbell", "content": "ding"}"#,
+            r#"{"type": "command", "command": "op Notch"}"#,
+            r#"{"type": "command", "command": "/spawn"}"#,
+            "not json",
+        ])
+        .await;
+        let (_plugin, events) = start(config(port).allow_command("spawn")).await;
+
+        let PluginEvent::Chat(chat) = recv(&events).await else {
+            panic!("Chat wasn't sent to Minecraft");
+        };
+        assert_eq!(chat.channel, CHAT_CHANNEL);
+        assert_eq!(chat.author.name, "Doorbell");
+        assert_eq!(chat.content, "ding");
+
+        // Commands that weren't allowed are dropped
+        let PluginEvent::Chat(command) = recv(&events).await else {
+            panic!("Command wasn't sent to Minecraft");
+        };
+        assert_eq!(command.channel, COMMAND_CHANNEL);
+        assert_eq!(command.content, "/spawn");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(events.is_empty());
+    }
+}
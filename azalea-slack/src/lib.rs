@@ -0,0 +1,80 @@
+use azalea_bridge::{Avatars, ClientSide, PlatformBuilder, Route, Routes};
+
+mod slack;
+
+pub use azalea_bridge::DEFAULT_AVATAR_URL;
+
+// Where the Web API is normally hosted
+pub const DEFAULT_API_URL: &str = "https://slack.com/api";
+
+/// The tokens of a Slack app with Socket Mode turned on.
+#[derive(Debug, Clone)]
+pub struct SlackConfig {
+    // The app-level token, starting with `xapp-`
+    pub app_token: String,
+    // The bot token, starting with `xoxb-`
+    pub bot_token: String,
+    pub api_url: String,
+    // Head renders for players, see `SlackConfig::avatar_url`
+    pub avatar_url: Option<String>,
+}
+
+impl SlackConfig {
+    pub fn new(app_token: &str, bot_token: &str) -> Self {
+        Self {
+            app_token: app_token.to_string(),
+            bot_token: bot_token.to_string(),
+            api_url: DEFAULT_API_URL.to_string(),
+            avatar_url: Some(DEFAULT_AVATAR_URL.to_string()),
+        }
+    }
+
+    // Send requests somewhere else, such as a local stub
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Get players' icons from another head renderer.
+    ///
+    /// The url can use `{uuid}`, `{name}` and `{texture}`, the id of the player's skin.
+    /// Messages from players are sent with the bot's icon if it's `None`.
+    pub fn avatar_url(mut self, avatar_url: Option<&str>) -> Self {
+        self.avatar_url = avatar_url.map(str::to_string);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SlackPlugin {
+    api: slack::Api,
+    channels: slack::Channels,
+    users: slack::Users,
+    avatars: Avatars,
+}
+
+impl SlackPlugin {
+    pub async fn new(
+        config: SlackConfig,
+        channel_id: &str,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<SlackPlugin> {
+        let routes = Routes::new().with(Route::new(channel_id));
+        Self::routed(config, routes, ignore_list).await
+    }
+
+    /// Bridges the channels of each route, which need to have targets.
+    ///
+    /// The bot needs to be in each channel.
+    pub async fn routed(
+        config: SlackConfig,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<SlackPlugin> {
+        let plugin = slack::connect(config, &routes);
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build()
+    }
+}
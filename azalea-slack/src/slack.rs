@@ -0,0 +1,814 @@
+use async_trait::async_trait;
+use azalea_bridge::{
+    parse_link_command, split_mentions, Attachment, AttachmentKind, Author, Avatars, AzaleaEvent,
+    BridgeMessage, BridgePlatform, Capabilities, Identities, LinkCodes, MessageId, MessageMap,
+    PluginEvent, Routes, Segment,
+};
+use flume::Sender;
+use futures::{SinkExt, StreamExt};
+use log::{error, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH},
+};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::{SlackConfig, SlackPlugin};
+
+pub(crate) const PLATFORM: &str = "slack";
+
+// How long to wait before reconnecting, doubling after each failed attempt
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+
+// Bridged channels and the messages relayed to them
+pub(crate) type Channels = Arc<HashMap<String, MessageMap>>;
+
+// Display names of users, by user id
+pub(crate) type Users = Arc<Mutex<HashMap<String, String>>>;
+
+// Set up the bot for the channels of each route
+pub(crate) fn connect(config: SlackConfig, routes: &Routes) -> SlackPlugin {
+    // Messages relayed from other plugins, for each channel
+    let mut channels = HashMap::new();
+    for target in routes.targets() {
+        let relayed = MessageMap::open_for("slack-messages", &target).unwrap_or_else(|e| {
+            error!("Unable to load relayed messages: {e}");
+            MessageMap::default()
+        });
+        channels.insert(target, relayed);
+    }
+
+    SlackPlugin {
+        api: Api {
+            http: reqwest::Client::new(),
+            url: config.api_url,
+            app_token: config.app_token,
+            bot_token: config.bot_token,
+        },
+        channels: Arc::new(channels),
+        users: Users::default(),
+        avatars: Avatars::new(config.avatar_url.as_deref()),
+    }
+}
+
+#[async_trait]
+impl BridgePlatform for SlackPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    // Replies would start threads, so they are quoted instead
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            replies: false,
+            attachments: false,
+            ..Capabilities::all()
+        }
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            match self.connect(&tx, &mut delay).await {
+                Ok(()) => info!("Slack asked to reconnect"),
+                Err(e) => error!("Slack connection failed: {e}"),
+            }
+
+            // Stop once Azalea is gone
+            if tx.is_disconnected() {
+                return Ok(());
+            }
+
+            info!("Reconnecting to Slack in {}s", delay.as_secs());
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        match event {
+            AzaleaEvent::Chat(message, targets) => {
+                let text = to_slack(&message.content);
+                let icon_url = self.avatars.url(&message.author, &message.id.platform);
+
+                for (channel, relayed) in get_targets(&self.channels, &targets) {
+                    // Send the message as the player, like a Discord webhook
+                    let body = PostMessage {
+                        channel,
+                        text: &text,
+                        username: &message.author.name,
+                        icon_url: icon_url.as_deref(),
+                    };
+
+                    // Remember the message so it can be edited and deleted
+                    match self.api.post::<Posted>("chat.postMessage", &body).await {
+                        Ok(sent) => relayed.insert(message.id.clone(), sent.ts),
+                        Err(e) => error!("Unable to send message: {e}"),
+                    }
+                }
+            }
+            AzaleaEvent::Edit(message, _) => {
+                let text = to_slack(&message.content);
+
+                // Edit every copy of the message
+                for (channel, relayed) in self.channels.iter() {
+                    let Some(ts) = relayed.get(&message.id) else {
+                        continue;
+                    };
+                    let body = json!({ "channel": channel, "ts": ts, "text": text });
+                    if let Err(e) = self.api.post::<Value>("chat.update", &body).await {
+                        error!("Unable to edit message: {e}");
+                    }
+                }
+            }
+            AzaleaEvent::Delete(id) => {
+                // Delete every copy of the message
+                for (channel, relayed) in self.channels.iter() {
+                    let Some(ts) = relayed.remove(&id) else {
+                        continue;
+                    };
+                    let body = json!({ "channel": channel, "ts": ts });
+                    if let Err(e) = self.api.post::<Value>("chat.delete", &body).await {
+                        error!("Unable to delete message: {e}");
+                    }
+                }
+            }
+            AzaleaEvent::Notice(notice, channel) => {
+                let body = json!({ "channel": channel, "text": escape(&notice) });
+                self.api.post::<Value>("chat.postMessage", &body).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SlackPlugin {
+    // Receive events over Socket Mode until Slack closes the connection
+    async fn connect(&self, tx: &Sender<PluginEvent>, delay: &mut Duration) -> anyhow::Result<()> {
+        let connection: Connection = self.api.open_connection().await?;
+        let (mut socket, _) = tokio_tungstenite::connect_async(&connection.url).await?;
+
+        while let Some(frame) = socket.next().await.transpose()? {
+            let WsMessage::Text(text) = frame else {
+                continue;
+            };
+            let envelope: Envelope = serde_json::from_str(&text)?;
+
+            // Acknowledge events so they aren't sent again
+            if let Some(envelope_id) = &envelope.envelope_id {
+                let ack = json!({ "envelope_id": envelope_id }).to_string();
+                socket.send(WsMessage::Text(ack)).await?;
+            }
+
+            match envelope.kind.as_str() {
+                "hello" => {
+                    info!("Slack bot is ready!");
+                    *delay = MIN_RECONNECT_DELAY;
+                }
+                // Sent before Slack closes the connection
+                "disconnect" => return Ok(()),
+                "events_api" => {
+                    let Some(event) = envelope.payload.and_then(|payload| payload.event) else {
+                        continue;
+                    };
+                    if let Err(e) = self.handle_slack_event(event, tx).await {
+                        error!("Unable to handle Slack event: {e}");
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_slack_event(
+        &self,
+        event: Event,
+        tx: &Sender<PluginEvent>,
+    ) -> anyhow::Result<()> {
+        if event.kind != "message" {
+            return Ok(());
+        }
+
+        // Only listen on bridged channels
+        let Some(channel) = event.channel.clone() else {
+            return Ok(());
+        };
+        if !self.channels.contains_key(&channel) {
+            return Ok(());
+        }
+
+        let (message, edit) = match event.subtype.as_deref() {
+            None | Some("file_share") | Some("thread_broadcast") => (event, false),
+            Some("message_changed") => match event.message {
+                Some(message) => (*message, true),
+                None => return Ok(()),
+            },
+            Some("message_deleted") => {
+                let Some(ts) = event.deleted_ts else {
+                    return Ok(());
+                };
+
+                // Send deletion to Azalea
+                let id = message_id(&channel, &ts);
+                if let Err(e) = tx.send_async(PluginEvent::Delete(id)).await {
+                    error!("SlackPlugin unable to send deletion to Azalea: {e}");
+                }
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        // Don't send messages from bots, which includes the ones sent by this one
+        let (Some(user), Some(ts), None) = (&message.user, &message.ts, &message.bot_id) else {
+            return Ok(());
+        };
+        let text = message.text.as_deref().unwrap_or_default();
+
+        // Link accounts instead of sending the code
+        if let (Some(code), false) = (parse_link_command(text), edit) {
+            let reply = match LinkCodes::global().redeem(code) {
+                Some(player) => {
+                    Identities::global().link(&player, PLATFORM, user);
+                    info!("Linked {user} to {player}");
+                    format!("Linked to {}", escape(&player))
+                }
+                None => "That code is invalid or has expired".to_string(),
+            };
+
+            let body = json!({ "channel": channel, "text": reply, "thread_ts": ts });
+            self.api.post::<Value>("chat.postMessage", &body).await?;
+            return Ok(());
+        }
+
+        let name = self.user_name(user).await;
+        let mut bridged = BridgeMessage::new(
+            message_id(&channel, ts),
+            channel.clone(),
+            Author::new(user, name),
+            self.plain_text(text).await,
+        );
+        if let Some(seconds) = ts.split('.').next().and_then(|s| s.parse().ok()) {
+            bridged.timestamp = UNIX_EPOCH + Duration::from_secs(seconds);
+        }
+
+        // Files can be opened by anyone in the workspace
+        for file in message.files.unwrap_or_default() {
+            let Some(url) = file.permalink else { continue };
+            bridged.attachments.push(Attachment {
+                kind: AttachmentKind::from_mime(file.mimetype.as_deref()),
+                name: file.name.unwrap_or_default(),
+                url,
+                mime: file.mimetype,
+            });
+        }
+
+        // Send message to Azalea
+        let event = if edit {
+            PluginEvent::Edit(bridged)
+        } else {
+            PluginEvent::Chat(bridged)
+        };
+        if let Err(e) = tx.send_async(event).await {
+            error!("SlackPlugin unable to send message to Azalea: {e}");
+        }
+        Ok(())
+    }
+
+    // Get the display name of a user, looking it up the first time
+    async fn user_name(&self, user: &str) -> String {
+        if let Some(name) = self.users.lock().unwrap().get(user) {
+            return name.clone();
+        }
+
+        let name = match self.api.user_info(user).await {
+            Ok(info) => info.user.name(),
+            Err(e) => {
+                warn!("Unable to get name of Slack user {user}: {e}");
+                return user.to_string();
+            }
+        };
+        self.users
+            .lock()
+            .unwrap()
+            .insert(user.to_string(), name.clone());
+        name
+    }
+
+    // Turn mrkdwn into plain text, using player names for linked users
+    async fn plain_text(&self, text: &str) -> String {
+        let text = strip_formatting(text);
+        let mut result = String::new();
+        let mut rest = text.as_str();
+
+        // Links and mentions look like `<target|label>`
+        while let Some(start) = rest.find('<') {
+            result.push_str(&rest[..start]);
+            let Some(length) = rest[start..].find('>') else {
+                result.push_str(&rest[start..]);
+                rest = "";
+                break;
+            };
+            let inner = &rest[start + 1..start + length];
+            rest = &rest[start + length + 1..];
+
+            let (target, label) = match inner.split_once('|') {
+                Some((target, label)) => (target, Some(label)),
+                None => (inner, None),
+            };
+            match (target.chars().next(), label) {
+                (Some('@'), _) => {
+                    let user = &target[1..];
+                    let name = match Identities::global().player(PLATFORM, user) {
+                        Some(player) => player,
+                        None => self.user_name(user).await,
+                    };
+                    result.push_str(&format!("@{name}"));
+                }
+                (Some('#'), Some(label)) => result.push_str(&format!("#{label}")),
+                (Some('#'), None) => result.push_str(target),
+                // Special mentions like `<!here>` and dates
+                (Some('!'), Some(label)) => result.push_str(label),
+                (Some('!'), None) => result.push_str(&format!("@{}", &target[1..])),
+                (_, Some(label)) if label != target => {
+                    result.push_str(&format!("{} ({target})", strip_formatting(label)))
+                }
+                _ => result.push_str(target),
+            }
+        }
+        result.push_str(rest);
+
+        result
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&")
+    }
+}
+
+// Get the channels to send a message to, which is all of them if none are given
+fn get_targets<'a>(
+    channels: &'a Channels,
+    targets: &[String],
+) -> Vec<(&'a String, &'a MessageMap)> {
+    if targets.is_empty() {
+        return channels.iter().collect();
+    }
+
+    targets
+        .iter()
+        .filter_map(|target| {
+            let channel = channels.get_key_value(target);
+            if channel.is_none() {
+                warn!("Message sent to unknown channel {target}");
+            }
+            channel
+        })
+        .collect()
+}
+
+// Message timestamps are only unique within a channel
+fn message_id(channel: &str, ts: &str) -> MessageId {
+    MessageId::new(PLATFORM, format!("{channel}/{ts}"))
+}
+
+// Remove the `*bold*`, `_italic_` and `~strike~` markers, which Minecraft can't show
+fn strip_formatting(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+
+    // Links and mentions are left alone
+    let mut in_link = vec![false; chars.len()];
+    let mut link = false;
+    for (i, c) in chars.iter().enumerate() {
+        link = match c {
+            '<' => true,
+            '>' => false,
+            _ => link,
+        };
+        in_link[i] = link || *c == '>';
+    }
+
+    // Markers only count around words, so `2*3*4` and `snake_case` stay as they are
+    let is_start = |i: usize| {
+        let before = i.checked_sub(1).map(|i| chars[i]);
+        let after = chars.get(i + 1);
+        !matches!(before, Some(c) if c.is_alphanumeric())
+            && matches!(after, Some(c) if !c.is_whitespace())
+    };
+    let is_end = |i: usize| {
+        let after = chars.get(i + 1);
+        !chars[i - 1].is_whitespace() && !matches!(after, Some(c) if c.is_alphanumeric())
+    };
+
+    let mut removed = vec![false; chars.len()];
+    for start in 0..chars.len() {
+        let marker = chars[start];
+        if !matches!(marker, '*' | '_' | '~')
+            || in_link[start]
+            || removed[start]
+            || !is_start(start)
+        {
+            continue;
+        }
+
+        // Formatting doesn't continue onto the next line
+        let end = (start + 2..chars.len())
+            .take_while(|&i| chars[i] != '\n')
+            .find(|&i| chars[i] == marker && !in_link[i] && is_end(i));
+        if let Some(end) = end {
+            removed[start] = true;
+            removed[end] = true;
+        }
+    }
+
+    chars
+        .into_iter()
+        .zip(removed)
+        .filter_map(|(c, removed)| (!removed).then_some(c))
+        .collect()
+}
+
+// Replace mentions of linked players with Slack mentions
+fn to_slack(message: &str) -> String {
+    let mut result = String::new();
+    for segment in split_mentions(message) {
+        match segment {
+            Segment::Text(text) => result.push_str(&escape(text)),
+            Segment::Mention(name) => match Identities::global().user_id(PLATFORM, name) {
+                Some(id) => result.push_str(&format!("<@{id}>")),
+                None => result.push_str(&escape(&format!("@{name}"))),
+            },
+        }
+    }
+    result
+}
+
+// Only these characters have to be escaped, the rest of mrkdwn is left alone
+fn escape(message: &str) -> String {
+    message
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// A small client for the Web API
+#[derive(Debug, Clone)]
+pub(crate) struct Api {
+    http: reqwest::Client,
+    url: String,
+    app_token: String,
+    bot_token: String,
+}
+
+impl Api {
+    // Call a method with a JSON body as the bot
+    async fn post<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: &impl Serialize,
+    ) -> anyhow::Result<T> {
+        let request = self
+            .http
+            .post(format!("{}/{method}", self.url))
+            .bearer_auth(&self.bot_token)
+            .json(body);
+        send(method, request).await
+    }
+
+    // Get a WebSocket URL for Socket Mode, which uses the app token
+    async fn open_connection(&self) -> anyhow::Result<Connection> {
+        let request = self
+            .http
+            .post(format!("{}/apps.connections.open", self.url))
+            .bearer_auth(&self.app_token);
+        send("apps.connections.open", request).await
+    }
+
+    // Reading methods don't accept JSON
+    async fn user_info(&self, user: &str) -> anyhow::Result<UserInfo> {
+        let request = self
+            .http
+            .post(format!("{}/users.info", self.url))
+            .bearer_auth(&self.bot_token)
+            .form(&[("user", user)]);
+        send("users.info", request).await
+    }
+}
+
+async fn send<T: DeserializeOwned>(
+    method: &str,
+    request: reqwest::RequestBuilder,
+) -> anyhow::Result<T> {
+    let response: Value = request.send().await?.error_for_status()?.json().await?;
+
+    // Errors are returned with a successful status
+    if response["ok"] != Value::Bool(true) {
+        let error = response["error"].as_str().unwrap_or("unknown error");
+        return Err(anyhow::Error::msg(format!("{method} failed: {error}")));
+    }
+    Ok(serde_json::from_value(response)?)
+}
+
+#[derive(Debug, Serialize)]
+struct PostMessage<'a> {
+    channel: &'a str,
+    text: &'a str,
+    // Needs the chat:write.customize scope
+    username: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon_url: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Connection {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Posted {
+    ts: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    kind: String,
+    envelope_id: Option<String>,
+    payload: Option<Payload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Payload {
+    event: Option<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(rename = "type", default)]
+    kind: String,
+    subtype: Option<String>,
+    channel: Option<String>,
+    user: Option<String>,
+    bot_id: Option<String>,
+    text: Option<String>,
+    ts: Option<String>,
+    // The new message of an edit
+    message: Option<Box<Event>>,
+    deleted_ts: Option<String>,
+    files: Option<Vec<File>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct File {
+    name: Option<String>,
+    mimetype: Option<String>,
+    permalink: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfo {
+    user: User,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    name: String,
+    profile: Option<Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    display_name: Option<String>,
+    real_name: Option<String>,
+}
+
+impl User {
+    // Prefer the name people choose to show
+    fn name(&self) -> String {
+        let profile = self.profile.as_ref();
+        [
+            profile.and_then(|profile| profile.display_name.as_ref()),
+            profile.and_then(|profile| profile.real_name.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|name| !name.is_empty())
+        .unwrap_or(&self.name)
+        .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::{Route, MINECRAFT_PLATFORM};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    // The method called and the JSON or form body
+    type Request = (String, String);
+
+    const UUID: &str = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+
+    // Answer Web API calls like Slack, sending each request on to the test
+    async fn stub(respond: fn(&str) -> Value) -> (String, flume::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = flume::unbounded();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let request = read_request(&mut socket).await;
+                let body = respond(&request.0).to_string();
+                let _ = tx.send(request);
+
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+                socket.write_all(head.as_bytes()).await.unwrap();
+                socket.write_all(body.as_bytes()).await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> Request {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "request ended early");
+            data.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&data);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse().ok())
+                .unwrap_or(0);
+            if body.len() >= length {
+                let path = head.split(' ').nth(1).unwrap_or_default();
+                return (path.trim_start_matches('/').to_string(), body.to_string());
+            }
+        }
+    }
+
+    fn respond(method: &str) -> Value {
+        match method {
+            "chat.postMessage" => json!({ "ok": true, "ts": "1700000000.000100" }),
+            "users.info" => json!({
+                "ok": true,
+                "user": { "name": "alex", "profile": { "display_name": "Alex" } },
+            }),
+            _ => json!({ "ok": true }),
+        }
+    }
+
+    // Each test uses its own channel, since relayed messages are stored globally
+    fn plugin(config: SlackConfig, url: &str, channel: &str) -> SlackPlugin {
+        connect(
+            config.api_url(url),
+            &Routes::new().with(Route::new(channel)),
+        )
+    }
+
+    fn message(platform: &str, author: Author, content: &str) -> BridgeMessage {
+        let id = MessageId::new(platform, "1");
+        BridgeMessage::new(id, String::new(), author, content.to_string())
+    }
+
+    async fn next(requests: &flume::Receiver<Request>) -> (String, Value) {
+        let (method, body) = requests.recv_async().await.unwrap();
+        (method, serde_json::from_str(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn sends_players_with_their_heads() {
+        let (url, requests) = stub(respond).await;
+        let plugin = plugin(SlackConfig::new("xapp", "xoxb"), &url, "C1");
+
+        let mut message = message(MINECRAFT_PLATFORM, Author::new(UUID, "Notch"), "a < b");
+        let event = AzaleaEvent::Chat(message.clone(), Vec::new());
+        plugin.handle(event).await.unwrap();
+
+        let (method, body) = next(&requests).await;
+        assert_eq!(method, "chat.postMessage");
+        assert_eq!(body["channel"], "C1");
+        assert_eq!(body["text"], "a &lt; b");
+        assert_eq!(body["username"], "Notch");
+        let icon_url = format!("https://crafatar.com/avatars/{UUID}?size=128&overlay");
+        assert_eq!(body["icon_url"], icon_url);
+
+        // Edits go to the message that was sent
+        message.content = "a > b".to_string();
+        plugin
+            .handle(AzaleaEvent::Edit(message, Vec::new()))
+            .await
+            .unwrap();
+        let (method, body) = next(&requests).await;
+        assert_eq!(method, "chat.update");
+        assert_eq!(body["ts"], "1700000000.000100");
+        assert_eq!(body["text"], "a &gt; b");
+    }
+
+    #[tokio::test]
+    async fn encodes_names_in_icons() {
+        let (url, requests) = stub(respond).await;
+        let config = SlackConfig::new("xapp", "xoxb").avatar_url(Some("https://heads/{name}"));
+        let plugin = plugin(config, &url, "C2");
+
+        let author = Author::new(UUID, "Alex [Bot]/x");
+        let message = message(MINECRAFT_PLATFORM, author, "hi");
+        let event = AzaleaEvent::Chat(message, Vec::new());
+        plugin.handle(event).await.unwrap();
+
+        let (_, body) = next(&requests).await;
+        assert_eq!(body["icon_url"], "https://heads/Alex%20%5BBot%5D%2Fx");
+    }
+
+    #[tokio::test]
+    async fn keeps_avatars_from_other_platforms() {
+        let (url, requests) = stub(respond).await;
+        let config = SlackConfig::new("xapp", "xoxb").avatar_url(None);
+        let plugin = plugin(config, &url, "C3");
+
+        let mut author = Author::new("1", "Alex");
+        author.avatar = Some("https://cdn.example.com/alex.png".to_string());
+        let event = AzaleaEvent::Chat(message("discord", author, "hi"), Vec::new());
+        plugin.handle(event).await.unwrap();
+        let (_, body) = next(&requests).await;
+        assert_eq!(body["icon_url"], "https://cdn.example.com/alex.png");
+
+        // Without a head renderer players get the bot's icon
+        let event = AzaleaEvent::Chat(
+            message(MINECRAFT_PLATFORM, Author::new(UUID, "Notch"), "hi"),
+            Vec::new(),
+        );
+        plugin.handle(event).await.unwrap();
+        let (_, body) = next(&requests).await;
+        assert!(body.get("icon_url").is_none());
+    }
+
+    #[tokio::test]
+    async fn returns_api_errors() {
+        let (url, _requests) = stub(|_| json!({ "ok": false, "error": "channel_not_found" })).await;
+        let plugin = plugin(SlackConfig::new("xapp", "xoxb"), &url, "C4");
+
+        let event = AzaleaEvent::Notice("hi".to_string(), "C4".to_string());
+        let error = plugin.handle(event).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "chat.postMessage failed: channel_not_found"
+        );
+    }
+
+    #[tokio::test]
+    async fn plain_text_translates_mrkdwn() {
+        let (url, requests) = stub(respond).await;
+        let plugin = plugin(SlackConfig::new("xapp", "xoxb"), &url, "C5");
+
+        let text = "*hey* <@U1>, _see_ ~this~ <https://example.com|*site*> &lt;3";
+        assert_eq!(
+            plugin.plain_text(text).await,
+            "hey @Alex, see this site (https://example.com) <3"
+        );
+
+        // The name is looked up once
+        let (method, _) = requests.recv_async().await.unwrap();
+        assert_eq!(method, "users.info");
+        assert_eq!(plugin.plain_text("<@U1>").await, "@Alex");
+        assert!(requests.is_empty());
+    }
+
+    #[test]
+    fn strip_formatting_around_words() {
+        assert_eq!(strip_formatting("*bold* and _it_"), "bold and it");
+        assert_eq!(strip_formatting("*_both_*"), "both");
+        assert_eq!(strip_formatting("~a few words~"), "a few words");
+    }
+
+    #[test]
+    fn strip_formatting_leaves_other_markers() {
+        assert_eq!(
+            strip_formatting("2*3*4 snake_case_name"),
+            "2*3*4 snake_case_name"
+        );
+        assert_eq!(strip_formatting("a * b * c"), "a * b * c");
+        assert_eq!(strip_formatting("*unclosed"), "*unclosed");
+        assert_eq!(strip_formatting("*two\nlines*"), "*two\nlines*");
+        assert_eq!(
+            strip_formatting("<https://a.com/_x_|_y_>"),
+            "<https://a.com/_x_|_y_>"
+        );
+    }
+}
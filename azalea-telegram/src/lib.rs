@@ -0,0 +1,58 @@
+use azalea_bridge::{ClientSide, PlatformBuilder, Route, Routes};
+
+mod telegram;
+
+// Where the Bot API is normally hosted
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// How to connect to the Bot API.
+#[derive(Debug, Clone)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub api_url: String,
+}
+
+impl TelegramConfig {
+    pub fn new(bot_token: &str) -> Self {
+        Self {
+            bot_token: bot_token.to_string(),
+            api_url: DEFAULT_API_URL.to_string(),
+        }
+    }
+
+    // Use a self-hosted Bot API server
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TelegramPlugin {
+    api: telegram::Api,
+    chats: telegram::Chats,
+}
+
+impl TelegramPlugin {
+    pub async fn new(
+        config: TelegramConfig,
+        chat_id: i64,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<TelegramPlugin> {
+        let routes = Routes::new().with(Route::new(chat_id));
+        Self::routed(config, routes, ignore_list).await
+    }
+
+    /// Bridges the group chats of each route, which need to have targets.
+    pub async fn routed(
+        config: TelegramConfig,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<TelegramPlugin> {
+        let plugin = telegram::connect(&config, &routes);
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build()
+    }
+}
@@ -0,0 +1,786 @@
+use async_trait::async_trait;
+use azalea_bridge::{
+    parse_link_command, split_mentions, Attachment, AttachmentKind, Author, AzaleaEvent,
+    BridgeMessage, BridgePlatform, Capabilities, Identities, LinkCodes, MessageId, MessageMap,
+    PluginEvent, Reply, Routes, Segment,
+};
+use flume::Sender;
+use log::{error, info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{TelegramConfig, TelegramPlugin};
+
+pub(crate) const PLATFORM: &str = "telegram";
+
+// How long each request for updates waits for something to happen
+const POLL_TIMEOUT: u64 = 30;
+
+// How long to wait after failing to get updates
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// Bridged chats and the messages relayed to them
+pub(crate) type Chats = Arc<HashMap<i64, MessageMap>>;
+
+// Set up the bot for the chats of each route
+pub(crate) fn connect(config: &TelegramConfig, routes: &Routes) -> TelegramPlugin {
+    // Messages relayed from other plugins, for each chat
+    let mut chats = HashMap::new();
+    for target in routes.targets() {
+        let Ok(chat_id) = target.parse::<i64>() else {
+            error!("Invalid Telegram chat id: {target}");
+            continue;
+        };
+        let relayed = MessageMap::open_for("telegram-messages", &target).unwrap_or_else(|e| {
+            error!("Unable to load relayed messages: {e}");
+            MessageMap::default()
+        });
+        chats.insert(chat_id, relayed);
+    }
+
+    TelegramPlugin {
+        api: Api {
+            http: reqwest::Client::new(),
+            url: format!("{}/bot{}", config.api_url, config.bot_token),
+        },
+        chats: Arc::new(chats),
+    }
+}
+
+#[async_trait]
+impl BridgePlatform for TelegramPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    // Files are sent as links
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            attachments: false,
+            avatars: false,
+            ..Capabilities::all()
+        }
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        info!("Telegram bot is ready!");
+
+        // Long poll for new messages
+        let mut offset = 0;
+        loop {
+            let updates = match self.api.get_updates(offset).await {
+                Ok(updates) => updates,
+                Err(e) => {
+                    warn!("Error receiving updates: {e}");
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                offset = offset.max(update.update_id + 1);
+
+                let (message, edit) = match (update.message, update.edited_message) {
+                    (Some(message), _) => (message, false),
+                    (_, Some(message)) => (message, true),
+                    _ => continue,
+                };
+                if let Err(e) = self.handle_telegram_message(message, edit, &tx).await {
+                    error!("Unable to handle Telegram message: {e}");
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        match event {
+            AzaleaEvent::Chat(message, targets) => {
+                for (chat_id, relayed) in get_targets(&self.chats, &targets) {
+                    let mut text = format_message(&message);
+
+                    // Reply to the message if it was relayed here, otherwise quote it
+                    let mut reply_to = None;
+                    if let Some(reply) = &message.reply {
+                        match get_message_id(chat_id, relayed, &reply.id) {
+                            Some(id) => reply_to = Some(id),
+                            None => text = format!("{} {text}", escape_html(&reply.to_string())),
+                        }
+                    }
+
+                    // Remember the message so it can be edited and deleted
+                    match self.api.send_message(chat_id, &text, reply_to).await {
+                        Ok(sent) => relayed.insert(message.id.clone(), sent.message_id),
+                        Err(e) => error!("Unable to send message: {e}"),
+                    }
+                }
+            }
+            AzaleaEvent::Edit(message, _) => {
+                let text = format_message(&message);
+
+                // Edit every copy of the message
+                for (chat_id, relayed) in self.chats.iter() {
+                    let Some(local) = get_message_id(*chat_id, relayed, &message.id) else {
+                        continue;
+                    };
+                    if let Err(e) = self.api.edit_message_text(*chat_id, local, &text).await {
+                        error!("Unable to edit message: {e}");
+                    }
+                }
+            }
+            AzaleaEvent::Delete(id) => {
+                // Delete every copy of the message
+                for (chat_id, relayed) in self.chats.iter() {
+                    let Some(local) = get_message_id(*chat_id, relayed, &id) else {
+                        continue;
+                    };
+                    relayed.remove(&id);
+
+                    if let Err(e) = self.api.delete_message(*chat_id, local).await {
+                        error!("Unable to delete message: {e}");
+                    }
+                }
+            }
+            AzaleaEvent::Notice(notice, chat) => {
+                let Ok(chat_id) = chat.parse::<i64>() else {
+                    warn!("Notice for unknown chat {chat}: {notice}");
+                    return Ok(());
+                };
+                self.api
+                    .send_message(chat_id, &escape_html(&notice), None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TelegramPlugin {
+    async fn handle_telegram_message(
+        &self,
+        message: Message,
+        edit: bool,
+        tx: &Sender<PluginEvent>,
+    ) -> anyhow::Result<()> {
+        // Only listen on bridged chats
+        let Some(relayed) = self.chats.get(&message.chat.id) else {
+            return Ok(());
+        };
+
+        // Don't send messages from bots, which includes this one
+        let Some(from) = &message.from else {
+            return Ok(());
+        };
+        if from.is_bot {
+            return Ok(());
+        }
+
+        let text = message
+            .text
+            .as_deref()
+            .or(message.caption.as_deref())
+            .unwrap_or_default();
+
+        // Link accounts instead of sending the code
+        if let (Some(code), false) = (parse_link_command(text), edit) {
+            let reply = match LinkCodes::global().redeem(code) {
+                Some(player) => {
+                    Identities::global().link(&player, PLATFORM, from.id);
+                    info!("Linked {} to {player}", from.name());
+                    format!("Linked to {}", escape_html(&player))
+                }
+                None => "That code is invalid or has expired".to_string(),
+            };
+
+            self.api
+                .send_message(message.chat.id, &reply, Some(message.message_id))
+                .await?;
+            return Ok(());
+        }
+
+        // Replies to relayed messages refer to the original message
+        let reply = message.reply_to_message.as_ref().map(|replied| {
+            let id = relayed
+                .origin(&replied.message_id.to_string())
+                .unwrap_or_else(|| message_id(replied));
+            let name = replied.from.as_ref().map(User::name).unwrap_or_default();
+            let content = replied.text.as_deref().or(replied.caption.as_deref());
+            Reply::new(id, name, content.unwrap_or_default())
+        });
+
+        let mut bridged = BridgeMessage::new(
+            message_id(&message),
+            message.chat.id.to_string(),
+            Author::new(from.id, from.name()),
+            text.to_string(),
+        );
+        bridged.reply = reply;
+        bridged.timestamp = UNIX_EPOCH + Duration::from_secs(message.date);
+
+        // Photos can only be linked to in public groups, without giving away the bot token
+        if message.photo.is_some() {
+            match &message.chat.username {
+                Some(username) => bridged.attachments.push(Attachment {
+                    kind: AttachmentKind::Image,
+                    name: String::new(),
+                    url: format!("https://t.me/{username}/{}", message.message_id),
+                    mime: None,
+                }),
+                None => bridged.content = format!("[image] {}", bridged.content),
+            }
+        }
+
+        // Send message to Azalea
+        let event = if edit {
+            PluginEvent::Edit(bridged)
+        } else {
+            PluginEvent::Chat(bridged)
+        };
+        if let Err(e) = tx.send_async(event).await {
+            error!("TelegramPlugin unable to send message to Azalea: {e}");
+        }
+        Ok(())
+    }
+}
+
+// Get the chats to send a message to, which is all of them if none are given
+fn get_targets<'a>(chats: &'a Chats, targets: &[String]) -> Vec<(i64, &'a MessageMap)> {
+    if targets.is_empty() {
+        return chats.iter().map(|(id, relayed)| (*id, relayed)).collect();
+    }
+
+    targets
+        .iter()
+        .filter_map(|target| {
+            let chat = target
+                .parse::<i64>()
+                .ok()
+                .and_then(|id| chats.get_key_value(&id));
+            if chat.is_none() {
+                warn!("Message sent to unknown chat {target}");
+            }
+            chat.map(|(id, relayed)| (*id, relayed))
+        })
+        .collect()
+}
+
+// Message ids are only unique within a chat
+fn message_id(message: &Message) -> MessageId {
+    MessageId::new(
+        PLATFORM,
+        format!("{}/{}", message.chat.id, message.message_id),
+    )
+}
+
+// Find the Telegram message in a chat a message was sent as,
+// which may already be from Telegram if it's from the same chat
+fn get_message_id(chat_id: i64, relayed: &MessageMap, origin: &MessageId) -> Option<i64> {
+    let local = match origin.id.split_once('/') {
+        Some((chat, local)) if origin.platform == PLATFORM && chat == chat_id.to_string() => {
+            local.to_string()
+        }
+        _ => relayed.get(origin)?,
+    };
+    match local.parse() {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!("Invalid Telegram message id {local}: {e}");
+            None
+        }
+    }
+}
+
+// Show the sender as a bold name before the message
+fn format_message(message: &BridgeMessage) -> String {
+    format!(
+        "<b>{}</b>: {}",
+        escape_html(&message.author.name),
+        to_telegram(&message.content)
+    )
+}
+
+// Replace mentions of linked players with Telegram mentions
+fn to_telegram(message: &str) -> String {
+    let mut result = String::new();
+    for segment in split_mentions(message) {
+        match segment {
+            Segment::Text(text) => result.push_str(&escape_html(text)),
+            Segment::Mention(name) => match Identities::global().user_id(PLATFORM, name) {
+                Some(id) => result.push_str(&format!(
+                    "<a href=\"tg://user?id={id}\">@{}</a>",
+                    escape_html(name)
+                )),
+                None => result.push_str(&escape_html(&format!("@{name}"))),
+            },
+        }
+    }
+    result
+}
+
+fn escape_html(message: &str) -> String {
+    message
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A small client for the Bot API
+#[derive(Debug, Clone)]
+pub(crate) struct Api {
+    http: reqwest::Client,
+    // Includes the bot token
+    url: String,
+}
+
+impl Api {
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: &impl Serialize,
+    ) -> anyhow::Result<T> {
+        let response: ApiResponse<T> = self
+            .http
+            .post(format!("{}/{method}", self.url))
+            .json(body)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match response.result {
+            Some(result) if response.ok => Ok(result),
+            _ => Err(anyhow::Error::msg(format!(
+                "{method} failed: {}",
+                response.description.unwrap_or_default()
+            ))),
+        }
+    }
+
+    async fn get_updates(&self, offset: i64) -> anyhow::Result<Vec<Update>> {
+        let body = GetUpdates {
+            offset,
+            timeout: POLL_TIMEOUT,
+            allowed_updates: &["message", "edited_message"],
+        };
+        self.call("getUpdates", &body).await
+    }
+
+    async fn send_message(
+        &self,
+        chat_id: i64,
+        text: &str,
+        reply_to_message_id: Option<i64>,
+    ) -> anyhow::Result<Message> {
+        let body = SendMessage {
+            chat_id,
+            text,
+            parse_mode: "HTML",
+            reply_to_message_id,
+        };
+        self.call("sendMessage", &body).await
+    }
+
+    async fn edit_message_text(
+        &self,
+        chat_id: i64,
+        message_id: i64,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        let body = EditMessageText {
+            chat_id,
+            message_id,
+            text,
+            parse_mode: "HTML",
+        };
+        self.call::<serde::de::IgnoredAny>("editMessageText", &body)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_message(&self, chat_id: i64, message_id: i64) -> anyhow::Result<()> {
+        let body = DeleteMessage {
+            chat_id,
+            message_id,
+        };
+        self.call::<bool>("deleteMessage", &body).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct GetUpdates {
+    offset: i64,
+    timeout: u64,
+    allowed_updates: &'static [&'static str],
+}
+
+#[derive(Debug, Serialize)]
+struct SendMessage<'a> {
+    chat_id: i64,
+    text: &'a str,
+    parse_mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to_message_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct EditMessageText<'a> {
+    chat_id: i64,
+    message_id: i64,
+    text: &'a str,
+    parse_mode: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct DeleteMessage {
+    chat_id: i64,
+    message_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+    edited_message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    message_id: i64,
+    from: Option<User>,
+    chat: Chat,
+    date: u64,
+    text: Option<String>,
+    caption: Option<String>,
+    photo: Option<Vec<serde::de::IgnoredAny>>,
+    reply_to_message: Option<Box<Message>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: i64,
+    is_bot: bool,
+    first_name: String,
+    last_name: Option<String>,
+}
+
+impl User {
+    fn name(&self) -> String {
+        match &self.last_name {
+            Some(last_name) => format!("{} {last_name}", self.first_name),
+            None => self.first_name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+    // Only public groups have usernames
+    username: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::Route;
+    use serde_json::{json, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    // The method called and the JSON body
+    type Request = (String, Value);
+
+    // Answers Bot API calls, or never answers if it returns None, like a long poll with no updates
+    type Respond = fn(&str, &Value) -> Option<Value>;
+
+    // Answer requests like the Bot API, sending each one on to the test
+    async fn stub(respond: Respond) -> (String, flume::Receiver<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = flume::unbounded();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let (method, body) = read_request(&mut socket).await;
+                    let response = respond(&method, &body);
+                    let _ = tx.send((method, body));
+                    let Some(response) = response else {
+                        return std::future::pending().await;
+                    };
+
+                    let response = json!({ "ok": true, "result": response }).to_string();
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        response.len()
+                    );
+                    socket.write_all(head.as_bytes()).await.unwrap();
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        (url, rx)
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> Request {
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            assert!(read > 0, "request ended early");
+            data.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&data);
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                continue;
+            };
+            let length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse().ok())
+                .unwrap_or(0);
+            if body.len() >= length {
+                // Paths look like `/bot<token>/<method>`
+                let path = head.split(' ').nth(1).unwrap_or_default();
+                let method = path.rsplit('/').next().unwrap_or_default().to_string();
+                return (method, serde_json::from_str(body).unwrap_or_default());
+            }
+        }
+    }
+
+    fn respond(method: &str, _body: &Value) -> Option<Value> {
+        match method {
+            "sendMessage" => Some(json!({ "message_id": 42, "chat": { "id": 0 }, "date": 0 })),
+            _ => Some(json!(true)),
+        }
+    }
+
+    // Each test uses its own chat, since relayed messages are stored globally
+    fn plugin(url: &str, chat_id: i64) -> TelegramPlugin {
+        let config = TelegramConfig::new("123:abc").api_url(url);
+        connect(&config, &Routes::new().with(Route::new(chat_id)))
+    }
+
+    fn message(platform: &str, id: &str, name: &str, content: &str) -> BridgeMessage {
+        let id = MessageId::new(platform, id);
+        BridgeMessage::new(
+            id,
+            String::new(),
+            Author::new(name, name),
+            content.to_string(),
+        )
+    }
+
+    async fn recv<T>(rx: &flume::Receiver<T>) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv_async())
+            .await
+            .expect("Nothing was received")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn polls_for_updates() {
+        let (url, requests) = stub(|method, body| match (method, body["offset"].as_i64()) {
+            ("getUpdates", Some(0)) => Some(json!([
+                {
+                    "update_id": 10,
+                    "message": {
+                        "message_id": 5,
+                        "from": { "id": 7, "is_bot": false, "first_name": "Alex", "last_name": "Doe" },
+                        "chat": { "id": -101 },
+                        "date": 1700000000,
+                        "text": "hello",
+                    },
+                },
+                {
+                    "update_id": 12,
+                    "edited_message": {
+                        "message_id": 5,
+                        "from": { "id": 7, "is_bot": false, "first_name": "Alex" },
+                        "chat": { "id": -101 },
+                        "date": 1700000000,
+                        "text": "hello!",
+                    },
+                },
+                {
+                    "update_id": 11,
+                    "message": {
+                        "message_id": 6,
+                        "from": { "id": 8, "is_bot": true, "first_name": "Bot" },
+                        "chat": { "id": -101 },
+                        "date": 1700000000,
+                        "text": "ignored",
+                    },
+                },
+            ])),
+            // Wait for updates that never come
+            _ => None,
+        })
+        .await;
+        let plugin = plugin(&url, -101);
+        let (tx, rx) = flume::unbounded();
+        tokio::spawn(async move { plugin.start(tx).await });
+
+        let (method, body) = recv(&requests).await;
+        assert_eq!(method, "getUpdates");
+        assert_eq!(body["offset"], 0);
+        assert_eq!(body["timeout"], POLL_TIMEOUT);
+
+        let PluginEvent::Chat(message) = recv(&rx).await else {
+            panic!("Expected a chat message");
+        };
+        assert_eq!(message.id, MessageId::new(PLATFORM, "-101/5"));
+        assert_eq!(message.channel, "-101");
+        assert_eq!(message.author.name, "Alex Doe");
+        assert_eq!(message.content, "hello");
+        assert_eq!(
+            message.timestamp,
+            UNIX_EPOCH + Duration::from_secs(1700000000)
+        );
+
+        let PluginEvent::Edit(edit) = recv(&rx).await else {
+            panic!("Expected an edit");
+        };
+        assert_eq!(edit.id, message.id);
+        assert_eq!(edit.content, "hello!");
+
+        // Messages from bots aren't sent, and updates are only received once
+        let (method, body) = recv(&requests).await;
+        assert_eq!(method, "getUpdates");
+        assert_eq!(body["offset"], 13);
+        assert!(rx.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sends_replies() {
+        let (url, requests) = stub(respond).await;
+        let plugin = plugin(&url, -102);
+
+        let first = message("minecraft", "1", "Notch", "a < b");
+        let event = AzaleaEvent::Chat(first.clone(), Vec::new());
+        plugin.handle(event).await.unwrap();
+        let (method, body) = recv(&requests).await;
+        assert_eq!(method, "sendMessage");
+        assert_eq!(
+            body,
+            json!({ "chat_id": -102, "text": "<b>Notch</b>: a &lt; b", "parse_mode": "HTML" })
+        );
+
+        // Replies to relayed messages are Telegram replies
+        let mut reply = message("discord", "2", "Alex", "yes");
+        reply.reply = Some(Reply::new(first.id.clone(), "Notch".to_string(), "a < b"));
+        plugin
+            .handle(AzaleaEvent::Chat(reply.clone(), Vec::new()))
+            .await
+            .unwrap();
+        let (_, body) = recv(&requests).await;
+        assert_eq!(body["reply_to_message_id"], 42);
+        assert_eq!(body["text"], "<b>Alex</b>: yes");
+
+        // Other replies are quoted
+        reply.reply = Some(Reply::new(
+            MessageId::new("discord", "3"),
+            "Steve".to_string(),
+            "hi",
+        ));
+        plugin
+            .handle(AzaleaEvent::Chat(reply, vec!["-102".to_string()]))
+            .await
+            .unwrap();
+        let (_, body) = recv(&requests).await;
+        assert!(body.get("reply_to_message_id").is_none());
+        assert_eq!(
+            body["text"],
+            "[reply to Steve: &quot;hi&quot;] <b>Alex</b>: yes"
+        );
+    }
+
+    #[tokio::test]
+    async fn replies_to_telegram_messages_in_their_own_chat() {
+        let (url, requests) = stub(respond).await;
+        let config = TelegramConfig::new("123:abc").api_url(&url);
+        let plugin = connect(&config, &Routes::new().with(Route::new(-104).target(-105)));
+
+        let original = message(PLATFORM, "-104/7", "Alex", "anyone online?");
+        let mut reply = message("minecraft", "1", "Notch", "yes");
+        reply.reply = Some(Reply::new(
+            original.id.clone(),
+            "Alex".to_string(),
+            "anyone online?",
+        ));
+        let reply_in = |chat: &str| AzaleaEvent::Chat(reply.clone(), vec![chat.to_string()]);
+
+        plugin.handle(reply_in("-104")).await.unwrap();
+        let (_, body) = recv(&requests).await;
+        assert_eq!(body["reply_to_message_id"], 7);
+
+        // The message isn't in the other chat until it's relayed there
+        plugin.handle(reply_in("-105")).await.unwrap();
+        let (_, body) = recv(&requests).await;
+        assert!(body.get("reply_to_message_id").is_none());
+        assert_eq!(
+            body["text"],
+            "[reply to Alex: &quot;anyone online?&quot;] <b>Notch</b>: yes"
+        );
+
+        let relay = AzaleaEvent::Chat(original, vec!["-105".to_string()]);
+        plugin.handle(relay).await.unwrap();
+        recv(&requests).await;
+        plugin.handle(reply_in("-105")).await.unwrap();
+        let (_, body) = recv(&requests).await;
+        assert_eq!(body["chat_id"], -105);
+        assert_eq!(body["reply_to_message_id"], 42);
+    }
+
+    #[tokio::test]
+    async fn edits_relayed_messages() {
+        let (url, requests) = stub(respond).await;
+        let plugin = plugin(&url, -103);
+
+        let mut sent = message("minecraft", "1", "Notch", "hi");
+        let event = AzaleaEvent::Chat(sent.clone(), Vec::new());
+        plugin.handle(event).await.unwrap();
+        recv(&requests).await;
+
+        sent.content = "hi & bye".to_string();
+        plugin
+            .handle(AzaleaEvent::Edit(sent, Vec::new()))
+            .await
+            .unwrap();
+        let (method, body) = recv(&requests).await;
+        assert_eq!(method, "editMessageText");
+        assert_eq!(
+            body,
+            json!({
+                "chat_id": -103,
+                "message_id": 42,
+                "text": "<b>Notch</b>: hi &amp; bye",
+                "parse_mode": "HTML",
+            })
+        );
+
+        // Messages that weren't relayed here aren't edited
+        let other = message("minecraft", "2", "Notch", "hi");
+        plugin
+            .handle(AzaleaEvent::Edit(other, Vec::new()))
+            .await
+            .unwrap();
+        assert!(requests.is_empty());
+    }
+}
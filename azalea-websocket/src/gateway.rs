@@ -0,0 +1,505 @@
+use async_trait::async_trait;
+use azalea_bridge::{
+    Author, AzaleaEvent, BridgeMessage, BridgePlatform, Capabilities, MessageId, PluginEvent,
+};
+use flume::Sender;
+use futures::{SinkExt, StreamExt};
+use log::{error, info, warn};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{tungstenite::Message as WsMessage, WebSocketStream};
+use uuid::Uuid;
+
+use crate::{
+    protocol::{ClientMessage, ServerMessage},
+    RateLimit, WebSocketPlugin,
+};
+
+pub(crate) const PLATFORM: &str = "websocket";
+
+// How long clients have to authenticate after connecting
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How many messages to remember the channels of, so edits and deletions go to the same clients
+const SENT_CAPACITY: usize = 1024;
+
+// Connected clients, by connection id
+pub(crate) type Clients = Arc<Mutex<HashMap<u64, Connected>>>;
+
+// The channels recent messages were sent to, oldest first
+pub(crate) type Sent = Arc<Mutex<VecDeque<(MessageId, Vec<String>)>>>;
+
+#[derive(Debug)]
+pub(crate) struct Connected {
+    channel: String,
+    // Serialized messages to send to the client
+    tx: Sender<String>,
+}
+
+#[async_trait]
+impl BridgePlatform for WebSocketPlugin {
+    fn name(&self) -> &'static str {
+        PLATFORM
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
+    async fn start(&self, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(self.config.address).await?;
+        info!("WebSocket gateway listening on {}", self.config.address);
+
+        loop {
+            let (stream, address) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Unable to accept connection: {e}");
+                    continue;
+                }
+            };
+
+            // Serve each client in its own task
+            let plugin = self.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = plugin.serve(stream, tx).await {
+                    warn!("WebSocket client {address} disconnected: {e}");
+                }
+            });
+        }
+    }
+
+    async fn handle(&self, event: AzaleaEvent) -> anyhow::Result<()> {
+        match &event {
+            AzaleaEvent::Chat(message, targets) => {
+                self.remember(&message.id, targets);
+                self.broadcast(targets, &ServerMessage::Message(message))
+            }
+            // Only clients that were sent the message can see it change
+            AzaleaEvent::Edit(message, _) => match self.sent_to(&message.id) {
+                Some(targets) => self.broadcast(&targets, &ServerMessage::Edit(message)),
+                None => Ok(()),
+            },
+            AzaleaEvent::Delete(id) => match self.sent_to(id) {
+                Some(targets) => self.broadcast(&targets, &ServerMessage::Delete(id)),
+                None => Ok(()),
+            },
+            AzaleaEvent::Notice(notice, channel) => self.broadcast(
+                &[channel.clone()],
+                &ServerMessage::Notice {
+                    channel,
+                    content: notice,
+                },
+            ),
+        }
+    }
+}
+
+impl WebSocketPlugin {
+    // Remember where a message went, forgetting the oldest once there are too many
+    fn remember(&self, id: &MessageId, targets: &[String]) {
+        let mut sent = self.sent.lock().unwrap();
+        sent.push_back((id.clone(), targets.to_vec()));
+        if sent.len() > SENT_CAPACITY {
+            sent.pop_front();
+        }
+    }
+
+    // The channels a message was sent to, if it was sent recently
+    fn sent_to(&self, id: &MessageId) -> Option<Vec<String>> {
+        let sent = self.sent.lock().unwrap();
+        let (_, targets) = sent.iter().rev().find(|(sent, _)| sent == id)?;
+        Some(targets.clone())
+    }
+
+    // Send a message to the clients in each target channel, or every client if none are given
+    fn broadcast(&self, targets: &[String], message: &ServerMessage) -> anyhow::Result<()> {
+        let json = serde_json::to_string(message)?;
+
+        let clients = self.clients.lock().unwrap();
+        for client in clients.values() {
+            if targets.is_empty() || targets.contains(&client.channel) {
+                // Clients that are leaving are removed by their own task
+                drop(client.tx.send(json.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn serve(&self, stream: TcpStream, tx: Sender<PluginEvent>) -> anyhow::Result<()> {
+        let socket = tokio_tungstenite::accept_async(stream).await?;
+        let (mut write, mut read) = socket.split();
+
+        // Clients have to authenticate first
+        let first = tokio::time::timeout(AUTH_TIMEOUT, read.next())
+            .await
+            .ok()
+            .flatten()
+            .transpose()?;
+        let auth = first.and_then(|frame| match frame {
+            WsMessage::Text(text) => serde_json::from_str(&text).ok(),
+            _ => None,
+        });
+        let Some(ClientMessage::Auth { token, channel }) = auth else {
+            send_error(&mut write, "Expected an auth message").await?;
+            return Ok(());
+        };
+        let Some(client_name) = self.config.tokens.get(&token).cloned() else {
+            send_error(&mut write, "Invalid token").await?;
+            return Ok(());
+        };
+        let channel = match channel {
+            Some(channel) if self.channels.contains(&channel) => channel,
+            None if !self.channels.is_empty() => self.channels[0].clone(),
+            _ => {
+                send_error(&mut write, "Unknown channel").await?;
+                return Ok(());
+            }
+        };
+
+        // Join before saying so, so the client doesn't miss anything sent in between
+        let (out_tx, out_rx) = flume::unbounded::<String>();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.clients.lock().unwrap().insert(
+            id,
+            Connected {
+                channel: channel.clone(),
+                tx: out_tx.clone(),
+            },
+        );
+
+        let ready = serde_json::to_string(&ServerMessage::Ready { channel: &channel })?;
+        if let Err(e) = write.send(WsMessage::Text(ready)).await {
+            self.clients.lock().unwrap().remove(&id);
+            return Err(e.into());
+        }
+        info!("WebSocket client {client_name} joined {channel}");
+
+        // Send events to the client until it disconnects
+        let writer = tokio::spawn(async move {
+            while let Ok(json) = out_rx.recv_async().await {
+                if write.send(WsMessage::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut limiter = RateLimiter::new(self.config.rate_limit);
+        let result = async {
+            while let Some(frame) = read.next().await.transpose()? {
+                let text = match frame {
+                    WsMessage::Text(text) => text,
+                    WsMessage::Close(_) => break,
+                    _ => continue,
+                };
+
+                let (name, content) = match serde_json::from_str(&text) {
+                    Ok(ClientMessage::Chat { name, content }) => (name, content),
+                    Ok(ClientMessage::Auth { .. }) => {
+                        queue_error(&out_tx, "Already authenticated")?;
+                        continue;
+                    }
+                    Err(e) => {
+                        queue_error(&out_tx, &format!("Invalid message: {e}"))?;
+                        continue;
+                    }
+                };
+                if content.trim().is_empty() {
+                    continue;
+                }
+                if !limiter.allow() {
+                    queue_error(&out_tx, "Sending messages too quickly")?;
+                    continue;
+                }
+
+                let message = BridgeMessage::new(
+                    MessageId::new(PLATFORM, Uuid::new_v4()),
+                    channel.clone(),
+                    Author::new(format!("{client_name}/{name}"), name),
+                    content,
+                );
+
+                // Show the message to every client in the channel, including the sender
+                self.broadcast(&[channel.clone()], &ServerMessage::Message(&message))?;
+
+                // Send message to Azalea
+                if let Err(e) = tx.send_async(PluginEvent::Chat(message)).await {
+                    error!("WebSocketPlugin unable to send message to Azalea: {e}");
+                }
+            }
+            anyhow::Ok(())
+        }
+        .await;
+
+        self.clients.lock().unwrap().remove(&id);
+        writer.abort();
+        info!("WebSocket client {client_name} left {channel}");
+        result
+    }
+}
+
+// Tell a client what went wrong before it's authenticated
+async fn send_error(
+    write: &mut futures::stream::SplitSink<WebSocketStream<TcpStream>, WsMessage>,
+    message: &str,
+) -> anyhow::Result<()> {
+    let json = serde_json::to_string(&ServerMessage::Error { message })?;
+    write.send(WsMessage::Text(json)).await?;
+    Ok(())
+}
+
+// Tell a client what went wrong, after the writer has been started
+fn queue_error(tx: &Sender<String>, message: &str) -> anyhow::Result<()> {
+    let json = serde_json::to_string(&ServerMessage::Error { message })?;
+    tx.send(json)?;
+    Ok(())
+}
+
+// Counts the messages sent by a client in the last period
+struct RateLimiter {
+    limit: RateLimit,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            sent: VecDeque::new(),
+        }
+    }
+
+    fn allow(&mut self) -> bool {
+        let now = Instant::now();
+        while let Some(first) = self.sent.front() {
+            if now.duration_since(*first) < self.limit.per {
+                break;
+            }
+            self.sent.pop_front();
+        }
+
+        if self.sent.len() >= self.limit.messages {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GatewayConfig;
+    use serde_json::{json, Value};
+    use tokio_tungstenite::MaybeTlsStream;
+
+    type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    // Start a gateway on a free port with the token `secret`
+    async fn gateway(
+        channels: &[&str],
+        rate_limit: RateLimit,
+    ) -> (WebSocketPlugin, String, flume::Receiver<PluginEvent>) {
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config = GatewayConfig::new(address)
+            .token("secret", "site")
+            .rate_limit(rate_limit);
+        let plugin = WebSocketPlugin {
+            config: Arc::new(config),
+            channels: channels.iter().map(|s| s.to_string()).collect(),
+            clients: Clients::default(),
+            sent: Sent::default(),
+            next_id: Arc::default(),
+        };
+
+        let (tx, rx) = flume::unbounded();
+        let started = plugin.clone();
+        tokio::spawn(async move { started.start(tx).await });
+        (plugin, format!("ws://{address}"), rx)
+    }
+
+    // Connect and send a first message, retrying until the gateway is listening
+    async fn connect(url: &str, first: Value) -> Client {
+        let mut client = loop {
+            match tokio_tungstenite::connect_async(url).await {
+                Ok((client, _)) => break client,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        send(&mut client, first).await;
+        client
+    }
+
+    // Connect and authenticate, returning the channel that was joined
+    async fn join(url: &str, channel: Option<&str>) -> (Client, Value) {
+        let auth = json!({"type": "auth", "token": "secret", "channel": channel});
+        let mut client = connect(url, auth).await;
+        let ready = recv(&mut client).await;
+        (client, ready)
+    }
+
+    async fn send(client: &mut Client, message: Value) {
+        let text = message.to_string();
+        client.send(WsMessage::Text(text)).await.unwrap();
+    }
+
+    async fn recv(client: &mut Client) -> Value {
+        let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .expect("no message from the gateway")
+            .unwrap()
+            .unwrap();
+        serde_json::from_str(frame.to_text().unwrap()).unwrap()
+    }
+
+    fn chat(content: &str) -> Value {
+        json!({"type": "chat", "name": "Alice", "content": content})
+    }
+
+    fn message(id: &str, content: &str) -> BridgeMessage {
+        let id = MessageId::new("discord", id);
+        BridgeMessage::new(
+            id,
+            "1".to_string(),
+            Author::new("1", "Notch"),
+            content.to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_tokens() {
+        let (_, url, _rx) = gateway(&["web"], RateLimit::default()).await;
+
+        let auth = json!({"type": "auth", "token": "wrong"});
+        let mut client = connect(&url, auth).await;
+        assert_eq!(
+            recv(&mut client).await,
+            json!({"type": "error", "message": "Invalid token"})
+        );
+    }
+
+    #[tokio::test]
+    async fn expects_auth_first() {
+        let (_, url, _rx) = gateway(&["web"], RateLimit::default()).await;
+
+        let mut client = connect(&url, chat("hello")).await;
+        assert_eq!(
+            recv(&mut client).await,
+            json!({"type": "error", "message": "Expected an auth message"})
+        );
+    }
+
+    #[tokio::test]
+    async fn joins_the_chosen_channel() {
+        let (plugin, url, _rx) = gateway(&["web", "staff"], RateLimit::default()).await;
+
+        let (mut web, ready) = join(&url, None).await;
+        assert_eq!(ready, json!({"type": "ready", "channel": "web"}));
+        let (mut staff, ready) = join(&url, Some("staff")).await;
+        assert_eq!(ready, json!({"type": "ready", "channel": "staff"}));
+        let (_, ready) = join(&url, Some("admin")).await;
+        assert_eq!(
+            ready,
+            json!({"type": "error", "message": "Unknown channel"})
+        );
+
+        // Each client only sees its own channel
+        let staff_only = message("1", "for staff");
+        let chat = AzaleaEvent::Chat(staff_only, vec!["staff".to_string()]);
+        plugin.handle(chat).await.unwrap();
+        let everyone = message("2", "for everyone");
+        plugin
+            .handle(AzaleaEvent::Chat(everyone, Vec::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(recv(&mut staff).await["content"], "for staff");
+        assert_eq!(recv(&mut staff).await["content"], "for everyone");
+        assert_eq!(recv(&mut web).await["content"], "for everyone");
+    }
+
+    #[tokio::test]
+    async fn edits_go_where_the_original_went() {
+        let (plugin, url, _rx) = gateway(&["web", "staff"], RateLimit::default()).await;
+        let (mut web, _) = join(&url, Some("web")).await;
+        let (mut staff, _) = join(&url, Some("staff")).await;
+
+        let original = message("1", "hello");
+        let chat = AzaleaEvent::Chat(original.clone(), vec!["staff".to_string()]);
+        plugin.handle(chat).await.unwrap();
+        let edited = message("1", "hello again");
+        plugin
+            .handle(AzaleaEvent::Edit(edited, Vec::new()))
+            .await
+            .unwrap();
+        plugin
+            .handle(AzaleaEvent::Delete(original.id))
+            .await
+            .unwrap();
+        // Never sent here, so there's nothing to delete
+        let unknown = MessageId::new("discord", "2");
+        plugin.handle(AzaleaEvent::Delete(unknown)).await.unwrap();
+        let notice = AzaleaEvent::Notice("done".to_string(), "web".to_string());
+        plugin.handle(notice).await.unwrap();
+
+        assert_eq!(recv(&mut staff).await["type"], "message");
+        assert_eq!(recv(&mut staff).await["content"], "hello again");
+        assert_eq!(recv(&mut staff).await["type"], "delete");
+        assert_eq!(recv(&mut web).await["type"], "notice");
+    }
+
+    #[tokio::test]
+    async fn sends_chat_to_azalea() {
+        let (_, url, rx) = gateway(&["web"], RateLimit::default()).await;
+        let (mut client, _) = join(&url, None).await;
+
+        send(&mut client, chat("hello")).await;
+
+        // The sender sees its own message too
+        let echo = recv(&mut client).await;
+        assert_eq!(echo["type"], "message");
+        assert_eq!(echo["content"], "hello");
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv_async())
+            .await
+            .unwrap()
+            .unwrap();
+        let PluginEvent::Chat(message) = event else {
+            panic!("expected chat, got {event:?}");
+        };
+        assert_eq!(message.id.platform, PLATFORM);
+        assert_eq!(message.channel, "web");
+        assert_eq!(message.author.id, "site/Alice");
+        assert_eq!(message.author.name, "Alice");
+        assert_eq!(message.content, "hello");
+    }
+
+    #[tokio::test]
+    async fn limits_chat() {
+        let limit = RateLimit {
+            messages: 2,
+            per: Duration::from_secs(60),
+        };
+        let (_, url, rx) = gateway(&["web"], limit).await;
+        let (mut client, _) = join(&url, None).await;
+
+        for content in ["one", "two", "three"] {
+            send(&mut client, chat(content)).await;
+        }
+
+        assert_eq!(recv(&mut client).await["content"], "one");
+        assert_eq!(recv(&mut client).await["content"], "two");
+        assert_eq!(
+            recv(&mut client).await,
+            json!({"type": "error", "message": "Sending messages too quickly"})
+        );
+        assert_eq!(rx.len(), 2);
+    }
+}
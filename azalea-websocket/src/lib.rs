@@ -0,0 +1,98 @@
+use azalea_bridge::{ClientSide, PlatformBuilder, Route, Routes};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
+
+mod gateway;
+mod protocol;
+
+// The channel clients join if they don't choose one
+pub const DEFAULT_CHANNEL: &str = "web";
+
+/// Where to listen and who can connect.
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    pub address: SocketAddr,
+    // Client names, by the token they authenticate with
+    pub tokens: HashMap<String, String>,
+    pub rate_limit: RateLimit,
+}
+
+impl GatewayConfig {
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            tokens: HashMap::new(),
+            rate_limit: RateLimit::default(),
+        }
+    }
+
+    // Let a client connect with a token
+    pub fn token(mut self, token: &str, name: &str) -> Self {
+        self.tokens.insert(token.to_string(), name.to_string());
+        self
+    }
+
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+}
+
+/// How many chat messages each client can send.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub messages: usize,
+    pub per: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            messages: 5,
+            per: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebSocketPlugin {
+    config: Arc<GatewayConfig>,
+    channels: Vec<String>,
+    clients: gateway::Clients,
+    sent: gateway::Sent,
+    next_id: Arc<AtomicU64>,
+}
+
+impl WebSocketPlugin {
+    /// Starts the gateway, which clients connect to with `ws://address`.
+    pub async fn new(config: GatewayConfig, ignore_list: Vec<&str>) -> ClientSide<WebSocketPlugin> {
+        let routes = Routes::new().with(Route::new(DEFAULT_CHANNEL));
+        Self::routed(config, routes, ignore_list).await
+    }
+
+    /// Like [`WebSocketPlugin::new`], but clients can join the channel of any route.
+    ///
+    /// Clients join the first channel if they don't choose one.
+    pub async fn routed(
+        config: GatewayConfig,
+        routes: Routes,
+        ignore_list: Vec<&str>,
+    ) -> ClientSide<WebSocketPlugin> {
+        let plugin = WebSocketPlugin {
+            config: Arc::new(config),
+            channels: routes.targets(),
+            clients: gateway::Clients::default(),
+            sent: gateway::Sent::default(),
+            next_id: Arc::default(),
+        };
+
+        PlatformBuilder::new(plugin)
+            .ignore_list(ignore_list)
+            .routes(routes)
+            .build()
+    }
+}
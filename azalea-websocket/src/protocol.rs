@@ -0,0 +1,51 @@
+use azalea_bridge::{BridgeMessage, MessageId};
+use serde::{Deserialize, Serialize};
+
+// Sent to clients, see the README for examples
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ServerMessage<'a> {
+    // The client is authenticated and will start receiving events
+    Ready { channel: &'a str },
+    // Chat, joins, leaves and deaths
+    Message(&'a BridgeMessage),
+    Edit(&'a BridgeMessage),
+    Delete(&'a MessageId),
+    Notice { channel: &'a str, content: &'a str },
+    Error { message: &'a str },
+}
+
+// Sent by clients
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ClientMessage {
+    // Has to be the first message
+    Auth {
+        token: String,
+        channel: Option<String>,
+    },
+    // The name is whoever is using the client
+    Chat {
+        name: String,
+        content: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azalea_bridge::{Author, EventKind};
+
+    #[test]
+    fn messages_have_their_kind() {
+        let id = MessageId::new("minecraft", "1");
+        let author = Author::new("1", "Notch");
+        let mut message = BridgeMessage::new(id, String::new(), author, "Notch joined".into());
+        message.kind = EventKind::Join;
+
+        let json = serde_json::to_value(ServerMessage::Message(&message)).unwrap();
+        assert_eq!(json["type"], "message");
+        assert_eq!(json["kind"], "join");
+        assert_eq!(json["content"], "Notch joined");
+    }
+}